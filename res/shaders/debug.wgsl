@@ -0,0 +1,79 @@
+// Vertex shader
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct LineUniform {
+    viewport: vec2<f32>,
+    width: f32,
+    world_space: u32,
+    pixels_per_unit: f32,
+};
+@group(1) @binding(0)
+var<uniform> line: LineUniform;
+
+struct VertexInput {
+    @location(0) start: vec3<f32>,
+    @location(1) end: vec3<f32>,
+    @location(2) corner: vec2<f32>,
+    @location(3) color: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+const NEAR_W: f32 = 0.0001;
+
+// Moves `a` towards `b` until it's in front of the camera so the screen
+// direction of lines crossing the near plane doesn't flip.
+fn clip_near(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    if (a.w < NEAR_W && b.w != a.w) {
+        return mix(a, b, (NEAR_W - a.w) / (b.w - a.w));
+    }
+    return a;
+}
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+    let start_clip = camera.view_proj * vec4<f32>(model.start, 1.0);
+    let end_clip = camera.view_proj * vec4<f32>(model.end, 1.0);
+    let start = clip_near(start_clip, end_clip);
+    let end = clip_near(end_clip, start_clip);
+
+    let half_viewport = line.viewport * 0.5;
+    let delta = (end.xy / end.w - start.xy / start.w) * half_viewport;
+    var dir = vec2<f32>(1.0, 0.0);
+    if (dot(delta, delta) > 0.0) {
+        dir = normalize(delta);
+    }
+    let normal = vec2<f32>(-dir.y, dir.x);
+
+    var clip = select(start, end, model.corner.x > 0.5);
+    var half_width = line.width * 0.5;
+    if (line.world_space != 0u) {
+        half_width = half_width * line.pixels_per_unit / clip.w;
+    }
+    // Offsets are in pixels; scaling by w undoes the perspective divide.
+    clip = vec4<f32>(
+        clip.xy + normal * model.corner.y * half_width / half_viewport * clip.w,
+        clip.zw,
+    );
+
+    var out: VertexOutput;
+    out.color = model.color;
+    out.clip_position = clip;
+    return out;
+}
+
+// Fragment shader
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
@@ -7,10 +7,21 @@ struct CameraUniform {
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
+struct LightUniform {
+    direction: vec3<f32>,
+    smooth_shading: u32,
+    color: vec3<f32>,
+};
+@group(2) @binding(0)
+var<uniform> light: LightUniform;
+
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) tex_idx: i32,
+    @location(3) normal: vec3<f32>,
+    @location(4) smooth_normal: vec3<f32>,
+    @location(9) light: f32,
 };
 
 struct InstanceInput {
@@ -23,7 +34,9 @@ struct InstanceInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-    @location(1) tex_idx: i32,
+    @location(1) @interpolate(flat) tex_idx: i32,
+    @location(2) normal: vec3<f32>,
+    @location(3) light: f32,
 };
 
 @vertex
@@ -40,6 +53,13 @@ fn vs_main(
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
     out.tex_idx = model.tex_idx;
+    out.light = model.light;
+    // Switching shading only flips the uniform, both normals are in the mesh.
+    var normal = model.normal;
+    if (light.smooth_shading != 0u) {
+        normal = model.smooth_normal;
+    }
+    out.normal = (model_matrix * vec4<f32>(normal, 0.0)).xyz;
     out.clip_position = camera.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
     return out;
 }
@@ -53,5 +73,8 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords, in.tex_idx);
+    let color = textureSample(t_diffuse, s_diffuse, in.tex_coords, in.tex_idx);
+    let diffuse = max(dot(normalize(in.normal), light.direction), 0.0);
+    let shade = vec3<f32>(0.4) + 0.6 * diffuse * light.color;
+    return vec4<f32>(color.rgb * shade * in.light, color.a);
 }
\ No newline at end of file
@@ -0,0 +1,253 @@
+// Reproducible benchmark scene: a fixed world rendered along a fixed camera
+// path with a fixed timestep, so frame times can be compared across changes.
+
+use crate::{
+    ecs::World,
+    gfx::render::{camera::Camera, config::RendererConfig, renderer::Renderer},
+};
+use anyhow::{anyhow, bail, Result};
+use cgmath::{Rad, Vector3};
+use instant::{Duration, Instant};
+use std::{f32::consts::TAU, fmt};
+
+pub const BENCH_SEED: u64 = 0x5eed;
+const DEFAULT_FRAMES: usize = 1000;
+// Frames rendered before timing starts, so the initial meshing of every
+// chunk doesn't skew the results.
+const WARMUP_FRAMES: usize = 10;
+const BENCH_DT: Duration = Duration::from_micros(16_667);
+// Chunk columns generated in each direction from the origin.
+const SCENE_RADIUS: i32 = 4;
+const SCENE_HEIGHT: i32 = 2;
+const PATH_RADIUS: f32 = 40.0;
+const PATH_HEIGHT: f32 = 30.0;
+// The window's default size, so offscreen runs draw as many pixels.
+const OFFSCREEN_WIDTH: u32 = 800;
+const OFFSCREEN_HEIGHT: u32 = 450;
+// Far enough that the whole scene is drawn from anywhere on the path.
+pub const RENDER_DISTANCE: u32 = 2 * SCENE_RADIUS as u32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchConfig {
+    pub frames: usize,
+    // Render into an offscreen texture instead of presenting to the window.
+    pub offscreen: bool,
+}
+
+impl BenchConfig {
+    // Returns `None` unless `--bench-scene` was passed, without looking at
+    // the other arguments, so they're left to whatever launched the game.
+    // With it, also accepts `--frames <N>` and `--offscreen`, and nothing
+    // else.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let args = args.into_iter().collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--bench-scene") {
+            return Ok(None);
+        }
+        let mut config = Self {
+            frames: DEFAULT_FRAMES,
+            offscreen: false,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bench-scene" => {}
+                "--offscreen" => config.offscreen = true,
+                "--frames" => {
+                    let frames = args
+                        .next()
+                        .ok_or_else(|| anyhow!("--frames needs a value"))?;
+                    config.frames = frames.parse()?;
+                }
+                _ => bail!("unknown argument {:?}", arg),
+            }
+        }
+        Ok(Some(config))
+    }
+}
+
+// Runs the whole benchmark on a renderer without a window, rendering every
+// frame into an offscreen texture.
+pub async fn run_offscreen(
+    config: BenchConfig,
+    renderer_config: RendererConfig,
+) -> Result<BenchReport> {
+    let mut renderer =
+        Renderer::headless(renderer_config, OFFSCREEN_WIDTH, OFFSCREEN_HEIGHT).await?;
+    renderer.set_render_distance(RENDER_DISTANCE);
+    populate(&mut renderer.world);
+    let mut scene = BenchScene::new(BenchConfig {
+        offscreen: true,
+        ..config
+    });
+    while !scene.is_done() {
+        scene.frame(&mut renderer)?;
+    }
+    Ok(scene.report())
+}
+
+// Fills `world` with the same terrain every run.
+pub fn populate(world: &mut World) {
+    world.reset(BENCH_SEED);
+    world.config.stream_radius = 0;
+    for y in 0..SCENE_HEIGHT {
+        for z in -SCENE_RADIUS..SCENE_RADIUS {
+            for x in -SCENE_RADIUS..SCENE_RADIUS {
+                world.generate_chunk(Vector3::new(x, y, z));
+            }
+        }
+    }
+}
+
+// Camera orbiting the scene once over the course of the benchmark, looking
+// slightly down at its center.
+pub fn camera_at(frame: usize, frames: usize) -> Camera {
+    let angle = frame as f32 / frames.max(1) as f32 * TAU;
+    let (sin, cos) = angle.sin_cos();
+    let position = (PATH_RADIUS * sin, PATH_HEIGHT, PATH_RADIUS * cos);
+    // Yaw 0 looks down -z, so face back towards the origin.
+    Camera::new(position, Rad(-angle), Rad(-0.4))
+}
+
+pub struct BenchScene {
+    config: BenchConfig,
+    frame: usize,
+    frame_times: Vec<Duration>,
+}
+
+impl BenchScene {
+    pub fn new(config: BenchConfig) -> Self {
+        Self {
+            frame_times: Vec::with_capacity(config.frames),
+            config,
+            frame: 0,
+        }
+    }
+
+    pub fn config(&self) -> &BenchConfig {
+        &self.config
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.frame_times.len() >= self.config.frames
+    }
+
+    pub fn frame(&mut self, renderer: &mut Renderer) -> Result<()> {
+        let path_frame = self.frame.saturating_sub(WARMUP_FRAMES);
+        renderer.camera = camera_at(path_frame, self.config.frames);
+        renderer.world.player.set_eye(renderer.camera.position);
+
+        let start = Instant::now();
+        renderer.update(BENCH_DT);
+        if self.config.offscreen {
+            let size = renderer.size;
+            renderer.render_to_texture(&renderer.camera, size.width, size.height)?;
+        } else {
+            renderer.render()?;
+        }
+        let elapsed = start.elapsed();
+        // Meshes are built in the background, so make sure every chunk is
+        // meshed before timing starts.
+        if self.frame + 1 == WARMUP_FRAMES {
+            while renderer.mesh_jobs.pending_count() > 0 {
+                #[cfg(not(target_arch = "wasm32"))]
+                std::thread::sleep(Duration::from_millis(1));
+                renderer.update(Duration::ZERO);
+            }
+        }
+
+        if self.frame >= WARMUP_FRAMES {
+            self.frame_times.push(elapsed);
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    pub fn report(&self) -> BenchReport {
+        BenchReport::new(&self.frame_times)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchReport {
+    pub frames: usize,
+    pub average: Duration,
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl BenchReport {
+    pub fn new(frame_times: &[Duration]) -> Self {
+        let mut sorted = frame_times.to_vec();
+        sorted.sort();
+        let percentile = |p: f32| {
+            if sorted.is_empty() {
+                return Duration::ZERO;
+            }
+            let i = (p * (sorted.len() - 1) as f32).round() as usize;
+            sorted[i]
+        };
+        let total: Duration = sorted.iter().sum();
+        Self {
+            frames: sorted.len(),
+            average: total / sorted.len().max(1) as u32,
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{} frames: avg {:.3}ms, p50 {:.3}ms, p95 {:.3}ms, p99 {:.3}ms, max {:.3}ms",
+            self.frames,
+            ms(self.average),
+            ms(self.p50),
+            ms(self.p95),
+            ms(self.p99),
+            ms(self.max)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args<'a>(args: &'a [&'a str]) -> impl Iterator<Item = String> + 'a {
+        args.iter().map(|arg| arg.to_string())
+    }
+
+    #[test]
+    fn other_arguments_are_ignored_without_bench_scene() {
+        assert_eq!(BenchConfig::from_args(args(&[])).unwrap(), None);
+        assert_eq!(
+            BenchConfig::from_args(args(&["--fullscreen", "-psn_0_1234"])).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn bench_scene_arguments_are_parsed_strictly() {
+        let config =
+            BenchConfig::from_args(args(&["--bench-scene", "--frames", "5", "--offscreen"]))
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            config,
+            BenchConfig {
+                frames: 5,
+                offscreen: true
+            }
+        );
+        assert!(BenchConfig::from_args(args(&["--bench-scene", "--fullscreen"])).is_err());
+        assert!(BenchConfig::from_args(args(&["--bench-scene", "--frames"])).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use super::{
+    chunk::{Chunk, CHUNK_SIZE},
+    rng::WorldRng,
+    voxel::{Voxel, VoxelType},
+};
+use cgmath::Vector3;
+
+// Keeps the cave noise apart from the terrain generator's other streams.
+const CAVE_KEY: u64 = 2;
+
+// Hollows caves out of generated terrain wherever 3D value noise rises above
+// a threshold. The noise is sampled in absolute voxel coordinates from the
+// world's seed, so caves carry on seamlessly across chunk borders and come
+// out the same every time.
+#[derive(Debug, Clone)]
+pub struct CaveCarver {
+    // Noise features per voxel. Smaller values give larger caves.
+    pub frequency: f32,
+    // Noise above this is carved, from 0 to 1. Higher values give fewer,
+    // smaller caves.
+    pub threshold: f32,
+    // The surface voxel is only carved through, opening a cave mouth, where
+    // the noise is above this. At 1 or more the surface is never broken.
+    pub mouth_threshold: f32,
+}
+
+impl Default for CaveCarver {
+    fn default() -> Self {
+        Self {
+            frequency: 1.0 / 16.0,
+            threshold: 0.7,
+            mouth_threshold: 0.9,
+        }
+    }
+}
+
+impl CaveCarver {
+    pub fn noise(&self, seed: u64, position: Vector3<i32>) -> f32 {
+        value_noise(seed, position.cast::<f32>().unwrap() * self.frequency)
+    }
+
+    // Carves the chunk, given the terrain's surface height for each column.
+    // Nothing above the surface is touched, so whatever grows on a cave
+    // mouth has to be left out with `carves_surface`.
+    pub fn carve(&self, seed: u64, chunk: &mut Chunk, height_at: impl Fn(i32, i32) -> i32) {
+        let origin = chunk.position() * CHUNK_SIZE as i32;
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+                let height = height_at(world_x, world_z);
+                let top = (height - origin.y).min(CHUNK_SIZE as i32 - 1);
+                for y in 0..=top {
+                    let world_y = origin.y + y;
+                    let noise = self.noise(seed, Vector3::new(world_x, world_y, world_z));
+                    let threshold = if world_y == height {
+                        self.mouth_threshold
+                    } else {
+                        self.threshold
+                    };
+                    if noise <= threshold {
+                        continue;
+                    }
+                    chunk.set(x, y as usize, z, Voxel::new(VoxelType::Air));
+                }
+            }
+        }
+    }
+
+    // Whether the surface voxel at `height` is carved into a cave mouth.
+    pub fn carves_surface(&self, seed: u64, x: i32, height: i32, z: i32) -> bool {
+        self.noise(seed, Vector3::new(x, height, z)) > self.mouth_threshold
+    }
+}
+
+// Pseudo-random value in [0, 1) for an integer lattice point.
+fn lattice(seed: u64, position: Vector3<i32>) -> f32 {
+    WorldRng::new(seed)
+        .fork(CAVE_KEY)
+        .at(position, 0)
+        .next_f32()
+}
+
+// Smoothly interpolated lattice values, in [0, 1).
+fn value_noise(seed: u64, p: Vector3<f32>) -> f32 {
+    let cell = p.map(f32::floor);
+    let t = (p - cell).map(smoothstep);
+    let cell = cell.cast::<i32>().unwrap();
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let corner = |dx, dy, dz| lattice(seed, cell + Vector3::new(dx, dy, dz));
+    let face = |dz| {
+        let bottom = lerp(corner(0, 0, dz), corner(1, 0, dz), t.x);
+        let top = lerp(corner(0, 1, dz), corner(1, 1, dz), t.x);
+        lerp(bottom, top, t.y)
+    };
+    lerp(face(0), face(1), t.z)
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
@@ -1,5 +1,221 @@
-use super::voxel::Voxel;
+use super::{
+    hash::Fnv1a,
+    voxel::{Voxel, VoxelType},
+};
+use crate::gfx::mesh::{ChunkMesh, Face, GreedyMesher};
+use cgmath::Vector3;
 
+pub const CHUNK_SIZE: usize = 16;
+
+// A chunk's position on the chunk grid, in chunks rather than voxels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ChunkCoord(pub i32, pub i32, pub i32);
+
+impl ChunkCoord {
+    // The chunk the voxel at `pos` falls in.
+    pub fn containing(pos: Vector3<i32>) -> Self {
+        let size = CHUNK_SIZE as i32;
+        pos.map(|v| v.div_euclid(size)).into()
+    }
+}
+
+impl From<Vector3<i32>> for ChunkCoord {
+    fn from(v: Vector3<i32>) -> Self {
+        Self(v.x, v.y, v.z)
+    }
+}
+
+impl From<ChunkCoord> for Vector3<i32> {
+    fn from(ChunkCoord(x, y, z): ChunkCoord) -> Self {
+        Vector3::new(x, y, z)
+    }
+}
+
+#[derive(Clone)]
 pub struct Chunk {
+    position: Vector3<i32>,
     voxels: Vec<Voxel>,
+    modified: bool,
+}
+
+impl Chunk {
+    pub fn new(position: Vector3<i32>) -> Self {
+        Self {
+            position,
+            voxels: vec![Voxel::default(); CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+            modified: false,
+        }
+    }
+
+    // Position of the chunk on the chunk grid, not in voxels.
+    pub fn position(&self) -> Vector3<i32> {
+        self.position
+    }
+
+    fn index(x: usize, y: usize, z: usize) -> usize {
+        (y * CHUNK_SIZE + z) * CHUNK_SIZE + x
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Voxel {
+        self.voxels[Self::index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, voxel: Voxel) {
+        self.voxels[Self::index(x, y, z)] = voxel;
+        self.modified = true;
+    }
+
+    // Whether every voxel is air.
+    pub fn is_empty(&self) -> bool {
+        self.voxels
+            .iter()
+            .all(|voxel| voxel.voxel_type() == VoxelType::Air)
+    }
+
+    // Whether the chunk changed since it was created or last saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.modified = false;
+    }
+
+    // Greedy meshes the chunk given the chunks touching each of its faces,
+    // in `Face::ALL` order, so faces between two solid voxels on either side
+    // of a border are culled. Missing neighbors read as air. Lighting depends
+    // on more than the face neighbors, so the mesh is unlit. Meshing it with
+    // `World::neighborhood` instead accounts for that too.
+    pub fn build_mesh(&self, neighbors: [Option<&Chunk>; 6]) -> ChunkMesh {
+        GreedyMesher.mesh_unlit(&ChunkNeighborhood::from_faces(self, neighbors))
+    }
+
+    // Like `build_mesh` at a lower level of detail, merging `1 << level`
+    // voxels a side into each cell. See `GreedyMesher::mesh_lod`.
+    pub fn build_mesh_lod(&self, neighbors: [Option<&Chunk>; 6], level: u32) -> ChunkMesh {
+        GreedyMesher.mesh_lod_unlit(&ChunkNeighborhood::from_faces(self, neighbors), level)
+    }
+
+    // Stable hash of the chunk's position and voxel contents.
+    pub fn hash(&self) -> u64 {
+        let mut hasher = Fnv1a::new();
+        hasher.write_i32(self.position.x);
+        hasher.write_i32(self.position.y);
+        hasher.write_i32(self.position.z);
+        for voxel in &self.voxels {
+            hasher.write_u16(voxel.voxel_type() as u16);
+        }
+        hasher.finish()
+    }
+}
+
+// A chunk together with the 26 chunks surrounding it, so meshers can look
+// across chunk borders. Missing neighbors read as air.
+pub struct ChunkNeighborhood<'a> {
+    chunks: [Option<&'a Chunk>; 27],
+}
+
+impl<'a> ChunkNeighborhood<'a> {
+    // `chunks` is indexed by `(dx + 1) + (dy + 1) * 3 + (dz + 1) * 9` for
+    // offsets in -1..=1, so index 13 is the center chunk.
+    pub fn new(center: &'a Chunk, mut chunks: [Option<&'a Chunk>; 27]) -> Self {
+        chunks[13] = Some(center);
+        Self { chunks }
+    }
+
+    // Only the chunks touching the center's faces, in `Face::ALL` order. The
+    // edge and corner neighbors are left out and read as air.
+    pub fn from_faces(center: &'a Chunk, neighbors: [Option<&'a Chunk>; 6]) -> Self {
+        let mut chunks = [None; 27];
+        for (face, neighbor) in Face::ALL.into_iter().zip(neighbors) {
+            let [dx, dy, dz] = face.normal();
+            chunks[((dx + 1) + (dy + 1) * 3 + (dz + 1) * 9) as usize] = neighbor;
+        }
+        Self::new(center, chunks)
+    }
+
+    pub fn chunks(&self) -> [Option<&'a Chunk>; 27] {
+        self.chunks
+    }
+
+    pub fn center(&self) -> &'a Chunk {
+        self.chunks[13].unwrap()
+    }
+
+    // Looks up a voxel relative to the center chunk's origin. Coordinates may
+    // reach up to one chunk outside of it in every direction.
+    pub fn get(&self, x: i32, y: i32, z: i32) -> Voxel {
+        let size = CHUNK_SIZE as i32;
+        let (cx, cy, cz) = (x.div_euclid(size), y.div_euclid(size), z.div_euclid(size));
+        if !(-1..=1).contains(&cx) || !(-1..=1).contains(&cy) || !(-1..=1).contains(&cz) {
+            return Voxel::default();
+        }
+        let index = ((cx + 1) + (cy + 1) * 3 + (cz + 1) * 9) as usize;
+        self.chunks[index].map_or_else(Voxel::default, |chunk| {
+            chunk.get(
+                x.rem_euclid(size) as usize,
+                y.rem_euclid(size) as usize,
+                z.rem_euclid(size) as usize,
+            )
+        })
+    }
+
+    pub fn voxel_type(&self, x: i32, y: i32, z: i32) -> VoxelType {
+        self.get(x, y, z).voxel_type()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid_chunk(position: Vector3<i32>) -> Chunk {
+        let mut chunk = Chunk::new(position);
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn solid_chunk_meshes_to_six_quads() {
+        let mesh = solid_chunk(Vector3::new(0, 0, 0)).build_mesh([None; 6]);
+        assert_eq!(mesh.vertices.len(), 6 * 4);
+        assert_eq!(mesh.indices.len(), 6 * 6);
+        assert!(mesh.transparent_indices.is_empty());
+    }
+
+    // Vertices of faces pointing along `normal` on the plane at local `x`.
+    fn vertices_on_x_plane(mesh: &ChunkMesh, x: f32, normal: [f32; 3]) -> usize {
+        mesh.vertices
+            .iter()
+            .filter(|v| v.position()[0] == x && v.normal() == normal)
+            .count()
+    }
+
+    #[test]
+    fn no_faces_between_adjacent_solid_chunks() {
+        let size = CHUNK_SIZE as f32;
+        let left = solid_chunk(Vector3::new(0, 0, 0));
+        let right = solid_chunk(Vector3::new(1, 0, 0));
+        let mut neighbors = [None; 6];
+        neighbors[Face::ALL.iter().position(|&f| f == Face::PosX).unwrap()] = Some(&right);
+        let mesh = left.build_mesh(neighbors);
+        assert_eq!(vertices_on_x_plane(&mesh, size, [1.0, 0.0, 0.0]), 0);
+        // The open faces are all still there.
+        assert_eq!(mesh.vertices.len(), 5 * 4);
+
+        let mut neighbors = [None; 6];
+        neighbors[Face::ALL.iter().position(|&f| f == Face::NegX).unwrap()] = Some(&left);
+        let mesh = right.build_mesh(neighbors);
+        assert_eq!(vertices_on_x_plane(&mesh, 0.0, [-1.0, 0.0, 0.0]), 0);
+
+        // A neighbor that isn't loaded reads as air, so the face stays.
+        let mesh = left.build_mesh([None; 6]);
+        assert_eq!(vertices_on_x_plane(&mesh, size, [1.0, 0.0, 0.0]), 4);
+    }
 }
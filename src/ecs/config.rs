@@ -0,0 +1,55 @@
+#[derive(Debug, Clone)]
+pub struct WorldConfig {
+    // Upper bound on the number of chunks kept in memory. Once exceeded, the
+    // chunks farthest from the player are evicted.
+    pub max_loaded_chunks: usize,
+    // How many modified chunks that were evicted stay in memory. Past this,
+    // the oldest are written to disk until they're loaded again.
+    pub max_evicted_chunks_in_memory: usize,
+    // Mesh each column of stacked chunks as a whole, letting meshers that
+    // support it merge faces across chunk boundaries.
+    pub column_meshing: bool,
+    // How far the player can get from the floating origin before it's moved
+    // to them, in voxels.
+    pub origin_recenter_distance: f32,
+    // How far away the player can break and place voxels.
+    pub reach: f32,
+    // Chunks are generated in columns up to this many chunks away from the
+    // player's, and `stream_height` chunks above and below it. 0 turns
+    // streaming off.
+    pub stream_radius: i32,
+    pub stream_height: i32,
+    // Streamed chunks are unloaded once their column is this many chunks
+    // away, or they're as much farther than `stream_height` above or below.
+    // Keeping it past `stream_radius` means walking back and forth across
+    // the edge doesn't unload and regenerate the same chunks over and over.
+    pub unload_radius: i32,
+    // The lowest and highest voxel layers, inclusive. The lowest is always
+    // bedrock, nothing is generated outside them and nothing can be placed
+    // outside them.
+    pub min_y: i32,
+    pub max_y: i32,
+}
+
+impl Default for WorldConfig {
+    fn default() -> Self {
+        Self {
+            max_loaded_chunks: 4096,
+            max_evicted_chunks_in_memory: 256,
+            column_meshing: false,
+            origin_recenter_distance: 512.0,
+            reach: 5.0,
+            stream_radius: 5,
+            stream_height: 1,
+            unload_radius: 7,
+            min_y: -64,
+            max_y: 255,
+        }
+    }
+}
+
+impl WorldConfig {
+    pub fn in_height_limits(&self, y: i32) -> bool {
+        (self.min_y..=self.max_y).contains(&y)
+    }
+}
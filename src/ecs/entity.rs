@@ -1,6 +1,237 @@
-use crate::ecs::chunk::Chunk;
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
 
-pub enum Entity {
-    Voxel(u64),
-    Chunk(Chunk),
+// A handle to an entity in `Entities`. Slots are reused, but each reuse bumps
+// the slot's generation, so a stale id never matches a newer entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+// The components of one type, erased so `Entities` can keep every type
+// together and still drop a despawned entity's components.
+trait Column {
+    fn remove(&mut self, id: EntityId);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<C: 'static> Column for HashMap<EntityId, C> {
+    fn remove(&mut self, id: EntityId) {
+        HashMap::remove(self, &id);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// Everything in the world besides its chunks, which `World` keeps by
+// coordinate instead. An entity is only an id; what it is comes from the
+// components attached to it, one column per component type.
+#[derive(Default)]
+pub struct Entities {
+    // The current generation of each slot, and whether it's in use.
+    slots: Vec<(u32, bool)>,
+    free: Vec<u32>,
+    columns: HashMap<TypeId, Box<dyn Column>>,
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> EntityId {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push((0, false));
+                self.slots.len() as u32 - 1
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.1 = true;
+        EntityId {
+            index,
+            generation: slot.0,
+        }
+    }
+
+    // Removes the entity and all its components. Returns whether it existed.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        for column in self.columns.values_mut() {
+            column.remove(id);
+        }
+        let slot = &mut self.slots[id.index as usize];
+        *slot = (slot.0.wrapping_add(1), false);
+        self.free.push(id.index);
+        true
+    }
+
+    pub fn is_alive(&self, id: EntityId) -> bool {
+        self.slots.get(id.index as usize) == Some(&(id.generation, true))
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Despawns everything. Ids handed out before stay invalid.
+    pub fn clear(&mut self) {
+        for index in 0..self.slots.len() as u32 {
+            let generation = self.slots[index as usize].0;
+            self.despawn(EntityId { index, generation });
+        }
+        self.columns.clear();
+    }
+
+    fn column<C: 'static>(&self) -> Option<&HashMap<EntityId, C>> {
+        self.columns
+            .get(&TypeId::of::<C>())?
+            .as_any()
+            .downcast_ref()
+    }
+
+    fn column_mut<C: 'static>(&mut self) -> Option<&mut HashMap<EntityId, C>> {
+        self.columns
+            .get_mut(&TypeId::of::<C>())?
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    // Attaches `component` to the entity, returning the one it replaced.
+    // Does nothing if the entity was despawned.
+    pub fn insert<C: 'static>(&mut self, id: EntityId, component: C) -> Option<C> {
+        if !self.is_alive(id) {
+            return None;
+        }
+        self.columns
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(HashMap::<EntityId, C>::new()));
+        self.column_mut::<C>()?.insert(id, component)
+    }
+
+    pub fn remove<C: 'static>(&mut self, id: EntityId) -> Option<C> {
+        self.column_mut::<C>()?.remove(&id)
+    }
+
+    pub fn get<C: 'static>(&self, id: EntityId) -> Option<&C> {
+        self.column::<C>()?.get(&id)
+    }
+
+    pub fn get_mut<C: 'static>(&mut self, id: EntityId) -> Option<&mut C> {
+        self.column_mut::<C>()?.get_mut(&id)
+    }
+
+    // Every entity with a `C`, in no particular order.
+    pub fn iter<C: 'static>(&self) -> impl Iterator<Item = (EntityId, &C)> {
+        self.column::<C>()
+            .into_iter()
+            .flat_map(|column| column.iter().map(|(&id, c)| (id, c)))
+    }
+
+    // Every entity with all the components in `Q`, a tuple of component
+    // types like `(DroppedItem, Transform)`, in no particular order.
+    pub fn query<Q: Query>(&self) -> impl Iterator<Item = (EntityId, Q::Item<'_>)> {
+        Q::candidates(self).filter_map(move |id| Some((id, Q::fetch(self, id)?)))
+    }
+}
+
+// A set of component types to look up together with `Entities::query`.
+pub trait Query {
+    type Item<'a>;
+
+    // Entities that might match, i.e. those with the first component.
+    fn candidates(entities: &Entities) -> Box<dyn Iterator<Item = EntityId> + '_>;
+
+    fn fetch(entities: &Entities, id: EntityId) -> Option<Self::Item<'_>>;
+}
+
+macro_rules! impl_query {
+    ($first:ident $(, $rest:ident)*) => {
+        impl<$first: 'static $(, $rest: 'static)*> Query for ($first, $($rest,)*) {
+            type Item<'a> = (&'a $first, $(&'a $rest,)*);
+
+            fn candidates(entities: &Entities) -> Box<dyn Iterator<Item = EntityId> + '_> {
+                Box::new(entities.iter::<$first>().map(|(id, _)| id))
+            }
+
+            fn fetch(entities: &Entities, id: EntityId) -> Option<Self::Item<'_>> {
+                Some((entities.get::<$first>(id)?, $(entities.get::<$rest>(id)?,)*))
+            }
+        }
+    };
+}
+
+impl_query!(A);
+impl_query!(A, B);
+impl_query!(A, B, C);
+impl_query!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(i32);
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    #[test]
+    fn query_finds_entities_with_every_component() {
+        let mut entities = Entities::new();
+        let both = entities.spawn();
+        let position_only = entities.spawn();
+        let health_only = entities.spawn();
+        entities.insert(both, Position(1));
+        entities.insert(both, Health(10));
+        entities.insert(position_only, Position(2));
+        entities.insert(health_only, Health(20));
+        assert_eq!(entities.len(), 3);
+
+        let found: Vec<_> = entities.query::<(Position, Health)>().collect();
+        assert_eq!(found, [(both, (&Position(1), &Health(10)))]);
+        let mut positions: Vec<_> = entities
+            .query::<(Position,)>()
+            .map(|(_, (p,))| p.0)
+            .collect();
+        positions.sort();
+        assert_eq!(positions, [1, 2]);
+
+        assert_eq!(entities.insert(both, Health(5)), Some(Health(10)));
+        assert_eq!(entities.get::<Health>(both), Some(&Health(5)));
+        assert_eq!(entities.remove::<Position>(both), Some(Position(1)));
+        assert_eq!(entities.query::<(Position, Health)>().count(), 0);
+    }
+
+    #[test]
+    fn despawned_ids_stay_dead_when_slots_are_reused() {
+        let mut entities = Entities::new();
+        let old = entities.spawn();
+        entities.insert(old, Position(1));
+        assert!(entities.despawn(old));
+        assert!(!entities.despawn(old));
+
+        let new = entities.spawn();
+        assert_ne!(new, old);
+        assert!(!entities.is_alive(old));
+        assert_eq!(entities.get::<Position>(new), None);
+        assert_eq!(entities.insert(old, Position(2)), None);
+        assert_eq!(entities.iter::<Position>().count(), 0);
+    }
 }
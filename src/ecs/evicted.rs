@@ -0,0 +1,178 @@
+use super::chunk::Chunk;
+use anyhow::Result;
+use cgmath::Vector3;
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// Modified chunks that were evicted, kept so their edits survive until
+// they're loaded again. Only the most recently evicted stay in memory; the
+// rest are written to a scratch directory, one file per chunk, so edits
+// don't hold on to memory after their chunks were unloaded to free it. On
+// the web, where there's no filesystem, they all stay in memory.
+#[derive(Default)]
+pub struct EvictedChunks {
+    memory: HashMap<Vector3<i32>, Chunk>,
+    // Positions in `memory`, oldest first, which is the order they spill in.
+    order: VecDeque<Vector3<i32>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    disk: HashSet<Vector3<i32>>,
+    // Created on the first spill.
+    #[cfg(not(target_arch = "wasm32"))]
+    dir: Option<PathBuf>,
+}
+
+impl EvictedChunks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.in_memory() + self.on_disk()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn in_memory(&self) -> usize {
+        self.memory.len()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn on_disk(&self) -> usize {
+        self.disk.len()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn on_disk(&self) -> usize {
+        0
+    }
+
+    // Adds `chunk`, replacing any chunk evicted at the same position, then
+    // spills the oldest chunks until at most `memory_limit` are in memory.
+    // Chunks that fail to spill stay in memory, so nothing is lost.
+    pub fn insert(&mut self, chunk: Chunk, memory_limit: usize) {
+        let position = chunk.position();
+        self.forget(position);
+        self.memory.insert(position, chunk);
+        self.order.push_back(position);
+        #[cfg(not(target_arch = "wasm32"))]
+        while self.memory.len() > memory_limit {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Err(err) = self.spill(oldest) {
+                log::warn!("keeping evicted chunk {:?} in memory: {}", oldest, err);
+                self.order.push_front(oldest);
+                break;
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = memory_limit;
+    }
+
+    // Takes the chunk evicted at `position` back, from memory or disk.
+    pub fn remove(&mut self, position: Vector3<i32>) -> Result<Option<Chunk>> {
+        if let Some(chunk) = self.memory.remove(&position) {
+            self.order.retain(|&p| p != position);
+            return Ok(Some(chunk));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.disk.contains(&position) {
+            let path = self.path(position);
+            let chunk = Chunk::deserialize(&fs::read(&path)?)?;
+            self.disk.remove(&position);
+            fs::remove_file(path).ok();
+            return Ok(Some(chunk));
+        }
+        Ok(None)
+    }
+
+    // Every chunk as written by `Chunk::serialize`, for saving the world.
+    // Spilled chunks are already stored that way, so they're read as is.
+    pub fn serialized(&self) -> Result<Vec<Vec<u8>>> {
+        let mut chunks = self
+            .memory
+            .values()
+            .map(Chunk::serialize)
+            .collect::<Vec<_>>();
+        #[cfg(not(target_arch = "wasm32"))]
+        for &position in &self.disk {
+            chunks.push(fs::read(self.path(position))?);
+        }
+        Ok(chunks)
+    }
+
+    pub fn clear(&mut self) {
+        self.memory.clear();
+        self.order.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        for position in std::mem::take(&mut self.disk) {
+            fs::remove_file(self.path(position)).ok();
+        }
+    }
+
+    fn forget(&mut self, position: Vector3<i32>) {
+        if self.memory.remove(&position).is_some() {
+            self.order.retain(|&p| p != position);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.disk.remove(&position) {
+            fs::remove_file(self.path(position)).ok();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spill(&mut self, position: Vector3<i32>) -> Result<()> {
+        let dir = match &self.dir {
+            Some(dir) => dir,
+            None => {
+                // One directory per world, since several can be alive at
+                // once, e.g. while one is being loaded to replace another.
+                static NEXT: AtomicUsize = AtomicUsize::new(0);
+                let name = format!(
+                    "voxl-evicted-{}-{}",
+                    std::process::id(),
+                    NEXT.fetch_add(1, Ordering::Relaxed)
+                );
+                let dir = std::env::temp_dir().join(name);
+                fs::create_dir_all(&dir)?;
+                self.dir.insert(dir)
+            }
+        };
+        let path = dir.join(Self::file_name(position));
+        fs::write(path, self.memory[&position].serialize())?;
+        self.memory.remove(&position);
+        self.disk.insert(position);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_name(position: Vector3<i32>) -> String {
+        format!("{}_{}_{}.chunk", position.x, position.y, position.z)
+    }
+
+    // Only called for spilled chunks, which means the directory exists.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn path(&self, position: Vector3<i32>) -> PathBuf {
+        let dir = self.dir.as_ref().expect("spilled chunks have a directory");
+        dir.join(Self::file_name(position))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for EvictedChunks {
+    fn drop(&mut self) {
+        self.clear();
+        if let Some(dir) = &self.dir {
+            fs::remove_dir(dir).ok();
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::ecs::voxel::VoxelType;
+
+pub const INVENTORY_SIZE: usize = 36;
+// The first `HOTBAR_SIZE` slots of the inventory make up the hotbar.
+pub const HOTBAR_SIZE: usize = 9;
+pub const MAX_STACK_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+    pub voxel_type: VoxelType,
+    pub count: u32,
+}
+
+#[derive(Debug)]
+pub struct Inventory {
+    slots: [Option<ItemStack>; INVENTORY_SIZE],
+    selected_slot: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            slots: [None; INVENTORY_SIZE],
+            selected_slot: 0,
+        }
+    }
+
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..HOTBAR_SIZE]
+    }
+
+    // Adds `count` items, topping up existing stacks before starting new ones.
+    // Returns how many didn't fit.
+    pub fn add(&mut self, voxel_type: VoxelType, mut count: u32) -> u32 {
+        for stack in self.slots.iter_mut().flatten() {
+            if count == 0 {
+                break;
+            }
+            if stack.voxel_type == voxel_type && stack.count < MAX_STACK_SIZE {
+                let moved = count.min(MAX_STACK_SIZE - stack.count);
+                stack.count += moved;
+                count -= moved;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|s| s.is_none()) {
+            if count == 0 {
+                break;
+            }
+            let moved = count.min(MAX_STACK_SIZE);
+            *slot = Some(ItemStack {
+                voxel_type,
+                count: moved,
+            });
+            count -= moved;
+        }
+        count
+    }
+
+    // Removes up to `count` items, taking from the last stacks first so the
+    // hotbar is drained last. Returns how many were removed.
+    pub fn remove(&mut self, voxel_type: VoxelType, count: u32) -> u32 {
+        let mut removed = 0;
+        for slot in self.slots.iter_mut().rev() {
+            if removed == count {
+                break;
+            }
+            if let Some(stack) = slot {
+                if stack.voxel_type == voxel_type {
+                    let taken = (count - removed).min(stack.count);
+                    stack.count -= taken;
+                    removed += taken;
+                    if stack.count == 0 {
+                        *slot = None;
+                    }
+                }
+            }
+        }
+        removed
+    }
+
+    pub fn count(&self, voxel_type: VoxelType) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|s| s.voxel_type == voxel_type)
+            .map(|s| s.count)
+            .sum()
+    }
+
+    pub fn selected_slot(&self) -> usize {
+        self.selected_slot
+    }
+
+    pub fn select_slot(&mut self, slot: usize) {
+        self.selected_slot = slot.min(HOTBAR_SIZE - 1);
+    }
+
+    pub fn selected(&self) -> Option<ItemStack> {
+        self.slots[self.selected_slot]
+    }
+
+    // Takes a single item from the selected hotbar slot, e.g. to place it.
+    pub fn take_selected(&mut self) -> Option<VoxelType> {
+        let slot = &mut self.slots[self.selected_slot];
+        let stack = slot.as_mut()?;
+        let voxel_type = stack.voxel_type;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(voxel_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(voxel_type: VoxelType, count: u32) -> Option<ItemStack> {
+        Some(ItemStack { voxel_type, count })
+    }
+
+    #[test]
+    fn adding_past_a_full_stack_starts_a_new_one() {
+        let mut inventory = Inventory::new();
+        assert_eq!(inventory.add(VoxelType::Stone, MAX_STACK_SIZE + 10), 0);
+        assert_eq!(
+            inventory.slots()[0],
+            stack(VoxelType::Stone, MAX_STACK_SIZE)
+        );
+        assert_eq!(inventory.slots()[1], stack(VoxelType::Stone, 10));
+        assert_eq!(inventory.count(VoxelType::Stone), MAX_STACK_SIZE + 10);
+    }
+
+    #[test]
+    fn adding_tops_up_existing_stacks_first() {
+        let mut inventory = Inventory::new();
+        inventory.add(VoxelType::Stone, 60);
+        inventory.add(VoxelType::Dirt, 1);
+        inventory.add(VoxelType::Stone, 10);
+        assert_eq!(
+            inventory.slots()[0],
+            stack(VoxelType::Stone, MAX_STACK_SIZE)
+        );
+        assert_eq!(inventory.slots()[1], stack(VoxelType::Dirt, 1));
+        assert_eq!(inventory.slots()[2], stack(VoxelType::Stone, 6));
+    }
+
+    #[test]
+    fn a_full_inventory_returns_what_didnt_fit() {
+        let mut inventory = Inventory::new();
+        let capacity = INVENTORY_SIZE as u32 * MAX_STACK_SIZE;
+        assert_eq!(inventory.add(VoxelType::Stone, capacity + 5), 5);
+        assert_eq!(inventory.add(VoxelType::Dirt, 1), 1);
+    }
+
+    #[test]
+    fn removing_drains_the_last_stacks_first() {
+        let mut inventory = Inventory::new();
+        inventory.add(VoxelType::Stone, MAX_STACK_SIZE + 10);
+        assert_eq!(inventory.remove(VoxelType::Stone, 20), 20);
+        assert_eq!(
+            inventory.slots()[0],
+            stack(VoxelType::Stone, MAX_STACK_SIZE - 10)
+        );
+        assert_eq!(inventory.slots()[1], None);
+    }
+
+    #[test]
+    fn taking_the_last_selected_item_empties_the_slot() {
+        let mut inventory = Inventory::new();
+        inventory.add(VoxelType::Stone, 1);
+        assert_eq!(inventory.take_selected(), Some(VoxelType::Stone));
+        assert_eq!(inventory.selected(), None);
+        assert_eq!(inventory.take_selected(), None);
+    }
+}
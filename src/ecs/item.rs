@@ -0,0 +1,62 @@
+use crate::ecs::{
+    physics::{Aabb, CollisionShape, GRAVITY},
+    voxel::VoxelType,
+};
+use cgmath::{Point3, Vector3};
+use instant::Duration;
+
+pub const ITEM_SIZE: f32 = 0.25;
+const ITEM_SPIN_SPEED: f32 = 1.5;
+
+#[derive(Debug, Clone)]
+pub struct DroppedItem {
+    pub voxel_type: VoxelType,
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    pub age: Duration,
+    pub on_ground: bool,
+}
+
+impl DroppedItem {
+    pub fn new(voxel_type: VoxelType, position: Point3<f32>) -> Self {
+        Self {
+            voxel_type,
+            position,
+            velocity: Vector3::new(0.0, 4.0, 0.0),
+            age: Duration::ZERO,
+            on_ground: false,
+        }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_center(self.position, Vector3::new(1.0, 1.0, 1.0) * ITEM_SIZE * 0.5)
+    }
+
+    // Rotation around the y axis in radians, used by the renderer to spin the item.
+    pub fn spin(&self) -> f32 {
+        self.age.as_secs_f32() * ITEM_SPIN_SPEED
+    }
+
+    pub fn update<F>(&mut self, dt: Duration, collision_shape: F)
+    where
+        F: Fn(Vector3<i32>) -> CollisionShape,
+    {
+        let secs = dt.as_secs_f32();
+        self.age += dt;
+        self.velocity.y -= GRAVITY * secs;
+
+        let delta = self.velocity * secs;
+        let allowed = self.aabb().sweep(delta, collision_shape);
+        for axis in 0..3 {
+            if allowed[axis] != delta[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        self.position += allowed;
+        self.on_ground = delta.y < 0.0 && self.velocity.y == 0.0;
+        if self.on_ground {
+            self.velocity.x = 0.0;
+            self.velocity.z = 0.0;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use cgmath::Vector3;
+use instant::Duration;
+
+// Progress of breaking the voxel under the crosshair in survival. Looking
+// away or letting go of the button starts over.
+#[derive(Debug, Clone, Default)]
+pub struct Mining {
+    target: Option<Vector3<i32>>,
+    elapsed: Duration,
+}
+
+impl Mining {
+    pub fn target(&self) -> Option<Vector3<i32>> {
+        self.target
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    // Mines `target` for another `dt` and returns the total time spent on it.
+    pub fn advance(&mut self, target: Vector3<i32>, dt: Duration) -> Duration {
+        if self.target != Some(target) {
+            self.target = Some(target);
+            self.elapsed = Duration::ZERO;
+        }
+        self.elapsed += dt;
+        self.elapsed
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.elapsed = Duration::ZERO;
+    }
+}
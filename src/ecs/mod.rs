@@ -1,10 +1,1246 @@
-mod chunk;
-mod components;
-mod entity;
-mod voxel;
+pub mod caves;
+pub mod chunk;
+pub mod components;
+pub mod config;
+pub mod entity;
+pub mod evicted;
+pub mod hash;
+pub mod inventory;
+pub mod item;
+pub mod mining;
+pub mod physics;
+pub mod player;
+pub mod rng;
+pub mod save;
+pub mod streaming;
+pub mod terrain;
+pub mod time;
+pub mod tool;
+pub mod transform;
+pub mod voxel;
 
-use crate::ecs::entity::Entity;
+use crate::ecs::{
+    chunk::{Chunk, ChunkCoord, ChunkNeighborhood, CHUNK_SIZE},
+    config::WorldConfig,
+    entity::Entities,
+    evicted::EvictedChunks,
+    inventory::Inventory,
+    item::DroppedItem,
+    mining::Mining,
+    physics::{raycast, Aabb, CollisionShape, RayHit},
+    player::{GameMode, Player},
+    rng::WorldRng,
+    streaming::ChunkStreamer,
+    terrain::TerrainGenerator,
+    time::TimeOfDay,
+    tool::{BoxSelection, ToolMode},
+    transform::Transforms,
+    voxel::{Voxel, VoxelType},
+};
+use crate::gfx::mesh::{GreedyMesher, Mesher};
+use cgmath::{Deg, EuclideanSpace, InnerSpace, MetricSpace, Point3, Rad, Vector3};
+use instant::Duration;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
 
-pub struct ECS {
-    entities: Vec<Entity>,
+const FACE_NEIGHBORS: [[i32; 3]; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+pub struct World {
+    // Shared so meshing jobs can hold on to chunks without copying them.
+    // Editing a chunk a job still holds copies it first.
+    chunks: HashMap<ChunkCoord, Arc<Chunk>>,
+    entities: Entities,
+    dirty_chunks: HashSet<Vector3<i32>>,
+    mesher: Arc<dyn Mesher>,
+    saved_chunks: EvictedChunks,
+    streaming_enabled: bool,
+    epoch: u64,
+    // Chunk-aligned voxel position everything is rendered relative to, so
+    // render-space coordinates stay small and precise far from zero.
+    origin: Vector3<i32>,
+    pub config: WorldConfig,
+    pub generator: TerrainGenerator,
+    pub streamer: ChunkStreamer,
+    pub inventory: Inventory,
+    pub player: Player,
+    pub mining: Mining,
+    pub transforms: Transforms,
+    pub item_despawn_time: Duration,
+    pub item_pickup_radius: f32,
+    pub time: TimeOfDay,
+    pub tool_mode: ToolMode,
+    pub box_selection: BoxSelection,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            entities: Entities::new(),
+            dirty_chunks: HashSet::new(),
+            mesher: Arc::new(GreedyMesher),
+            saved_chunks: EvictedChunks::new(),
+            streaming_enabled: true,
+            epoch: 0,
+            origin: Vector3::new(0, 0, 0),
+            config: WorldConfig::default(),
+            generator: TerrainGenerator::new(0),
+            streamer: ChunkStreamer::new(),
+            inventory: Inventory::new(),
+            player: Player::default(),
+            mining: Mining::default(),
+            transforms: Transforms::new(),
+            item_despawn_time: Duration::from_secs(300),
+            item_pickup_radius: 2.0,
+            time: TimeOfDay::default(),
+            tool_mode: ToolMode::default(),
+            box_selection: BoxSelection::default(),
+        }
+    }
+
+    pub fn add_chunk(&mut self, chunk: Chunk) {
+        self.insert_chunk(chunk);
+    }
+
+    // Adds `chunk` at its position, returning the chunk it replaced.
+    pub fn insert_chunk(&mut self, mut chunk: Chunk) -> Option<Chunk> {
+        self.apply_height_limits(&mut chunk);
+        self.mark_chunk_and_neighbors_dirty(chunk.position());
+        self.chunks
+            .insert(chunk.position().into(), Arc::new(chunk))
+            .map(Arc::unwrap_or_clone)
+    }
+
+    pub fn get_chunk(&self, coord: ChunkCoord) -> Option<&Chunk> {
+        self.chunks.get(&coord).map(Arc::as_ref)
+    }
+
+    // Edits made through this aren't marked dirty, so call `mark_dirty`
+    // afterwards to have the chunk remeshed.
+    pub fn get_chunk_mut(&mut self, coord: ChunkCoord) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&coord).map(Arc::make_mut)
+    }
+
+    // Lays bedrock on the bottom layer and clears everything outside the
+    // height limits. Only chunks that reach past a limit are touched.
+    fn apply_height_limits(&self, chunk: &mut Chunk) {
+        let (min_y, max_y) = (self.config.min_y, self.config.max_y);
+        let bottom = chunk.position().y * CHUNK_SIZE as i32;
+        if bottom > min_y && bottom + CHUNK_SIZE as i32 - 1 <= max_y {
+            return;
+        }
+        let modified = chunk.is_modified();
+        for y in 0..CHUNK_SIZE {
+            let world_y = bottom + y as i32;
+            let voxel_type = if world_y == min_y {
+                VoxelType::Bedrock
+            } else if self.config.in_height_limits(world_y) {
+                continue;
+            } else {
+                VoxelType::Air
+            };
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Voxel::new(voxel_type));
+                }
+            }
+        }
+        // Generated chunks stay unmodified, since the limits are part of
+        // generating them.
+        if !modified {
+            chunk.mark_saved();
+        }
+    }
+
+    // Loads the chunk at `position`, restoring saved edits if it was evicted
+    // and generating it from the world's seed otherwise.
+    pub fn generate_chunk(&mut self, position: Vector3<i32>) {
+        if self.chunk(position).is_some() || self.load_saved_chunk(position) {
+            return;
+        }
+        let chunk = self.generator.generate_chunk(position);
+        self.add_chunk(chunk);
+    }
+
+    // Drops every chunk, item and saved edit. Anything caching per-chunk
+    // data (like GPU meshes) should watch `epoch` and throw it all away
+    // when it changes, since no individual chunks are marked dirty.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.entities.clear();
+        self.dirty_chunks.clear();
+        self.saved_chunks.clear();
+        self.streamer.clear();
+        self.box_selection.cancel();
+        self.epoch += 1;
+    }
+
+    pub fn streaming_enabled(&self) -> bool {
+        self.streaming_enabled
+    }
+
+    // While disabled, `stream_chunks` and `evict_chunks` do nothing, so the
+    // loaded chunks stay exactly as they are however the player moves.
+    // Chunks that finish generating in the meantime are inserted once it's
+    // enabled again.
+    pub fn set_streaming_enabled(&mut self, enabled: bool) {
+        self.streaming_enabled = enabled;
+    }
+
+    // Requests every missing chunk within `config.stream_radius` of `center`,
+    // nearest first, and inserts the ones that finished generating as far
+    // as the streamer's per-frame limit allows.
+    pub fn stream_chunks(&mut self, center: Vector3<i32>) {
+        if !self.streaming_enabled {
+            return;
+        }
+        let (radius, height) = (self.config.stream_radius, self.config.stream_height);
+        let mut missing = vec![];
+        if radius > 0 {
+            for y in -height..=height {
+                for z in 1 - radius..radius {
+                    for x in 1 - radius..radius {
+                        let position = center + Vector3::new(x, y, z);
+                        let bottom = position.y * CHUNK_SIZE as i32;
+                        if bottom + CHUNK_SIZE as i32 <= self.config.min_y
+                            || bottom > self.config.max_y
+                        {
+                            continue;
+                        }
+                        if !self.streamer.is_pending(position) && self.chunk(position).is_none() {
+                            missing.push(position);
+                        }
+                    }
+                }
+            }
+        }
+        missing.sort_by_key(|p| {
+            let d = p - center;
+            d.x * d.x + d.y * d.y + d.z * d.z
+        });
+        for position in missing {
+            if !self.load_saved_chunk(position) {
+                self.streamer.request(position, &self.generator);
+            }
+        }
+
+        let started = instant::Instant::now();
+        self.insert_ready_chunks(|| started.elapsed());
+    }
+
+    // Inserts generated chunks until `streamer.max_inserts_per_frame` have
+    // gone in or `elapsed` passes `streamer.insert_budget`, leaving the rest
+    // for later frames. Returns how many were inserted.
+    pub fn insert_ready_chunks(&mut self, elapsed: impl Fn() -> Duration) -> usize {
+        let mut inserted = 0;
+        while inserted < self.streamer.max_inserts_per_frame {
+            let Some(chunk) = self.streamer.pop_ready() else {
+                break;
+            };
+            if self.chunk(chunk.position()).is_none() {
+                self.add_chunk(chunk);
+            }
+            inserted += 1;
+            if elapsed() >= self.streamer.insert_budget {
+                break;
+            }
+        }
+        inserted
+    }
+
+    // Clears the world and switches generation to a new seed.
+    pub fn reset(&mut self, seed: u64) {
+        self.clear();
+        self.generator.seed = seed;
+    }
+
+    // Random numbers seeded from the world's seed. Fork a stream per use so
+    // results don't depend on the order things happen in.
+    pub fn rng(&self) -> WorldRng {
+        WorldRng::new(self.generator.seed)
+    }
+
+    // Incremented every time the world is cleared.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub fn remove_chunk(&mut self, position: Vector3<i32>) -> Option<Chunk> {
+        let chunk = self.chunks.remove(&position.into())?;
+        self.mark_chunk_and_neighbors_dirty(position);
+        Some(Arc::unwrap_or_clone(chunk))
+    }
+
+    // Neighbors share border faces with the chunk, so they need remeshing
+    // whenever it appears or disappears.
+    fn mark_chunk_and_neighbors_dirty(&mut self, position: Vector3<i32>) {
+        self.dirty_chunks.insert(position);
+        for offset in FACE_NEIGHBORS {
+            let neighbor = position + Vector3::from(offset);
+            if self.chunk(neighbor).is_some() {
+                self.dirty_chunks.insert(neighbor);
+            }
+        }
+    }
+
+    // Every loaded chunk, in no particular order.
+    pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+        self.chunks.values().map(Arc::as_ref)
+    }
+
+    pub fn chunk_positions(&self) -> impl Iterator<Item = Vector3<i32>> + '_ {
+        self.chunks().map(Chunk::position)
+    }
+
+    // Order independent hash of every loaded chunk's contents, stable across
+    // platforms. Two worlds with the same chunks hash the same regardless of
+    // the order they were loaded in.
+    pub fn hash(&self) -> u64 {
+        self.chunks()
+            .map(|chunk| hash::mix64(chunk.hash()))
+            .fold(0, u64::wrapping_add)
+    }
+
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.chunk_positions().count()
+    }
+
+    // Evicts chunks beyond `config.unload_radius` of `center` while
+    // streaming, then the farthest ones left until the loaded count fits in
+    // `config.max_loaded_chunks`. Modified chunks are saved first so
+    // `load_saved_chunk` can bring them back. Returns the evicted positions.
+    pub fn evict_chunks(&mut self, center: Vector3<i32>) -> Vec<Vector3<i32>> {
+        if !self.streaming_enabled {
+            return vec![];
+        }
+        let mut evicted = vec![];
+        let radius = self.config.stream_radius;
+        if radius > 0 {
+            // Never inside the streamed area, or chunks would be unloaded as
+            // soon as they're generated.
+            let unload_radius = self.config.unload_radius.max(radius);
+            let unload_height = self.config.stream_height + unload_radius - radius;
+            evicted.extend(self.chunk_positions().filter(|p| {
+                let d = p - center;
+                d.x.abs() >= unload_radius
+                    || d.z.abs() >= unload_radius
+                    || d.y.abs() > unload_height
+            }));
+            for position in &evicted {
+                self.evict_chunk(*position);
+            }
+        }
+
+        let loaded = self.loaded_chunk_count();
+        if loaded <= self.config.max_loaded_chunks {
+            return evicted;
+        }
+        let distance = |p: &Vector3<i32>| {
+            let d = p - center;
+            d.x as i64 * d.x as i64 + d.y as i64 * d.y as i64 + d.z as i64 * d.z as i64
+        };
+        let mut positions = self.chunk_positions().collect::<Vec<_>>();
+        // Ties are broken by position so eviction doesn't depend on the
+        // map's iteration order.
+        positions.sort_by_key(|p| (std::cmp::Reverse(distance(p)), p.x, p.y, p.z));
+        positions.truncate(loaded - self.config.max_loaded_chunks);
+        for position in &positions {
+            self.evict_chunk(*position);
+        }
+        evicted.extend(positions);
+        evicted
+    }
+
+    fn evict_chunk(&mut self, position: Vector3<i32>) {
+        if let Some(chunk) = self.remove_chunk(position) {
+            if chunk.is_modified() {
+                self.saved_chunks
+                    .insert(chunk, self.config.max_evicted_chunks_in_memory);
+            }
+        }
+    }
+
+    // Reloads a previously evicted chunk, if one was saved at `position`.
+    // A chunk that can't be read back from disk is logged and generated
+    // again instead.
+    pub fn load_saved_chunk(&mut self, position: Vector3<i32>) -> bool {
+        match self.saved_chunks.remove(position) {
+            Ok(Some(chunk)) => {
+                self.add_chunk(chunk);
+                true
+            }
+            Ok(None) => false,
+            Err(err) => {
+                log::error!("couldn't reload evicted chunk {:?}: {}", position, err);
+                false
+            }
+        }
+    }
+
+    // Evicted chunks kept so their edits aren't lost.
+    pub fn saved_chunks(&self) -> &EvictedChunks {
+        &self.saved_chunks
+    }
+
+    // Chunks held in memory, loaded or evicted but not yet spilled to disk.
+    pub fn chunks_in_memory(&self) -> usize {
+        self.loaded_chunk_count() + self.saved_chunks.in_memory()
+    }
+
+    pub fn mesher(&self) -> &Arc<dyn Mesher> {
+        &self.mesher
+    }
+
+    // Switches the meshing strategy and remeshes every chunk with it.
+    pub fn set_mesher(&mut self, mesher: Arc<dyn Mesher>) {
+        self.mesher = mesher;
+        self.mark_all_dirty();
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        let positions = self.chunk_positions().collect::<Vec<_>>();
+        for position in positions {
+            self.mark_dirty(position);
+        }
+    }
+
+    pub fn neighborhood(&self, position: Vector3<i32>) -> Option<ChunkNeighborhood<'_>> {
+        let center = self.chunk(position)?;
+        let chunks = std::array::from_fn(|i| {
+            let offset = Vector3::new(i as i32 % 3 - 1, i as i32 / 3 % 3 - 1, i as i32 / 9 - 1);
+            self.chunk(position + offset)
+        });
+        Some(ChunkNeighborhood::new(center, chunks))
+    }
+
+    // Handles on the chunks `neighborhood` would look at, in the same order,
+    // for meshing them somewhere the world can't be borrowed.
+    pub fn shared_neighborhood(&self, position: Vector3<i32>) -> Option<[Option<Arc<Chunk>>; 27]> {
+        self.chunk(position)?;
+        Some(std::array::from_fn(|i| {
+            let offset = Vector3::new(i as i32 % 3 - 1, i as i32 / 3 % 3 - 1, i as i32 / 9 - 1);
+            self.chunks.get(&(position + offset).into()).cloned()
+        }))
+    }
+
+    // Splits a world voxel position into its chunk position and the local
+    // position inside that chunk.
+    fn locate(pos: Vector3<i32>) -> (Vector3<i32>, [usize; 3]) {
+        let size = CHUNK_SIZE as i32;
+        let chunk = pos.map(|v| v.div_euclid(size));
+        let local = pos.map(|v| v.rem_euclid(size) as usize);
+        (chunk, [local.x, local.y, local.z])
+    }
+
+    fn chunk(&self, position: Vector3<i32>) -> Option<&Chunk> {
+        self.chunks.get(&position.into()).map(Arc::as_ref)
+    }
+
+    fn chunk_mut(&mut self, position: Vector3<i32>) -> Option<&mut Chunk> {
+        self.chunks.get_mut(&position.into()).map(Arc::make_mut)
+    }
+
+    // The voxel at the world voxel position `pos`, if its chunk is loaded.
+    pub fn voxel_at(&self, pos: Vector3<i32>) -> Option<Voxel> {
+        let (chunk, [x, y, z]) = Self::locate(pos);
+        self.chunk(chunk).map(|c| c.get(x, y, z))
+    }
+
+    // Unloaded voxels don't block movement.
+    pub fn collision_shape(&self, pos: Vector3<i32>) -> CollisionShape {
+        self.voxel_at(pos)
+            .map_or(CollisionShape::None, |v| v.voxel_type().collision_shape())
+    }
+
+    // Like `collision_shape`, except unloaded voxels block too, so nothing
+    // moving through the world falls into chunks that haven't been
+    // generated yet. Whatever's waiting on them stays put until they are.
+    fn blocking_shape(&self, pos: Vector3<i32>) -> CollisionShape {
+        self.voxel_at(pos).map_or(CollisionShape::FullCube, |v| {
+            v.voxel_type().collision_shape()
+        })
+    }
+
+    // How far `aabb` can actually move of the displacement `delta` before
+    // it runs into a solid voxel, with each axis resolved separately so it
+    // slides along walls.
+    pub fn resolve_movement(&self, aabb: Aabb, delta: Vector3<f32>) -> Vector3<f32> {
+        aabb.sweep(delta, |pos| self.blocking_shape(pos))
+    }
+
+    pub fn is_solid(&self, pos: Vector3<i32>) -> bool {
+        self.voxel_at(pos)
+            .is_some_and(|v| v.voxel_type().is_solid())
+    }
+
+    pub fn set_voxel(&mut self, pos: Vector3<i32>, voxel: Voxel) -> bool {
+        let (chunk, [x, y, z]) = Self::locate(pos);
+        match self.chunk_mut(chunk) {
+            Some(c) => {
+                c.set(x, y, z, voxel);
+                self.dirty_chunks.insert(chunk);
+                self.mark_border_neighbors_dirty(chunk, [x, y, z]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Applies many edits at once, marking each chunk dirty only once per run
+    // of edits that land in it. Returns the number of voxels written; edits
+    // outside loaded chunks are skipped.
+    pub fn apply_edits<I>(&mut self, edits: I) -> usize
+    where
+        I: IntoIterator<Item = (Vector3<i32>, Voxel)>,
+    {
+        let mut written = 0;
+        let mut current = None;
+        for (pos, voxel) in edits {
+            let (chunk, [x, y, z]) = Self::locate(pos);
+            let Some(c) = self.chunk_mut(chunk) else {
+                continue;
+            };
+            c.set(x, y, z, voxel);
+            written += 1;
+            if current != Some(chunk) {
+                self.dirty_chunks.insert(chunk);
+                current = Some(chunk);
+            }
+            self.mark_border_neighbors_dirty(chunk, [x, y, z]);
+        }
+        written
+    }
+
+    // Marks the loaded neighbors of `chunk` that can see the voxel at `local`
+    // dirty, if it's on the chunk's border. Faces and ambient occlusion look
+    // one voxel across borders, diagonally too, so an edge or corner voxel
+    // affects up to seven neighbors.
+    fn mark_border_neighbors_dirty(&mut self, chunk: Vector3<i32>, local: [usize; 3]) {
+        let offsets = local.map(|v| {
+            let low = if v == 0 { -1 } else { 0 };
+            let high = if v == CHUNK_SIZE - 1 { 1 } else { 0 };
+            low..=high
+        });
+        for dx in offsets[0].clone() {
+            for dy in offsets[1].clone() {
+                for dz in offsets[2].clone() {
+                    let neighbor = chunk + Vector3::new(dx, dy, dz);
+                    if neighbor != chunk && self.chunk(neighbor).is_some() {
+                        self.dirty_chunks.insert(neighbor);
+                    }
+                }
+            }
+        }
+    }
+
+    // Fills the inclusive box between `min` and `max`.
+    pub fn fill_box(&mut self, min: Vector3<i32>, max: Vector3<i32>, voxel: Voxel) -> usize {
+        let edits = (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z)
+                .flat_map(move |z| (min.x..=max.x).map(move |x| (Vector3::new(x, y, z), voxel)))
+        });
+        self.apply_edits(edits)
+    }
+
+    // Replaces the voxels connected to `start` through faces that satisfy
+    // `matches`, e.g. all the air in a room, stopping once `max_voxels` have
+    // been found. Only loaded chunks are searched. Returns the number of
+    // voxels written.
+    pub fn flood_fill<F>(
+        &mut self,
+        start: Vector3<i32>,
+        matches: F,
+        replace: VoxelType,
+        max_voxels: usize,
+    ) -> usize
+    where
+        F: Fn(Voxel) -> bool,
+    {
+        let mut found = vec![];
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([start]);
+        seen.insert(start);
+        while let Some(pos) = queue.pop_front() {
+            if found.len() >= max_voxels {
+                break;
+            }
+            if !self.voxel_at(pos).is_some_and(&matches) {
+                continue;
+            }
+            found.push(pos);
+            for offset in FACE_NEIGHBORS {
+                let next = pos + Vector3::from(offset);
+                if seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        // Grouped by chunk so each one is only looked up once.
+        found.sort_by_key(|&pos| {
+            let (chunk, _) = Self::locate(pos);
+            (chunk.x, chunk.y, chunk.z)
+        });
+        let voxel = Voxel::new(replace);
+        self.apply_edits(found.into_iter().map(|pos| (pos, voxel)))
+    }
+
+    // Feeds a click to the box tool. Once both corners are set the box is
+    // filled with the selected hotbar type, or cleared to air if `clear` is
+    // set. Returns the number of voxels written.
+    pub fn box_tool_click(&mut self, pos: Vector3<i32>, clear: bool) -> usize {
+        let Some((min, max)) = self.box_selection.select(pos) else {
+            return 0;
+        };
+        let voxel_type = if clear {
+            VoxelType::Air
+        } else {
+            match self.inventory.selected() {
+                Some(stack) => stack.voxel_type,
+                None => return 0,
+            }
+        };
+        self.fill_box(min, max, Voxel::new(voxel_type))
+    }
+
+    // Switches to the next tool. A box selection that was still waiting for
+    // its second corner is dropped.
+    pub fn cycle_tool(&mut self) -> ToolMode {
+        self.tool_mode = self.tool_mode.next();
+        self.box_selection.cancel();
+        self.tool_mode
+    }
+
+    pub fn dirty_chunks(&self) -> impl Iterator<Item = &Vector3<i32>> {
+        self.dirty_chunks.iter()
+    }
+
+    // Queues the chunk at `position` for remeshing even though its voxels
+    // didn't change, e.g. after swapping out the mesher.
+    pub fn mark_dirty(&mut self, position: Vector3<i32>) {
+        self.dirty_chunks.insert(position);
+    }
+
+    // Yields every chunk that needs remeshing exactly once and clears the
+    // set, so whoever drains it is responsible for remeshing and uploading
+    // them. The built-in renderer drives its uploads the same way.
+    pub fn drain_dirty(&mut self) -> impl Iterator<Item = Vector3<i32>> + '_ {
+        self.dirty_chunks.drain()
+    }
+
+    // Replaces the voxel with air and drops it as an item. Returns the type
+    // that was broken, if there was anything solid there.
+    pub fn break_voxel(&mut self, pos: Vector3<i32>) -> Option<VoxelType> {
+        let voxel_type = self.voxel_at(pos)?.voxel_type();
+        if !voxel_type.is_solid() || !voxel_type.is_breakable() {
+            return None;
+        }
+        self.set_voxel(pos, Voxel::new(VoxelType::Air));
+        let center =
+            Point3::new(pos.x as f32, pos.y as f32, pos.z as f32) + Vector3::new(0.5, 0.5, 0.5);
+        let item = self.entities.spawn();
+        self.entities
+            .insert(item, DroppedItem::new(voxel_type, center));
+        Some(voxel_type)
+    }
+
+    // Places a voxel from the selected hotbar slot, consuming one item
+    // unless the player is in creative.
+    pub fn place_voxel(&mut self, pos: Vector3<i32>) -> bool {
+        if !self.config.in_height_limits(pos.y)
+            || self.voxel_at(pos).is_none_or(|v| v.voxel_type().is_solid())
+        {
+            return false;
+        }
+        let voxel_type = match self.player.game_mode {
+            GameMode::Creative => self.inventory.selected().map(|stack| stack.voxel_type),
+            GameMode::Survival => self.inventory.take_selected(),
+        };
+        match voxel_type {
+            Some(voxel_type) => self.set_voxel(pos, Voxel::new(voxel_type)),
+            None => false,
+        }
+    }
+
+    // The first solid voxel along the ray within `max_distance`, e.g. from a
+    // camera's position along its forward vector. A ray starting inside a
+    // solid voxel hits it straight away, at distance zero.
+    pub fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<RayHit> {
+        raycast(origin, direction, max_distance, |pos| self.is_solid(pos))
+    }
+
+    // The solid voxel the player would interact with when looking along
+    // `direction` from `eye`.
+    pub fn target_voxel(&self, eye: Point3<f32>, direction: Vector3<f32>) -> Option<RayHit> {
+        self.raycast(eye, direction, self.config.reach)
+    }
+
+    // Breaks `target` while the break button is held. Creative breaks on the
+    // frame the button is `pressed`, survival once it's been mined for the
+    // voxel's hardness. Returns the type of any voxel that broke.
+    pub fn update_breaking(
+        &mut self,
+        dt: Duration,
+        target: Option<Vector3<i32>>,
+        held: bool,
+        pressed: bool,
+    ) -> Option<VoxelType> {
+        let Some(target) = target.filter(|_| held) else {
+            self.mining.reset();
+            return None;
+        };
+        match self.player.game_mode {
+            GameMode::Creative => {
+                self.mining.reset();
+                if pressed {
+                    self.break_voxel(target)
+                } else {
+                    None
+                }
+            }
+            GameMode::Survival => {
+                let hardness = self.voxel_at(target)?.voxel_type().hardness();
+                if self.mining.advance(target, dt) < hardness {
+                    return None;
+                }
+                self.mining.reset();
+                self.break_voxel(target)
+            }
+        }
+    }
+
+    // Places the selected voxel against the face of `hit`, as long as it
+    // wouldn't end up inside the player.
+    pub fn place_against(&mut self, hit: RayHit) -> bool {
+        let pos = hit.voxel + hit.normal;
+        let min = Point3::from_vec(pos.cast::<f32>().unwrap());
+        let cell = Aabb::new(min, min + Vector3::new(1.0, 1.0, 1.0));
+        if hit.normal == Vector3::new(0, 0, 0) || cell.intersects(&self.player.aabb()) {
+            return false;
+        }
+        self.place_voxel(pos)
+    }
+
+    // Dynamic things in the world, like dropped items, which are entities
+    // with a `DroppedItem` component.
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &DroppedItem> {
+        self.entities.iter::<DroppedItem>().map(|(_, item)| item)
+    }
+
+    pub fn origin(&self) -> Vector3<i32> {
+        self.origin
+    }
+
+    pub fn to_render_space(&self, pos: Point3<f32>) -> Point3<f32> {
+        pos - self.origin.cast::<f32>().unwrap()
+    }
+
+    // Moves the origin to the chunk the player is in.
+    pub fn recenter_origin(&mut self) {
+        let size = CHUNK_SIZE as i32;
+        let chunk = self
+            .player
+            .position
+            .map(|v| (v / size as f32).floor() as i32);
+        self.origin = chunk.to_vec() * size;
+    }
+
+    // Puts the player back at the spawn point, a little above the terrain
+    // near the origin and looking slightly down on it.
+    pub fn spawn_player(&mut self) {
+        let ground = self.generator.height_at(0, 0).max(0) as f32;
+        self.player.set_eye(Point3::new(-5.0, 5.0 + ground, 10.0));
+        self.player.yaw = Rad(0.0);
+        self.player.pitch = Deg(-20.0).into();
+        self.teleport_player(self.player.position);
+    }
+
+    // Moves the player and recenters the origin right away rather than
+    // waiting for them to cross `config.origin_recenter_distance`.
+    pub fn teleport_player(&mut self, position: Point3<f32>) {
+        self.player.position = position;
+        self.player.velocity = Vector3::new(0.0, 0.0, 0.0);
+        self.player.on_ground = false;
+        self.recenter_origin();
+    }
+
+    // Moves the player with the walking velocity `walk`. Does nothing while
+    // the player is flying.
+    pub fn update_player(&mut self, dt: Duration, walk: Vector3<f32>, jump: bool) {
+        let mut player = self.player.clone();
+        player.update(dt, walk, jump, |pos| self.blocking_shape(pos));
+        self.player = player;
+    }
+
+    pub fn update(&mut self, dt: Duration, player_position: Point3<f32>) {
+        self.time.advance(dt);
+        if self
+            .to_render_space(self.player.position)
+            .to_vec()
+            .magnitude()
+            > self.config.origin_recenter_distance
+        {
+            self.recenter_origin();
+        }
+        self.transforms.propagate();
+
+        let items = self
+            .entities
+            .iter::<DroppedItem>()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        for id in items {
+            let mut item = self.entities.get::<DroppedItem>(id).unwrap().clone();
+            item.update(dt, |pos| self.blocking_shape(pos));
+            let picked_up = item.position.distance(player_position) <= self.item_pickup_radius
+                && self.inventory.add(item.voxel_type, 1) == 0;
+            if picked_up || item.age >= self.item_despawn_time {
+                self.entities.despawn(id);
+            } else {
+                self.entities.insert(id, item);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::item::ITEM_SIZE;
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    // One chunk at the origin with a stone floor along its bottom layer.
+    fn floor_world() -> World {
+        let mut world = World::new();
+        let mut chunk = Chunk::new(Vector3::new(0, 0, 0));
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set(x, 0, z, Voxel::new(VoxelType::Stone));
+            }
+        }
+        world.add_chunk(chunk);
+        world
+    }
+
+    #[test]
+    fn dropped_items_land_and_are_picked_up() {
+        let mut world = floor_world();
+        world.set_voxel(Vector3::new(8, 1, 8), Voxel::new(VoxelType::Stone));
+        assert_eq!(
+            world.break_voxel(Vector3::new(8, 1, 8)),
+            Some(VoxelType::Stone)
+        );
+        assert_eq!(world.items().count(), 1);
+
+        let far_away = Point3::new(100.0, 1.0, 100.0);
+        for _ in 0..120 {
+            world.update(FRAME, far_away);
+        }
+        let item = world.items().next().unwrap();
+        assert!(item.on_ground);
+        assert!((item.position.y - (1.0 + ITEM_SIZE * 0.5)).abs() < 1e-4);
+        assert_eq!(world.inventory.count(VoxelType::Stone), 0);
+
+        world.update(FRAME, Point3::new(8.5, 1.0, 8.5));
+        assert_eq!(world.items().count(), 0);
+        assert_eq!(world.inventory.count(VoxelType::Stone), 1);
+    }
+
+    #[test]
+    fn creative_breaks_at_once_and_survival_has_to_hold() {
+        let target = Vector3::new(8, 1, 8);
+        let mut world = floor_world();
+        world.player.game_mode = GameMode::Creative;
+        world.set_voxel(target, Voxel::new(VoxelType::Stone));
+        assert_eq!(
+            world.update_breaking(FRAME, Some(target), true, true),
+            Some(VoxelType::Stone)
+        );
+        assert!(!world.is_solid(target));
+
+        let mut world = floor_world();
+        world.player.game_mode = GameMode::Survival;
+        world.set_voxel(target, Voxel::new(VoxelType::Stone));
+        let hardness = VoxelType::Stone.hardness();
+        let frames = (hardness.as_secs_f64() / FRAME.as_secs_f64()).ceil() as usize;
+        assert_eq!(world.update_breaking(FRAME, Some(target), true, true), None);
+        for _ in 2..frames {
+            assert_eq!(
+                world.update_breaking(FRAME, Some(target), true, false),
+                None
+            );
+        }
+        // Letting go starts over.
+        assert_eq!(
+            world.update_breaking(FRAME, Some(target), false, false),
+            None
+        );
+        for _ in 1..frames {
+            assert_eq!(
+                world.update_breaking(FRAME, Some(target), true, false),
+                None
+            );
+            assert!(world.is_solid(target));
+        }
+        assert_eq!(
+            world.update_breaking(FRAME, Some(target), true, false),
+            Some(VoxelType::Stone)
+        );
+        assert!(!world.is_solid(target));
+    }
+
+    #[test]
+    fn only_survival_places_from_the_inventory() {
+        let mut world = floor_world();
+        world.inventory = Inventory::new();
+        world.inventory.add(VoxelType::Stone, 1);
+        world.player.game_mode = GameMode::Creative;
+        assert!(world.place_voxel(Vector3::new(1, 1, 1)));
+        assert_eq!(world.inventory.count(VoxelType::Stone), 1);
+
+        world.player.game_mode = GameMode::Survival;
+        assert!(world.place_voxel(Vector3::new(2, 1, 1)));
+        assert_eq!(world.inventory.count(VoxelType::Stone), 0);
+        assert!(!world.place_voxel(Vector3::new(3, 1, 1)));
+    }
+
+    #[test]
+    fn box_fill_writes_every_voxel_and_marks_chunks_dirty_once() {
+        let mut world = World::new();
+        for x in 0..3 {
+            world.add_chunk(Chunk::new(Vector3::new(x, 0, 0)));
+        }
+        world.drain_dirty().for_each(drop);
+        world.inventory = Inventory::new();
+        world.inventory.add(VoxelType::Stone, 1);
+
+        // Straddles the border between the first two chunks, away from the
+        // third.
+        assert_eq!(world.box_tool_click(Vector3::new(17, 6, 6), false), 0);
+        assert_eq!(world.box_selection.corner(), Some(Vector3::new(17, 6, 6)));
+        assert_eq!(world.box_tool_click(Vector3::new(15, 4, 4), false), 27);
+        assert_eq!(world.box_selection.corner(), None);
+
+        for x in 15..=17 {
+            for y in 4..=6 {
+                for z in 4..=6 {
+                    assert!(world.is_solid(Vector3::new(x, y, z)));
+                }
+            }
+        }
+        let mut dirty: Vec<_> = world.drain_dirty().collect();
+        dirty.sort_by_key(|p| p.x);
+        assert_eq!(dirty, [Vector3::new(0, 0, 0), Vector3::new(1, 0, 0)]);
+    }
+
+    // Three chunks in a row, solid stone apart from a closed room of air
+    // running from the first chunk into the second.
+    fn room_world() -> World {
+        let mut world = World::new();
+        for cx in 0..3 {
+            let mut chunk = Chunk::new(Vector3::new(cx, 0, 0));
+            for z in 0..CHUNK_SIZE {
+                for y in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                    }
+                }
+            }
+            world.add_chunk(chunk);
+        }
+        for x in 1..30 {
+            for y in 1..5 {
+                for z in 1..5 {
+                    world.set_voxel(Vector3::new(x, y, z), Voxel::new(VoxelType::Air));
+                }
+            }
+        }
+        world.drain_dirty().for_each(drop);
+        world
+    }
+
+    #[test]
+    fn flood_fill_stops_at_its_limit() {
+        let is_air = |voxel: Voxel| voxel.voxel_type() == VoxelType::Air;
+        let mut world = room_world();
+        let filled = world.flood_fill(Vector3::new(1, 1, 1), is_air, VoxelType::Dirt, 50);
+        assert_eq!(filled, 50);
+        let dirt = (1..30)
+            .flat_map(|x| (1..5).flat_map(move |y| (1..5).map(move |z| Vector3::new(x, y, z))))
+            .filter(|&pos| world.voxel_at(pos).unwrap().voxel_type() == VoxelType::Dirt)
+            .count();
+        assert_eq!(dirt, 50);
+        // Breadth first, so the far end of the room is still air.
+        assert!(!world.is_solid(Vector3::new(29, 4, 4)));
+        assert_eq!(
+            world.drain_dirty().collect::<Vec<_>>(),
+            [Vector3::new(0, 0, 0)]
+        );
+
+        // Without the limit the whole room fills, and the walls keep it out
+        // of the third chunk.
+        let mut world = room_world();
+        let filled = world.flood_fill(Vector3::new(1, 1, 1), is_air, VoxelType::Dirt, 10_000);
+        assert_eq!(filled, 29 * 4 * 4);
+        let mut dirty: Vec<_> = world.drain_dirty().collect();
+        dirty.sort_by_key(|p| p.x);
+        assert_eq!(dirty, [Vector3::new(0, 0, 0), Vector3::new(1, 0, 0)]);
+    }
+
+    #[test]
+    fn bedrock_stays_and_nothing_goes_above_the_limit() {
+        let mut world = World::new();
+        world.config.min_y = 0;
+        world.config.max_y = 20;
+        world.add_chunk(Chunk::new(Vector3::new(0, 0, 0)));
+        world.add_chunk(Chunk::new(Vector3::new(0, 1, 0)));
+
+        let bedrock = Vector3::new(4, 0, 4);
+        assert_eq!(
+            world.voxel_at(bedrock).unwrap().voxel_type(),
+            VoxelType::Bedrock
+        );
+        assert_eq!(world.break_voxel(bedrock), None);
+        assert_eq!(
+            world.voxel_at(bedrock).unwrap().voxel_type(),
+            VoxelType::Bedrock
+        );
+        assert_eq!(world.items().count(), 0);
+
+        world.player.game_mode = GameMode::Survival;
+        world.inventory = Inventory::new();
+        world.inventory.add(VoxelType::Stone, 2);
+        assert!(!world.place_voxel(Vector3::new(4, 21, 4)));
+        assert!(!world.is_solid(Vector3::new(4, 21, 4)));
+        assert_eq!(world.inventory.count(VoxelType::Stone), 2);
+        assert!(world.place_voxel(Vector3::new(4, 20, 4)));
+        assert_eq!(world.inventory.count(VoxelType::Stone), 1);
+    }
+
+    #[test]
+    fn draining_yields_each_dirty_chunk_once() {
+        let mut world = World::new();
+        for x in 0..3 {
+            world.add_chunk(Chunk::new(Vector3::new(x, 0, 0)));
+        }
+        world.drain_dirty().for_each(drop);
+        // Twice in one chunk, once in another, and a remesh of a third
+        // without any edit.
+        world.set_voxel(Vector3::new(1, 1, 1), Voxel::new(VoxelType::Stone));
+        world.set_voxel(Vector3::new(2, 2, 2), Voxel::new(VoxelType::Stone));
+        world.set_voxel(Vector3::new(17, 1, 1), Voxel::new(VoxelType::Stone));
+        world.mark_dirty(Vector3::new(2, 0, 0));
+        world.mark_dirty(Vector3::new(2, 0, 0));
+
+        let mut dirty: Vec<_> = world.drain_dirty().collect();
+        dirty.sort_by_key(|p| p.x);
+        assert_eq!(
+            dirty,
+            [
+                Vector3::new(0, 0, 0),
+                Vector3::new(1, 0, 0),
+                Vector3::new(2, 0, 0)
+            ]
+        );
+        assert_eq!(world.dirty_chunks().count(), 0);
+        assert_eq!(world.drain_dirty().count(), 0);
+    }
+
+    #[test]
+    fn inserts_are_capped_per_frame() {
+        let mut world = World::new();
+        world.streamer.max_inserts_per_frame = 8;
+        for x in 0..100 {
+            world.streamer.push_ready(Chunk::new(Vector3::new(x, 0, 0)));
+        }
+        let no_time = || Duration::ZERO;
+        assert_eq!(world.insert_ready_chunks(no_time), 8);
+        assert_eq!(world.loaded_chunk_count(), 8);
+        // The rest wait their turn, oldest first.
+        assert!(world.streamer.is_pending(Vector3::new(8, 0, 0)));
+        assert_eq!(world.insert_ready_chunks(no_time), 8);
+        assert!(world.chunk(Vector3::new(15, 0, 0)).is_some());
+        assert!(world.chunk(Vector3::new(16, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn inserting_stops_when_the_time_budget_runs_out() {
+        let mut world = World::new();
+        world.streamer.max_inserts_per_frame = 100;
+        world.streamer.insert_budget = Duration::from_millis(5);
+        for x in 0..20 {
+            world.streamer.push_ready(Chunk::new(Vector3::new(x, 0, 0)));
+        }
+        // Each insert takes a simulated millisecond.
+        let clock = std::cell::Cell::new(Duration::ZERO);
+        let elapsed = || {
+            clock.set(clock.get() + Duration::from_millis(1));
+            clock.get()
+        };
+        assert_eq!(world.insert_ready_chunks(elapsed), 5);
+        assert_eq!(world.loaded_chunk_count(), 5);
+        assert!(world.streamer.is_pending(Vector3::new(5, 0, 0)));
+
+        // A frame that's already over budget still makes progress.
+        assert_eq!(world.insert_ready_chunks(|| Duration::from_secs(1)), 1);
+        assert!(world.chunk(Vector3::new(5, 0, 0)).is_some());
+    }
+
+    #[test]
+    fn evicting_modified_chunks_frees_their_memory() {
+        let mut world = World::new();
+        world.config.stream_radius = 0;
+        world.config.max_loaded_chunks = 2;
+        world.config.max_evicted_chunks_in_memory = 3;
+        for x in 0..10 {
+            world.add_chunk(Chunk::new(Vector3::new(x, 0, 0)));
+            let corner = Vector3::new(x * CHUNK_SIZE as i32, 0, 0);
+            world.set_voxel(corner, Voxel::new(VoxelType::Stone));
+        }
+        let before = world.chunks_in_memory();
+        assert_eq!(before, 10);
+
+        assert_eq!(world.evict_chunks(Vector3::new(0, 0, 0)).len(), 8);
+        assert_eq!(world.chunks_in_memory(), 2 + 3);
+        assert_eq!(world.saved_chunks().on_disk(), 5);
+        assert_eq!(world.saved_chunks().len(), 8);
+
+        // The farthest went first, so it was spilled, but its edit survives.
+        assert!(world.load_saved_chunk(Vector3::new(9, 0, 0)));
+        assert!(world.is_solid(Vector3::new(9 * CHUNK_SIZE as i32, 0, 0)));
+        assert_eq!(world.saved_chunks().on_disk(), 4);
+
+        // Saving reads the spilled chunks back too.
+        let path = std::env::temp_dir().join(format!("voxl-{}-evicted.sav", std::process::id()));
+        world.save(&path).unwrap();
+        let loaded = World::load(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap().saved_chunks().len(), 10);
+    }
+
+    #[test]
+    fn over_budget_evicts_the_farthest_chunks() {
+        let mut world = World::new();
+        world.config.stream_radius = 0;
+        world.config.max_loaded_chunks = 3;
+        for x in 0..4 {
+            world.add_chunk(Chunk::new(Vector3::new(x, 0, 0)));
+        }
+        world.set_voxel(
+            Vector3::new(3 * CHUNK_SIZE as i32, 0, 0),
+            Voxel::new(VoxelType::Stone),
+        );
+
+        let evicted = world.evict_chunks(Vector3::new(0, 0, 0));
+        assert_eq!(evicted, [Vector3::new(3, 0, 0)]);
+        assert_eq!(world.loaded_chunk_count(), 3);
+        assert!(world.chunk(Vector3::new(0, 0, 0)).is_some());
+        assert!(world.chunk(Vector3::new(3, 0, 0)).is_none());
+
+        // Under budget, nothing else goes.
+        assert!(world.evict_chunks(Vector3::new(0, 0, 0)).is_empty());
+
+        // The farthest chunk was modified, so it was kept to come back.
+        assert!(world.load_saved_chunk(Vector3::new(3, 0, 0)));
+        assert!(world.is_solid(Vector3::new(3 * CHUNK_SIZE as i32, 0, 0)));
+    }
+
+    #[test]
+    fn same_seed_worlds_hash_the_same() {
+        let region =
+            || (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| (x, y, z))));
+        let mut a = World::new();
+        a.reset(42);
+        for (x, y, z) in region() {
+            a.generate_chunk(Vector3::new(x, y, z));
+        }
+        // Generated in the opposite order, which shouldn't matter.
+        let mut b = World::new();
+        b.reset(42);
+        for (x, y, z) in region().collect::<Vec<_>>().into_iter().rev() {
+            b.generate_chunk(Vector3::new(x, y, z));
+        }
+        assert_eq!(a.hash(), b.hash());
+
+        let pos = Vector3::new(0, 0, 0);
+        let replacement = match a.voxel_at(pos).unwrap().voxel_type() {
+            VoxelType::Stone => VoxelType::Air,
+            _ => VoxelType::Stone,
+        };
+        a.set_voxel(pos, Voxel::new(replacement));
+        assert_ne!(a.hash(), b.hash());
+    }
+
+    #[test]
+    fn reset_drops_everything_and_uses_the_new_seed() {
+        let generate = |world: &mut World| {
+            for x in -1..=1 {
+                for z in -1..=1 {
+                    world.generate_chunk(Vector3::new(x, 0, z));
+                }
+            }
+        };
+        let mut world = World::new();
+        world.reset(1);
+        generate(&mut world);
+        world.set_voxel(Vector3::new(0, 0, 0), Voxel::new(VoxelType::Glass));
+        let first = world.hash();
+        let epoch = world.epoch();
+
+        world.reset(2);
+        assert_eq!(world.loaded_chunk_count(), 0);
+        assert_eq!(world.dirty_chunks().count(), 0);
+        assert_eq!(world.epoch(), epoch + 1);
+        assert_eq!(world.generator.seed, 2);
+
+        generate(&mut world);
+        let mut fresh = World::new();
+        fresh.reset(2);
+        generate(&mut fresh);
+        assert_eq!(world.hash(), fresh.hash());
+        assert_ne!(world.hash(), first);
+    }
+
+    #[test]
+    fn teleporting_recenters_the_origin_at_once() {
+        let mut world = World::new();
+        let far = Point3::new(3_000_000.5, 70.0, -1_500_000.5);
+        world.teleport_player(far);
+        assert_eq!(world.player.position, far);
+        let relative = world.to_render_space(world.player.position).to_vec();
+        assert!(
+            relative.magnitude() < CHUNK_SIZE as f32 * 2.0,
+            "{:?}",
+            relative
+        );
+        assert_eq!(world.origin() % CHUNK_SIZE as i32, Vector3::new(0, 0, 0));
+    }
 }
@@ -0,0 +1,215 @@
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Vector3};
+
+pub const GRAVITY: f32 = 20.0;
+
+// Small tolerance so boxes resting exactly on a voxel face don't snag on it.
+const EPSILON: f32 = 1e-4;
+
+// The part of a voxel's cell that blocks movement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionShape {
+    None,
+    FullCube,
+    // The bottom of the cell up to the given height.
+    Slab(f32),
+}
+
+impl CollisionShape {
+    // Bounds of the shape relative to the voxel's minimum corner.
+    pub fn bounds(self) -> Option<Aabb> {
+        let max_y = match self {
+            CollisionShape::None => return None,
+            CollisionShape::FullCube => 1.0,
+            CollisionShape::Slab(height) => height,
+        };
+        Some(Aabb::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, max_y, 1.0),
+        ))
+    }
+}
+
+// The first voxel a ray hits, along with the normal of the face it entered
+// through. The normal is zero if the ray started inside the voxel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub voxel: Vector3<i32>,
+    pub normal: Vector3<i32>,
+    pub distance: f32,
+}
+
+// Steps through the voxel grid along the ray one cell at a time, returning
+// the first cell within `max_distance` that `is_hit` accepts.
+pub fn raycast<F>(
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    max_distance: f32,
+    is_hit: F,
+) -> Option<RayHit>
+where
+    F: Fn(Vector3<i32>) -> bool,
+{
+    let direction = direction.normalize();
+    let mut voxel = origin.map(|v| v.floor() as i32).to_vec();
+    let step = direction.map(|d| {
+        if d > 0.0 {
+            1
+        } else if d < 0.0 {
+            -1
+        } else {
+            0
+        }
+    });
+    let delta = direction.map(|d| 1.0 / d.abs());
+    // Distance along the ray to the next cell boundary on each axis.
+    let mut next = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+    for axis in 0..3 {
+        if step[axis] > 0 {
+            next[axis] = (voxel[axis] as f32 + 1.0 - origin[axis]) * delta[axis];
+        } else if step[axis] < 0 {
+            next[axis] = (origin[axis] - voxel[axis] as f32) * delta[axis];
+        }
+    }
+
+    let mut normal = Vector3::new(0, 0, 0);
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        if is_hit(voxel) {
+            return Some(RayHit {
+                voxel,
+                normal,
+                distance,
+            });
+        }
+        let axis = (0..3).min_by(|&a, &b| next[a].total_cmp(&next[b])).unwrap();
+        distance = next[axis];
+        voxel[axis] += step[axis];
+        next[axis] += delta[axis];
+        normal = Vector3::new(0, 0, 0);
+        normal[axis] = -step[axis];
+    }
+    None
+}
+
+// The box something collides with, standing on its position, which is the
+// center of the box's bottom face.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub size: Vector3<f32>,
+}
+
+impl Collider {
+    pub fn new(size: Vector3<f32>) -> Self {
+        Self { size }
+    }
+
+    pub fn aabb(&self, position: Point3<f32>) -> Aabb {
+        let half = Vector3::new(self.size.x * 0.5, 0.0, self.size.z * 0.5);
+        Aabb::new(
+            position - half,
+            position + half + Vector3::unit_y() * self.size.y,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center(center: Point3<f32>, half_extents: Vector3<f32>) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|a| self.min[a] < other.max[a] && self.max[a] > other.min[a])
+    }
+
+    pub fn translate(&self, offset: Vector3<f32>) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+
+    // Moves `self` by `delta` as far as it can go without entering a voxel's
+    // collision shape, returning the allowed displacement. Each axis is
+    // resolved on its own, y first so boxes land before they slide, which
+    // lets them slide along walls instead of stopping dead.
+    pub fn sweep<F>(&self, delta: Vector3<f32>, collision_shape: F) -> Vector3<f32>
+    where
+        F: Fn(Vector3<i32>) -> CollisionShape,
+    {
+        let mut aabb = *self;
+        let mut allowed = Vector3::new(0.0, 0.0, 0.0);
+        for axis in [1, 0, 2] {
+            allowed[axis] = aabb.sweep_axis(axis, delta[axis], &collision_shape);
+            let mut offset = Vector3::new(0.0, 0.0, 0.0);
+            offset[axis] = allowed[axis];
+            aabb = aabb.translate(offset);
+        }
+        allowed
+    }
+
+    // Moves `self` along a single axis as far as it can go without entering a
+    // voxel's collision shape, returning the allowed distance.
+    pub fn sweep_axis<F>(&self, axis: usize, delta: f32, collision_shape: F) -> f32
+    where
+        F: Fn(Vector3<i32>) -> CollisionShape,
+    {
+        if delta == 0.0 {
+            return 0.0;
+        }
+
+        let mut swept = *self;
+        if delta > 0.0 {
+            swept.max[axis] += delta;
+        } else {
+            swept.min[axis] += delta;
+        }
+
+        let lo = swept.min.map(|v| (v + EPSILON).floor() as i32);
+        let hi = swept.max.map(|v| (v - EPSILON).ceil() as i32);
+
+        let mut allowed = delta;
+        for x in lo.x..hi.x {
+            for y in lo.y..hi.y {
+                for z in lo.z..hi.z {
+                    let cell = Vector3::new(x, y, z);
+                    let Some(shape) = collision_shape(cell).bounds() else {
+                        continue;
+                    };
+                    let shape = shape.translate(cell.cast::<f32>().unwrap());
+                    // Partial shapes may miss the box on the other axes even
+                    // though their cell overlaps it.
+                    let overlaps = (0..3).filter(|&a| a != axis).all(|a| {
+                        shape.min[a] < swept.max[a] - EPSILON
+                            && shape.max[a] > swept.min[a] + EPSILON
+                    });
+                    if !overlaps {
+                        continue;
+                    }
+                    let near = shape.min[axis];
+                    let far = shape.max[axis];
+                    // Only voxels ahead of the box block it; anything already
+                    // overlapping is ignored so we never get stuck.
+                    if delta > 0.0 && near >= self.max[axis] - EPSILON {
+                        allowed = allowed.min(near - self.max[axis]);
+                    } else if delta < 0.0 && far <= self.min[axis] + EPSILON {
+                        allowed = allowed.max(far - self.min[axis]);
+                    }
+                }
+            }
+        }
+        allowed
+    }
+}
@@ -0,0 +1,172 @@
+use crate::ecs::physics::{Aabb, Collider, CollisionShape, GRAVITY};
+use cgmath::{Point3, Rad, Vector3};
+use instant::Duration;
+
+pub const PLAYER_WIDTH: f32 = 0.6;
+pub const PLAYER_HEIGHT: f32 = 1.8;
+pub const EYE_HEIGHT: f32 = 1.62;
+const JUMP_SPEED: f32 = 8.0;
+
+// Creative breaks voxels instantly and places them without using up the
+// inventory. Survival has to mine each voxel for its hardness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GameMode {
+    Creative,
+    #[default]
+    Survival,
+}
+
+#[derive(Debug, Clone)]
+pub struct Player {
+    // Center of the player's feet.
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    pub collider: Collider,
+    // Where the player is looking. The renderer keeps these in step with its
+    // camera so they're saved with the world.
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    pub on_ground: bool,
+    // Flying players ignore gravity and collisions and are moved directly
+    // by the camera controller.
+    pub flying: bool,
+    pub game_mode: GameMode,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new(Point3::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Player {
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            collider: Collider::new(Vector3::new(PLAYER_WIDTH, PLAYER_HEIGHT, PLAYER_WIDTH)),
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            on_ground: false,
+            flying: true,
+            game_mode: GameMode::default(),
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.position + Vector3::new(0.0, EYE_HEIGHT, 0.0)
+    }
+
+    pub fn set_eye(&mut self, eye: Point3<f32>) {
+        self.position = eye - Vector3::new(0.0, EYE_HEIGHT, 0.0);
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.collider.aabb(self.position)
+    }
+
+    // Walks with the horizontal velocity `walk`, jumping if `jump` is set
+    // while standing on the ground.
+    pub fn update<F>(&mut self, dt: Duration, walk: Vector3<f32>, jump: bool, collision_shape: F)
+    where
+        F: Fn(Vector3<i32>) -> CollisionShape,
+    {
+        if self.flying {
+            self.velocity = Vector3::new(0.0, 0.0, 0.0);
+            self.on_ground = false;
+            return;
+        }
+
+        let secs = dt.as_secs_f32();
+        self.velocity.x = walk.x;
+        self.velocity.z = walk.z;
+        if jump && self.on_ground {
+            self.velocity.y = JUMP_SPEED;
+        }
+        self.velocity.y -= GRAVITY * secs;
+
+        let delta = self.velocity * secs;
+        let allowed = self.aabb().sweep(delta, collision_shape);
+        for axis in 0..3 {
+            if allowed[axis] != delta[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        self.position += allowed;
+        self.on_ground = delta.y < 0.0 && self.velocity.y == 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::voxel::VoxelType;
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    // Solid ground below y = 0, with `voxel_type` at the origin.
+    fn shapes(voxel_type: VoxelType) -> impl Fn(Vector3<i32>) -> CollisionShape {
+        move |pos| {
+            if pos.y < 0 {
+                CollisionShape::FullCube
+            } else if pos == Vector3::new(0, 0, 0) {
+                voxel_type.collision_shape()
+            } else {
+                CollisionShape::None
+            }
+        }
+    }
+
+    fn walking_player(position: Point3<f32>) -> Player {
+        Player {
+            flying: false,
+            ..Player::new(position)
+        }
+    }
+
+    #[test]
+    fn player_stands_on_top_of_a_slab() {
+        let mut player = walking_player(Point3::new(0.5, 2.0, 0.5));
+        for _ in 0..120 {
+            player.update(
+                FRAME,
+                Vector3::new(0.0, 0.0, 0.0),
+                false,
+                shapes(VoxelType::Slab),
+            );
+        }
+        assert!(player.on_ground);
+        assert!(
+            (player.position.y - 0.5).abs() < 1e-4,
+            "{}",
+            player.position.y
+        );
+    }
+
+    #[test]
+    fn player_passes_through_plants() {
+        let mut player = walking_player(Point3::new(0.5, 2.0, 0.5));
+        for _ in 0..120 {
+            player.update(
+                FRAME,
+                Vector3::new(0.0, 0.0, 0.0),
+                false,
+                shapes(VoxelType::TallGrass),
+            );
+        }
+        assert!(player.on_ground);
+        assert!(player.position.y.abs() < 1e-4, "{}", player.position.y);
+
+        // And walks straight through them.
+        let mut player = walking_player(Point3::new(-1.5, 0.0, 0.5));
+        for _ in 0..60 {
+            player.update(
+                FRAME,
+                Vector3::new(4.0, 0.0, 0.0),
+                false,
+                shapes(VoxelType::TallGrass),
+            );
+        }
+        assert!(player.position.x > 1.5, "{}", player.position.x);
+    }
+}
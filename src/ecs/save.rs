@@ -0,0 +1,434 @@
+use crate::ecs::{
+    chunk::{Chunk, CHUNK_SIZE},
+    terrain::TerrainGenerator,
+    time::TimeOfDay,
+    voxel::{Voxel, VoxelType},
+    World,
+};
+use anyhow::*;
+use cgmath::{Point3, Rad, Vector3};
+use instant::Duration;
+use std::{fs, path::Path};
+
+const MAGIC: &[u8; 4] = b"VOXL";
+pub const SAVE_VERSION: u8 = 4;
+
+// Upgrades everything after the version byte by one version, indexed by the
+// version being upgraded from minus one. Older saves are run through each
+// step in turn and then read as the current version.
+const MIGRATIONS: [Migration; SAVE_VERSION as usize - 1] = [migrate_v1, migrate_v2, migrate_v3];
+
+type Migration = fn(&[u8]) -> Result<Vec<u8>>;
+
+// v1 only held the time of day. v2 adds the seed and the edited chunks, and
+// v1 worlds always had seed 0 and lost their edits on exit.
+fn migrate_v1(body: &[u8]) -> Result<Vec<u8>> {
+    ensure!(
+        body.len() == 8,
+        "v1 save is {} bytes, expected 8",
+        body.len()
+    );
+    let mut v2 = body.to_vec();
+    v2.extend_from_slice(&0u64.to_le_bytes());
+    v2.extend_from_slice(&0u32.to_le_bytes());
+    Ok(v2)
+}
+
+// v2 stored every voxel of a chunk as a byte. v3 run-length encodes them,
+// since chunks are mostly long runs of air or stone, and prefixes each chunk
+// with its length.
+fn migrate_v2(body: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = Reader { bytes: body };
+    let mut v3 = reader.take(16)?.to_vec();
+    let count = reader.u32()?;
+    v3.extend_from_slice(&count.to_le_bytes());
+    for _ in 0..count {
+        let mut chunk = reader.take(12)?.to_vec();
+        encode_runs(&mut chunk, reader.take(CHUNK_VOLUME)?.iter().copied());
+        v3.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+        v3.extend_from_slice(&chunk);
+    }
+    ensure!(reader.bytes.is_empty(), "trailing data after v2 save");
+    Ok(v3)
+}
+
+// v4 adds where the player is standing and looking, after the seed. It's
+// behind a flag, which v3 saves don't set, so they start at the spawn point.
+fn migrate_v3(body: &[u8]) -> Result<Vec<u8>> {
+    ensure!(body.len() >= 16, "v3 save is too short");
+    let mut v4 = body[..16].to_vec();
+    v4.push(0);
+    v4.extend_from_slice(&body[16..]);
+    Ok(v4)
+}
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+
+// Appends `ids` as runs of a save id followed by how many times it repeats,
+// as a u16.
+fn encode_runs(bytes: &mut Vec<u8>, ids: impl IntoIterator<Item = u8>) {
+    let mut run: Option<(u8, u16)> = None;
+    for id in ids {
+        run = match run {
+            Some((run_id, len)) if run_id == id && len < u16::MAX => Some((id, len + 1)),
+            Some((run_id, len)) => {
+                bytes.push(run_id);
+                bytes.extend_from_slice(&len.to_le_bytes());
+                Some((id, 1))
+            }
+            None => Some((id, 1)),
+        };
+    }
+    if let Some((id, len)) = run {
+        bytes.push(id);
+        bytes.extend_from_slice(&len.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "unexpected end of save data");
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+impl Chunk {
+    // The chunk's position followed by its voxels, y then z then x, as runs
+    // of `VoxelType::save_id`s. Only voxel types are stored.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let position = self.position();
+        for v in [position.x, position.y, position.z] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        let mut ids = Vec::with_capacity(CHUNK_VOLUME);
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    ids.push(self.get(x, y, z).voxel_type().save_id());
+                }
+            }
+        }
+        encode_runs(&mut bytes, ids);
+        bytes
+    }
+
+    // Reads a chunk written by `serialize`. The runs have to cover the chunk
+    // exactly. The chunk comes back marked modified.
+    pub fn deserialize(bytes: &[u8]) -> Result<Chunk> {
+        let mut reader = Reader { bytes };
+        let position = Vector3::new(reader.i32()?, reader.i32()?, reader.i32()?);
+        let mut chunk = Chunk::new(position);
+        let mut index = 0;
+        while !reader.bytes.is_empty() {
+            let id = reader.u8()?;
+            let len = reader.u16()? as usize;
+            let Some(voxel_type) = VoxelType::from_save_id(id) else {
+                bail!("unknown voxel id {} in chunk {:?}", id, position);
+            };
+            ensure!(len > 0, "empty run in chunk {:?}", position);
+            ensure!(
+                index + len <= CHUNK_VOLUME,
+                "chunk {:?} has more than {} voxels",
+                position,
+                CHUNK_VOLUME
+            );
+            for i in index..index + len {
+                let (x, z, y) = (
+                    i % CHUNK_SIZE,
+                    i / CHUNK_SIZE % CHUNK_SIZE,
+                    i / (CHUNK_SIZE * CHUNK_SIZE),
+                );
+                chunk.set(x, y, z, Voxel::new(voxel_type));
+            }
+            index += len;
+        }
+        ensure!(
+            index == CHUNK_VOLUME,
+            "chunk {:?} has {} of {} voxels",
+            position,
+            index,
+            CHUNK_VOLUME
+        );
+        Ok(chunk)
+    }
+}
+
+impl World {
+    // Saves the time of day, the seed, where the player is standing and
+    // looking, and every chunk that differs from what the seed generates,
+    // whether it's loaded or was evicted.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(SAVE_VERSION);
+        bytes.extend_from_slice(&self.time.time().to_le_bytes());
+        bytes.extend_from_slice(&self.time.day_length.as_secs_f32().to_le_bytes());
+        bytes.extend_from_slice(&self.generator.seed.to_le_bytes());
+        bytes.push(1);
+        let player = &self.player;
+        let position = player.position;
+        for v in [
+            position.x,
+            position.y,
+            position.z,
+            player.yaw.0,
+            player.pitch.0,
+        ] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+
+        let mut chunks = self
+            .chunks()
+            .filter(|chunk| chunk.is_modified())
+            .map(Chunk::serialize)
+            .collect::<Vec<_>>();
+        chunks.extend(self.saved_chunks.serialized()?);
+        bytes.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        for chunk in chunks {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&chunk);
+        }
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    // Loads a save from this or any earlier version. Saved chunks replace
+    // generated ones as they're loaded or streamed in.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+        ensure!(reader.take(4)? == MAGIC, "not a voxl save file");
+        let version = reader.u8()?;
+        ensure!(
+            version <= SAVE_VERSION,
+            "save version {} is newer than the newest supported version {}",
+            version,
+            SAVE_VERSION
+        );
+        ensure!(version > 0, "unsupported save version {}", version);
+
+        let mut body = reader.bytes.to_vec();
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            body = migrate(&body)?;
+        }
+        let mut reader = Reader { bytes: &body };
+
+        let time = reader.f32()?;
+        let day_length = Duration::from_secs_f32(reader.f32()?.max(0.0));
+        let seed = reader.u64()?;
+        let player = match reader.u8()? {
+            0 => None,
+            1 => {
+                let position = Point3::new(reader.f32()?, reader.f32()?, reader.f32()?);
+                let (yaw, pitch) = (reader.f32()?, reader.f32()?);
+                ensure!(
+                    [position.x, position.y, position.z, yaw, pitch]
+                        .iter()
+                        .all(|v| v.is_finite()),
+                    "saved player position or view isn't finite"
+                );
+                Some((position, Rad(yaw), Rad(pitch)))
+            }
+            flag => bail!("unknown player flag {}", flag),
+        };
+
+        let mut world = World::new();
+        world.time = TimeOfDay::new(time, day_length);
+        world.generator = TerrainGenerator::new(seed);
+        match player {
+            Some((position, yaw, pitch)) => {
+                world.player.yaw = yaw;
+                world.player.pitch = pitch;
+                world.teleport_player(position);
+            }
+            None => world.spawn_player(),
+        }
+        for _ in 0..reader.u32()? {
+            let len = reader.u32()? as usize;
+            let chunk = Chunk::deserialize(reader.take(len)?)?;
+            // Deserializing leaves the chunk marked modified, which it
+            // still is compared to the generated one, so it's saved again.
+            let memory_limit = world.config.max_evicted_chunks_in_memory;
+            world.saved_chunks.insert(chunk, memory_limit);
+        }
+        ensure!(reader.bytes.is_empty(), "trailing data after save");
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A file in the temp directory that's removed when dropped.
+    struct TempFile(std::path::PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("voxl-{}-{}.sav", std::process::id(), name);
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn time_of_day_survives_a_round_trip() {
+        let file = TempFile::new("noon");
+        let mut world = World::new();
+        world.time = TimeOfDay::new(0.5, Duration::from_secs(600));
+        world.save(&file.0).unwrap();
+
+        let loaded = World::load(&file.0).unwrap();
+        assert_eq!(loaded.time, world.time);
+        assert_eq!(loaded.time.sun_direction(), world.time.sun_direction());
+        assert_eq!(loaded.time.sky_color(), world.time.sky_color());
+    }
+
+    fn pattern(x: usize, y: usize, z: usize) -> VoxelType {
+        match (x, y, z) {
+            (_, 0, _) => VoxelType::Bedrock,
+            (x, _, z) if x == z => VoxelType::Glass,
+            (_, y, _) if y < 4 => VoxelType::Stone,
+            _ => VoxelType::Air,
+        }
+    }
+
+    #[test]
+    fn old_saves_are_migrated_with_their_voxels() {
+        // A v2 save by hand: the time of day, the seed and one chunk with
+        // every voxel stored as a byte.
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(2);
+        bytes.extend_from_slice(&0.25f32.to_le_bytes());
+        bytes.extend_from_slice(&600.0f32.to_le_bytes());
+        bytes.extend_from_slice(&1234u64.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        let position = Vector3::new(3i32, -1, 7);
+        for v in [position.x, position.y, position.z] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    bytes.push(pattern(x, y, z).save_id());
+                }
+            }
+        }
+        let file = TempFile::new("v2");
+        fs::write(&file.0, &bytes).unwrap();
+
+        let mut world = World::load(&file.0).unwrap();
+        assert_eq!(world.generator.seed, 1234);
+        assert_eq!(world.time, TimeOfDay::new(0.25, Duration::from_secs(600)));
+        let chunk = world.saved_chunks.remove(position).unwrap().unwrap();
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    assert_eq!(chunk.get(x, y, z).voxel_type(), pattern(x, y, z));
+                }
+            }
+        }
+
+        // v1 only had the time of day.
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&0.75f32.to_le_bytes());
+        bytes.extend_from_slice(&600.0f32.to_le_bytes());
+        fs::write(&file.0, &bytes).unwrap();
+        let world = World::load(&file.0).unwrap();
+        assert_eq!(world.generator.seed, 0);
+        assert!(world.saved_chunks().is_empty());
+    }
+
+    #[test]
+    fn chunks_survive_a_round_trip() {
+        // Mostly long runs with some noise, like generated terrain.
+        let mut rng = crate::ecs::rng::WorldRng::new(9);
+        let mut chunk = Chunk::new(Vector3::new(-4, 2, 17));
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let voxel_type = if rng.chance(0.1) {
+                        VoxelType::ALL[rng.next_u64() as usize % VoxelType::ALL.len()]
+                    } else if y < 8 {
+                        VoxelType::Stone
+                    } else {
+                        VoxelType::Air
+                    };
+                    chunk.set(x, y, z, Voxel::new(voxel_type));
+                }
+            }
+        }
+        let bytes = chunk.serialize();
+        let loaded = Chunk::deserialize(&bytes).unwrap();
+        assert_eq!(loaded.position(), chunk.position());
+        assert_eq!(loaded.hash(), chunk.hash());
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    assert_eq!(loaded.get(x, y, z), chunk.get(x, y, z));
+                }
+            }
+        }
+
+        // Cut short, the runs no longer cover the chunk.
+        assert!(Chunk::deserialize(&bytes[..bytes.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        let file = TempFile::new("version");
+        for version in [0, u8::MAX] {
+            let mut bytes = MAGIC.to_vec();
+            bytes.push(version);
+            fs::write(&file.0, &bytes).unwrap();
+            assert!(World::load(&file.0).is_err(), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn newer_saves_are_rejected() {
+        let file = TempFile::new("newer");
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(SAVE_VERSION + 1);
+        fs::write(&file.0, &bytes).unwrap();
+        let err = World::load(&file.0).err().unwrap();
+        assert!(err.to_string().contains("newer"), "{}", err);
+    }
+}
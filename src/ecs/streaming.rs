@@ -0,0 +1,122 @@
+use super::{chunk::Chunk, terrain::TerrainGenerator};
+use cgmath::Vector3;
+use instant::Duration;
+use std::collections::{HashSet, VecDeque};
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    crate::workers,
+    std::sync::mpsc::{self, Receiver, Sender},
+};
+
+struct Request {
+    epoch: u64,
+    position: Vector3<i32>,
+    generator: TerrainGenerator,
+}
+
+// Generates chunks on the shared worker threads and queues them up to be
+// inserted into the world. On the web, where there are no threads, chunks
+// are generated as soon as they're requested instead.
+pub struct ChunkStreamer {
+    // Generated chunks, with the epoch they were requested in.
+    #[cfg(not(target_arch = "wasm32"))]
+    chunk_sender: Sender<(u64, Chunk)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    chunks: Receiver<(u64, Chunk)>,
+    epoch: u64,
+    // Requested chunks that haven't been inserted yet.
+    pending: HashSet<Vector3<i32>>,
+    // Generated chunks held back by the insert limit.
+    ready: VecDeque<Chunk>,
+    // How many generated chunks may be inserted per frame, so a burst of
+    // finished chunks doesn't all need meshing in the same frame.
+    pub max_inserts_per_frame: usize,
+    // How long inserting may take per frame. At least one chunk is inserted
+    // each frame however long it takes, so streaming always makes progress.
+    pub insert_budget: Duration,
+}
+
+impl Default for ChunkStreamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkStreamer {
+    pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let (chunk_sender, chunks) = mpsc::channel();
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            chunk_sender,
+            #[cfg(not(target_arch = "wasm32"))]
+            chunks,
+            epoch: 0,
+            pending: HashSet::new(),
+            ready: VecDeque::new(),
+            max_inserts_per_frame: 8,
+            insert_budget: Duration::from_millis(2),
+        }
+    }
+
+    pub fn is_pending(&self, position: Vector3<i32>) -> bool {
+        self.pending.contains(&position)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Queues the chunk at `position` to be generated with `generator`.
+    pub fn request(&mut self, position: Vector3<i32>, generator: &TerrainGenerator) {
+        if !self.pending.insert(position) {
+            return;
+        }
+        let request = Request {
+            epoch: self.epoch,
+            position,
+            generator: generator.clone(),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let chunk_sender = self.chunk_sender.clone();
+            workers::spawn(move || {
+                let chunk = request.generator.generate_chunk(request.position);
+                chunk_sender.send((request.epoch, chunk)).ok();
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.ready
+            .push_back(request.generator.generate_chunk(request.position));
+    }
+
+    // Hands back a generated chunk as if it came from the worker, e.g. for
+    // chunks produced elsewhere that should still respect the insert limit.
+    pub fn push_ready(&mut self, chunk: Chunk) {
+        self.pending.insert(chunk.position());
+        self.ready.push_back(chunk);
+    }
+
+    // Takes the next generated chunk to insert, oldest first. Limiting how
+    // many are inserted per frame is up to the caller.
+    pub fn pop_ready(&mut self) -> Option<Chunk> {
+        #[cfg(not(target_arch = "wasm32"))]
+        for (epoch, chunk) in self.chunks.try_iter() {
+            if epoch == self.epoch {
+                self.ready.push_back(chunk);
+            }
+        }
+        let chunk = self.ready.pop_front()?;
+        self.pending.remove(&chunk.position());
+        Some(chunk)
+    }
+
+    // Forgets every request. Chunks still being generated are dropped when
+    // they come back.
+    pub fn clear(&mut self) {
+        self.epoch += 1;
+        self.pending.clear();
+        self.ready.clear();
+    }
+}
@@ -0,0 +1,125 @@
+use super::{
+    caves::CaveCarver,
+    chunk::{Chunk, CHUNK_SIZE},
+    rng::WorldRng,
+    voxel::{Voxel, VoxelType},
+};
+use cgmath::Vector3;
+
+const DIRT_DEPTH: i32 = 3;
+// Keys separating the random streams of each terrain feature.
+const HEIGHT_KEY: u64 = 0;
+const TALL_GRASS_KEY: u64 = 1;
+
+// Height-map terrain from 2D value noise. Heights are sampled in absolute
+// voxel coordinates so neighboring chunks always line up.
+#[derive(Debug, Clone)]
+pub struct TerrainGenerator {
+    pub seed: u64,
+    // Noise features per voxel. Smaller values give broader hills.
+    pub frequency: f32,
+    // Height difference between the lowest valleys and highest peaks.
+    pub amplitude: f32,
+    pub base_height: i32,
+    // Chance of tall grass growing on each grass voxel.
+    pub tall_grass_density: f32,
+    // Hollows out caves after the terrain is filled in. `None` leaves the
+    // ground solid.
+    pub caves: Option<CaveCarver>,
+}
+
+impl TerrainGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frequency: 1.0 / 32.0,
+            amplitude: 12.0,
+            base_height: 8,
+            tall_grass_density: 0.05,
+            caves: Some(CaveCarver::default()),
+        }
+    }
+
+    pub fn height_at(&self, x: i32, z: i32) -> i32 {
+        let noise = value_noise(
+            self.seed,
+            x as f32 * self.frequency,
+            z as f32 * self.frequency,
+        );
+        self.base_height + (noise * self.amplitude).round() as i32
+    }
+
+    // Whether tall grass grows on the grass voxel at the given position.
+    // It doesn't grow where a cave mouth took the grass.
+    pub fn has_tall_grass(&self, x: i32, y: i32, z: i32) -> bool {
+        if let Some(caves) = &self.caves {
+            if caves.carves_surface(self.seed, x, y, z) {
+                return false;
+            }
+        }
+        WorldRng::new(self.seed)
+            .fork(TALL_GRASS_KEY)
+            .at(Vector3::new(x, y, z), 0)
+            .chance(self.tall_grass_density)
+    }
+
+    // Takes either a `ChunkCoord` or a chunk position vector.
+    pub fn generate_chunk(&self, position: impl Into<Vector3<i32>>) -> Chunk {
+        let position = position.into();
+        let size = CHUNK_SIZE as i32;
+        let origin = position * size;
+        let mut chunk = Chunk::new(position);
+        for z in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let height = self.height_at(origin.x + x as i32, origin.z + z as i32);
+                for y in 0..CHUNK_SIZE {
+                    let world_y = origin.y + y as i32;
+                    let voxel_type = if world_y == height + 1 {
+                        if !self.has_tall_grass(origin.x + x as i32, height, origin.z + z as i32) {
+                            continue;
+                        }
+                        VoxelType::TallGrass
+                    } else if world_y > height {
+                        continue;
+                    } else if world_y == height {
+                        VoxelType::Grass
+                    } else if world_y > height - DIRT_DEPTH {
+                        VoxelType::Dirt
+                    } else {
+                        VoxelType::Stone
+                    };
+                    chunk.set(x, y, z, Voxel::new(voxel_type));
+                }
+            }
+        }
+        if let Some(caves) = &self.caves {
+            caves.carve(self.seed, &mut chunk, |x, z| self.height_at(x, z));
+        }
+        // Generated chunks can be regenerated, so they don't need saving.
+        chunk.mark_saved();
+        chunk
+    }
+}
+
+// Pseudo-random value in [0, 1) for an integer lattice point.
+fn lattice(seed: u64, x: i32, z: i32) -> f32 {
+    WorldRng::new(seed)
+        .fork(HEIGHT_KEY)
+        .at(Vector3::new(x, 0, z), 0)
+        .next_f32()
+}
+
+// Smoothly interpolated lattice values, in [0, 1).
+fn value_noise(seed: u64, x: f32, z: f32) -> f32 {
+    let (x0, z0) = (x.floor(), z.floor());
+    let (tx, tz) = (smoothstep(x - x0), smoothstep(z - z0));
+    let (x0, z0) = (x0 as i32, z0 as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let top = lerp(lattice(seed, x0, z0), lattice(seed, x0 + 1, z0), tx);
+    let bottom = lerp(lattice(seed, x0, z0 + 1), lattice(seed, x0 + 1, z0 + 1), tx);
+    lerp(top, bottom, tz)
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,96 @@
+use cgmath::{InnerSpace, Vector3};
+use instant::Duration;
+use std::f32::consts::TAU;
+
+const DAY_SKY: [f32; 3] = [0.5, 0.7, 1.0];
+const NIGHT_SKY: [f32; 3] = [0.01, 0.01, 0.05];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeOfDay {
+    // Fraction of the day in [0, 1); 0.0 is midnight and 0.5 is noon.
+    time: f32,
+    pub day_length: Duration,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self::new(0.5, Duration::from_secs(20 * 60))
+    }
+}
+
+impl TimeOfDay {
+    pub fn new(time: f32, day_length: Duration) -> Self {
+        Self {
+            time: time.rem_euclid(1.0),
+            day_length,
+        }
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.rem_euclid(1.0);
+    }
+
+    pub fn advance(&mut self, dt: Duration) {
+        let day_length = self.day_length.as_secs_f32();
+        if day_length > 0.0 {
+            self.set_time(self.time + dt.as_secs_f32() / day_length);
+        }
+    }
+
+    // Direction pointing towards the sun. The sun rises along +x, is overhead
+    // at noon and sets along -x.
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = (self.time - 0.25) * TAU;
+        Vector3::new(angle.cos(), angle.sin(), 0.0).normalize()
+    }
+
+    // How bright the sky is, from 0.0 at night to 1.0 during the day.
+    pub fn daylight(&self) -> f32 {
+        (self.sun_direction().y * 2.0 + 0.5).clamp(0.0, 1.0)
+    }
+
+    pub fn sky_color(&self) -> wgpu::Color {
+        let t = self.daylight();
+        let mix = |i: usize| (NIGHT_SKY[i] + (DAY_SKY[i] - NIGHT_SKY[i]) * t) as f64;
+        wgpu::Color {
+            r: mix(0),
+            g: mix(1),
+            b: mix(2),
+            a: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_day_takes_its_configured_length() {
+        let mut time = TimeOfDay::new(0.5, Duration::from_secs(100));
+        time.advance(Duration::from_secs(25));
+        assert!((time.time() - 0.75).abs() < 1e-6);
+        time.advance(Duration::from_secs(75));
+        assert!((time.time() - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn the_sun_is_overhead_at_noon() {
+        let noon = TimeOfDay::new(0.5, Duration::from_secs(100));
+        assert!((noon.sun_direction() - Vector3::unit_y()).magnitude() < 1e-6);
+        assert_eq!(noon.daylight(), 1.0);
+        let midnight = TimeOfDay::new(0.0, Duration::from_secs(100));
+        assert_eq!(midnight.daylight(), 0.0);
+    }
+
+    #[test]
+    fn a_zero_day_length_stops_time() {
+        let mut time = TimeOfDay::new(0.3, Duration::ZERO);
+        time.advance(Duration::from_secs(10));
+        assert_eq!(time.time(), 0.3);
+    }
+}
@@ -0,0 +1,66 @@
+use cgmath::Vector3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolMode {
+    #[default]
+    Single,
+    BoxFill,
+}
+
+impl ToolMode {
+    pub fn next(self) -> Self {
+        match self {
+            ToolMode::Single => ToolMode::BoxFill,
+            ToolMode::BoxFill => ToolMode::Single,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToolMode::Single => "single",
+            ToolMode::BoxFill => "box fill",
+        }
+    }
+}
+
+// The inclusive (min, max) bounds of the box with corners `a` and `b`.
+fn bounds(a: Vector3<i32>, b: Vector3<i32>) -> (Vector3<i32>, Vector3<i32>) {
+    (
+        Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+        Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+    )
+}
+
+#[derive(Debug, Default)]
+pub struct BoxSelection {
+    corner: Option<Vector3<i32>>,
+}
+
+impl BoxSelection {
+    // The first corner of a selection that is still in progress.
+    pub fn corner(&self) -> Option<Vector3<i32>> {
+        self.corner
+    }
+
+    // The first call sets corner A; the second sets corner B and returns the
+    // inclusive (min, max) bounds of the box, resetting the selection.
+    pub fn select(&mut self, pos: Vector3<i32>) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        match self.corner.take() {
+            Some(a) => Some(bounds(a, pos)),
+            None => {
+                self.corner = Some(pos);
+                None
+            }
+        }
+    }
+
+    // The bounds `select` would return if `pos` were picked as corner B, to
+    // show what the next click would fill.
+    pub fn preview(&self, pos: Vector3<i32>) -> Option<(Vector3<i32>, Vector3<i32>)> {
+        Some(bounds(self.corner?, pos))
+    }
+
+    pub fn cancel(&mut self) {
+        self.corner = None;
+    }
+}
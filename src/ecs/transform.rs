@@ -0,0 +1,206 @@
+use anyhow::{bail, ensure, Result};
+use cgmath::{Matrix4, One, Quaternion, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransformId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::from_translation(Vector3::new(0.0, 0.0, 0.0))
+    }
+}
+
+impl Transform {
+    pub fn from_translation(translation: Vector3<f32>) -> Self {
+        Self {
+            translation,
+            rotation: Quaternion::one(),
+            scale: 1.0,
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.translation)
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_scale(self.scale)
+    }
+}
+
+struct Node {
+    local: Transform,
+    parent: Option<TransformId>,
+    world: Matrix4<f32>,
+}
+
+// Transforms that can be attached to each other, e.g. a held block to the
+// player's camera. Each one is relative to its parent, and `propagate`
+// works out where everything ends up in the world.
+#[derive(Default)]
+pub struct Transforms {
+    nodes: Vec<Option<Node>>,
+}
+
+impl Transforms {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self, local: Transform) -> TransformId {
+        let node = Node {
+            local,
+            parent: None,
+            world: local.matrix(),
+        };
+        // Slots aren't reused, so a stale id can't alias a newer transform.
+        self.nodes.push(Some(node));
+        TransformId(self.nodes.len() - 1)
+    }
+
+    // Removes the transform. Its children become roots, so their local
+    // transforms are relative to the world from then on.
+    pub fn remove(&mut self, id: TransformId) -> Option<Transform> {
+        let node = self.nodes.get_mut(id.0)?.take()?;
+        for child in self.nodes.iter_mut().flatten() {
+            if child.parent == Some(id) {
+                child.parent = None;
+            }
+        }
+        Some(node.local)
+    }
+
+    fn node(&self, id: TransformId) -> Option<&Node> {
+        self.nodes.get(id.0)?.as_ref()
+    }
+
+    pub fn local(&self, id: TransformId) -> Option<Transform> {
+        Some(self.node(id)?.local)
+    }
+
+    pub fn set_local(&mut self, id: TransformId, local: Transform) {
+        if let Some(Some(node)) = self.nodes.get_mut(id.0) {
+            node.local = local;
+        }
+    }
+
+    pub fn parent(&self, id: TransformId) -> Option<TransformId> {
+        self.node(id)?.parent
+    }
+
+    // Attaches `id` to `parent`, or detaches it with `None`. Fails if either
+    // doesn't exist or if `id` would end up its own ancestor.
+    pub fn set_parent(&mut self, id: TransformId, parent: Option<TransformId>) -> Result<()> {
+        ensure!(self.node(id).is_some(), "no transform {:?}", id);
+        let mut ancestor = parent;
+        while let Some(current) = ancestor {
+            if current == id {
+                bail!("attaching {:?} to {:?} would create a cycle", id, parent);
+            }
+            let Some(node) = self.node(current) else {
+                bail!("no transform {:?}", current);
+            };
+            ancestor = node.parent;
+        }
+        self.nodes[id.0].as_mut().unwrap().parent = parent;
+        Ok(())
+    }
+
+    // The world transform as of the last `propagate`.
+    pub fn world(&self, id: TransformId) -> Option<Matrix4<f32>> {
+        Some(self.node(id)?.world)
+    }
+
+    // Recomputes every world transform from the local ones, parents first.
+    pub fn propagate(&mut self) {
+        let mut children = vec![vec![]; self.nodes.len()];
+        let mut stack = vec![];
+        for (index, node) in self.nodes.iter().enumerate() {
+            match node.as_ref().map(|n| n.parent) {
+                Some(Some(parent)) => children[parent.0].push(index),
+                Some(None) => stack.push((index, Matrix4::one())),
+                None => {}
+            }
+        }
+        while let Some((index, parent_world)) = stack.pop() {
+            let node = self.nodes[index].as_mut().unwrap();
+            node.world = parent_world * node.local.matrix();
+            let world = node.world;
+            stack.extend(children[index].iter().map(|&child| (child, world)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, InnerSpace, Rotation3, Vector4};
+
+    fn origin_of(matrix: Matrix4<f32>) -> Vector3<f32> {
+        matrix.w.truncate()
+    }
+
+    fn assert_near(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude2() < 1e-8, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn moving_a_parent_moves_its_children() {
+        let mut transforms = Transforms::new();
+        let parent = transforms.spawn(Transform::from_translation(Vector3::new(10.0, 0.0, 0.0)));
+        let child = transforms.spawn(Transform::from_translation(Vector3::new(0.0, 2.0, 0.0)));
+        let grandchild = transforms.spawn(Transform::from_translation(Vector3::new(1.0, 0.0, 0.0)));
+        transforms.set_parent(child, Some(parent)).unwrap();
+        transforms.set_parent(grandchild, Some(child)).unwrap();
+        transforms.propagate();
+        assert_near(
+            origin_of(transforms.world(grandchild).unwrap()),
+            Vector3::new(11.0, 2.0, 0.0),
+        );
+
+        // A quarter turn of the parent about y swings the children round it.
+        transforms.set_local(
+            parent,
+            Transform {
+                rotation: Quaternion::from_angle_y(Deg(90.0)),
+                ..Transform::from_translation(Vector3::new(-5.0, 0.0, 3.0))
+            },
+        );
+        transforms.propagate();
+        assert_near(
+            origin_of(transforms.world(child).unwrap()),
+            Vector3::new(-5.0, 2.0, 3.0),
+        );
+        assert_near(
+            origin_of(transforms.world(grandchild).unwrap()),
+            Vector3::new(-5.0, 2.0, 2.0),
+        );
+        let forward = transforms.world(grandchild).unwrap() * Vector4::new(1.0, 0.0, 0.0, 0.0);
+        assert_near(forward.truncate(), Vector3::new(0.0, 0.0, -1.0));
+
+        // Detached children keep their local transform, now in world space.
+        transforms.remove(parent);
+        transforms.propagate();
+        assert_near(
+            origin_of(transforms.world(child).unwrap()),
+            Vector3::new(0.0, 2.0, 0.0),
+        );
+        assert!(transforms.world(parent).is_none());
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut transforms = Transforms::new();
+        let a = transforms.spawn(Transform::default());
+        let b = transforms.spawn(Transform::default());
+        transforms.set_parent(b, Some(a)).unwrap();
+        assert!(transforms.set_parent(a, Some(b)).is_err());
+        assert!(transforms.set_parent(a, Some(a)).is_err());
+        assert_eq!(transforms.parent(a), None);
+    }
+}
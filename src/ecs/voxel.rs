@@ -1,4 +1,207 @@
-pub struct Voxel {
-    voxel_id: usize,
-    position: [f32; 3],
+use super::physics::CollisionShape;
+use crate::gfx::mesh::Face;
+use instant::Duration;
+
+// The discriminants are what `to_bits` packs into a `Voxel`, so they never
+// change once assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(u16)]
+pub enum VoxelType {
+    #[default]
+    Air = 0,
+    Stone = 1,
+    Dirt = 2,
+    Grass = 3,
+    Slab = 4,
+    TallGrass = 5,
+    Glass = 6,
+    // The floor of the world, which can't be broken.
+    Bedrock = 7,
+}
+
+impl VoxelType {
+    pub const ALL: [VoxelType; 8] = [
+        VoxelType::Air,
+        VoxelType::Stone,
+        VoxelType::Dirt,
+        VoxelType::Grass,
+        VoxelType::Slab,
+        VoxelType::TallGrass,
+        VoxelType::Glass,
+        VoxelType::Bedrock,
+    ];
+
+    // Whether the voxel is drawn. Collision is decided separately by
+    // `collision_shape`, so visible voxels can still be walked through.
+    pub fn is_solid(self) -> bool {
+        self != VoxelType::Air
+    }
+
+    // Transparent voxels are drawn in a separate blended pass, and the faces
+    // behind them stay visible.
+    pub fn is_transparent(self) -> bool {
+        self == VoxelType::Glass
+    }
+
+    pub fn is_opaque(self) -> bool {
+        self.is_solid() && !self.is_transparent()
+    }
+
+    // Whether this voxel's face touching `neighbor` is hidden. Anything opaque
+    // hides it, and transparent voxels also hide faces against their own type
+    // only, so a glass wall has no faces inside it but the far side of a
+    // glass box still shows through the near one.
+    pub fn face_hidden_by(self, neighbor: VoxelType) -> bool {
+        neighbor.is_opaque() || (self.is_transparent() && neighbor == self)
+    }
+
+    pub fn collision_shape(self) -> CollisionShape {
+        match self {
+            VoxelType::Air | VoxelType::TallGrass => CollisionShape::None,
+            VoxelType::Slab => CollisionShape::Slab(0.5),
+            VoxelType::Stone
+            | VoxelType::Dirt
+            | VoxelType::Grass
+            | VoxelType::Glass
+            | VoxelType::Bedrock => CollisionShape::FullCube,
+        }
+    }
+
+    pub fn is_breakable(self) -> bool {
+        self != VoxelType::Bedrock
+    }
+
+    // How long the voxel takes to break by hand in survival.
+    pub fn hardness(self) -> Duration {
+        let millis = match self {
+            VoxelType::Bedrock => return Duration::MAX,
+            VoxelType::Air | VoxelType::TallGrass => 0,
+            VoxelType::Glass => 300,
+            VoxelType::Dirt | VoxelType::Grass => 750,
+            VoxelType::Stone | VoxelType::Slab => 1500,
+        };
+        Duration::from_millis(millis)
+    }
+
+    pub fn to_bits(self) -> u16 {
+        self as u16
+    }
+
+    pub fn from_bits(bits: u16) -> Option<Self> {
+        Some(match bits {
+            0 => VoxelType::Air,
+            1 => VoxelType::Stone,
+            2 => VoxelType::Dirt,
+            3 => VoxelType::Grass,
+            4 => VoxelType::Slab,
+            5 => VoxelType::TallGrass,
+            6 => VoxelType::Glass,
+            7 => VoxelType::Bedrock,
+            _ => return None,
+        })
+    }
+
+    // The id the voxel is stored as in saves, which is its bits while there
+    // are few enough types to fit a byte.
+    pub fn save_id(self) -> u8 {
+        self.to_bits() as u8
+    }
+
+    pub fn from_save_id(id: u8) -> Option<Self> {
+        Self::from_bits(id.into())
+    }
+}
+
+const TYPE_BITS: u32 = 16;
+const LIGHT_SHIFT: u32 = TYPE_BITS;
+const LIGHT_BITS: u32 = 4;
+const ORIENTATION_SHIFT: u32 = LIGHT_SHIFT + LIGHT_BITS;
+const ORIENTATION_BITS: u32 = 3;
+const USED_BITS: u32 = ORIENTATION_SHIFT + ORIENTATION_BITS;
+
+// A single voxel packed into a `u64`:
+//
+// - bits 0..16: the `VoxelType`, as `VoxelType::to_bits`
+// - bits 16..20: light level, 0 to `Voxel::MAX_LIGHT`
+// - bits 20..23: the face pointing the way the voxel is turned, as an index
+//   into `Face::ALL`
+//
+// The rest are zero. Saves only store the type, as its one byte
+// `VoxelType::save_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Voxel(u64);
+
+impl Voxel {
+    pub const MAX_LIGHT: u8 = (1 << LIGHT_BITS) - 1;
+
+    // A voxel of `voxel_type` with no light, facing `Face::PosX`.
+    pub fn new(voxel_type: VoxelType) -> Self {
+        Self(voxel_type.to_bits().into())
+    }
+
+    pub fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    // Fails if the type or orientation is unknown or any unused bit is set.
+    pub fn from_bits(bits: u64) -> Option<Self> {
+        let voxel = Self(bits);
+        let orientation = voxel.field(ORIENTATION_SHIFT, ORIENTATION_BITS) as usize;
+        (bits >> USED_BITS == 0
+            && VoxelType::from_bits(bits as u16).is_some()
+            && orientation < Face::ALL.len())
+        .then_some(voxel)
+    }
+
+    fn field(self, shift: u32, bits: u32) -> u64 {
+        (self.0 >> shift) & ((1 << bits) - 1)
+    }
+
+    fn with_field(self, shift: u32, bits: u32, value: u64) -> Self {
+        let mask = ((1 << bits) - 1) << shift;
+        Self((self.0 & !mask) | ((value << shift) & mask))
+    }
+
+    pub fn voxel_type(&self) -> VoxelType {
+        // Every way of making a voxel checks the type.
+        VoxelType::from_bits(self.0 as u16).unwrap_or_default()
+    }
+
+    pub fn with_voxel_type(self, voxel_type: VoxelType) -> Self {
+        self.with_field(0, TYPE_BITS, voxel_type.to_bits().into())
+    }
+
+    pub fn light_level(&self) -> u8 {
+        self.field(LIGHT_SHIFT, LIGHT_BITS) as u8
+    }
+
+    // Levels above `MAX_LIGHT` are clamped to it.
+    pub fn with_light_level(self, level: u8) -> Self {
+        let level = level.min(Self::MAX_LIGHT);
+        self.with_field(LIGHT_SHIFT, LIGHT_BITS, level.into())
+    }
+
+    pub fn orientation(&self) -> Face {
+        Face::ALL[self.field(ORIENTATION_SHIFT, ORIENTATION_BITS) as usize]
+    }
+
+    pub fn with_orientation(self, orientation: Face) -> Self {
+        let index = Face::ALL.iter().position(|&f| f == orientation).unwrap();
+        self.with_field(ORIENTATION_SHIFT, ORIENTATION_BITS, index as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transparent_faces_only_hide_against_their_own_type() {
+        assert!(VoxelType::Glass.face_hidden_by(VoxelType::Glass));
+        assert!(VoxelType::Glass.face_hidden_by(VoxelType::Stone));
+        assert!(!VoxelType::Glass.face_hidden_by(VoxelType::Air));
+        // Stone behind glass is still seen through it.
+        assert!(!VoxelType::Stone.face_hidden_by(VoxelType::Glass));
+        assert!(VoxelType::Stone.face_hidden_by(VoxelType::Stone));
+    }
 }
@@ -0,0 +1,93 @@
+use crate::{
+    gfx::render::renderer::Renderer,
+    input::{GamepadAxis, GamepadButton},
+};
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+// Reads controllers through gilrs and hands their events to the renderer.
+pub struct GamepadBackend {
+    gilrs: Gilrs,
+}
+
+impl GamepadBackend {
+    // Returns `None`, after logging why, if the platform has no controller
+    // support.
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Self { gilrs }),
+            Err(err) => {
+                log::warn!("controllers are unavailable: {}", err);
+                None
+            }
+        }
+    }
+
+    // Controllers already plugged in when the game starts don't send a
+    // connect event, so the renderer is told about them here.
+    pub fn connect_existing(&self, renderer: &mut Renderer) {
+        for (id, _) in self.gilrs.gamepads() {
+            renderer.gamepad_connected(id.into());
+        }
+    }
+
+    // Feeds every event since the last poll to `renderer`. Called once a
+    // frame, before the renderer updates.
+    pub fn poll(&mut self, renderer: &mut Renderer) {
+        while let Some(event) = self.gilrs.next_event() {
+            let id = event.id.into();
+            match event.event {
+                EventType::Connected => renderer.gamepad_connected(id),
+                EventType::Disconnected => renderer.gamepad_disconnected(id),
+                EventType::ButtonPressed(button, _) => {
+                    if let Some(button) = gamepad_button(button) {
+                        renderer.gamepad_button(id, button, true);
+                    }
+                }
+                EventType::ButtonReleased(button, _) => {
+                    if let Some(button) = gamepad_button(button) {
+                        renderer.gamepad_button(id, button, false);
+                    }
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    if let Some(axis) = gamepad_axis(axis) {
+                        renderer.gamepad_axis(id, axis, value);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// gilrs calls the bumpers the first triggers and the triggers the second.
+pub fn gamepad_button(button: Button) -> Option<GamepadButton> {
+    Some(match button {
+        Button::South => GamepadButton::South,
+        Button::East => GamepadButton::East,
+        Button::North => GamepadButton::North,
+        Button::West => GamepadButton::West,
+        Button::LeftTrigger => GamepadButton::LeftBumper,
+        Button::LeftTrigger2 => GamepadButton::LeftTrigger,
+        Button::RightTrigger => GamepadButton::RightBumper,
+        Button::RightTrigger2 => GamepadButton::RightTrigger,
+        Button::Select => GamepadButton::Select,
+        Button::Start => GamepadButton::Start,
+        Button::LeftThumb => GamepadButton::LeftThumb,
+        Button::RightThumb => GamepadButton::RightThumb,
+        Button::DPadUp => GamepadButton::DPadUp,
+        Button::DPadDown => GamepadButton::DPadDown,
+        Button::DPadLeft => GamepadButton::DPadLeft,
+        Button::DPadRight => GamepadButton::DPadRight,
+        _ => return None,
+    })
+}
+
+pub fn gamepad_axis(axis: Axis) -> Option<GamepadAxis> {
+    Some(match axis {
+        Axis::LeftStickX => GamepadAxis::LeftStickX,
+        Axis::LeftStickY => GamepadAxis::LeftStickY,
+        Axis::RightStickX => GamepadAxis::RightStickX,
+        Axis::RightStickY => GamepadAxis::RightStickY,
+        _ => return None,
+    })
+}
@@ -1 +1,2 @@
+pub mod mesh;
 pub mod render;
\ No newline at end of file
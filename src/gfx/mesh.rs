@@ -0,0 +1,375 @@
+pub mod culled;
+pub mod greedy;
+pub mod jobs;
+pub mod lighting;
+pub mod marching_cubes;
+pub mod seam;
+
+pub use culled::CulledMesher;
+pub use greedy::GreedyMesher;
+pub use marching_cubes::MarchingCubesMesher;
+
+use crate::{
+    ecs::{
+        chunk::{ChunkNeighborhood, CHUNK_SIZE},
+        voxel::VoxelType,
+    },
+    gfx::render::vertex::Vertex,
+};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+    PosZ,
+    NegZ,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::PosX,
+        Face::NegX,
+        Face::PosY,
+        Face::NegY,
+        Face::PosZ,
+        Face::NegZ,
+    ];
+
+    pub fn axis(self) -> usize {
+        match self {
+            Face::PosX | Face::NegX => 0,
+            Face::PosY | Face::NegY => 1,
+            Face::PosZ | Face::NegZ => 2,
+        }
+    }
+
+    pub fn is_positive(self) -> bool {
+        matches!(self, Face::PosX | Face::PosY | Face::PosZ)
+    }
+
+    pub fn normal(self) -> [i32; 3] {
+        let mut normal = [0; 3];
+        normal[self.axis()] = if self.is_positive() { 1 } else { -1 };
+        normal
+    }
+}
+
+// Layer of the block texture array used for a given face.
+pub fn texture_index(voxel_type: VoxelType, face: Face) -> u32 {
+    match (voxel_type, face) {
+        (VoxelType::Grass, Face::PosY) => 1,
+        (VoxelType::Grass, Face::NegY) => 2,
+        (VoxelType::Grass, _) => 0,
+        (VoxelType::TallGrass, _) => 1,
+        (VoxelType::Glass, _) => 3,
+        (VoxelType::Bedrock, _) => 4,
+        _ => 2,
+    }
+}
+
+// Texture coordinates for a point on a face, chosen so textures are upright
+// on the sides and not mirrored when seen from outside. They are in voxel
+// units, so the sampler must repeat to tile merged quads.
+pub fn face_tex_coords(face: Face, p: [f32; 3]) -> [f32; 2] {
+    match face {
+        Face::PosX => [-p[2], -p[1]],
+        Face::NegX => [p[2], -p[1]],
+        Face::PosY => [p[0], p[2]],
+        Face::NegY => [p[0], -p[2]],
+        Face::PosZ => [p[0], -p[1]],
+        Face::NegZ => [-p[0], -p[1]],
+    }
+}
+
+// The corners of the quad `ChunkMesh::push_face` emits, in order.
+pub fn quad_corners(
+    face: Face,
+    depth: f32,
+    [u, v]: [f32; 2],
+    [width, height]: [f32; 2],
+) -> [[f32; 3]; 4] {
+    let axis = face.axis();
+    let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+    let corner = |du: f32, dv: f32| {
+        let mut p = [0.0; 3];
+        p[axis] = depth;
+        p[ua] = u + du;
+        p[va] = v + dv;
+        p
+    };
+    let mut corners = [
+        corner(0.0, 0.0),
+        corner(width, 0.0),
+        corner(width, height),
+        corner(0.0, height),
+    ];
+    if !face.is_positive() {
+        corners.reverse();
+    }
+    corners
+}
+
+// Faces of transparent voxels share the vertex list but are indexed
+// separately, since they're drawn after everything opaque.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkMesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub transparent_indices: Vec<u32>,
+}
+
+impl ChunkMesh {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty() && self.transparent_indices.is_empty()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    // The smallest box around every vertex, or `None` if there are none.
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = self.vertices.first()?.position();
+        Some(
+            self.vertices
+                .iter()
+                .fold((first, first), |(min, max), vertex| {
+                    let p = vertex.position();
+                    (
+                        [0, 1, 2].map(|a| min[a].min(p[a])),
+                        [0, 1, 2].map(|a| max[a].max(p[a])),
+                    )
+                }),
+        )
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        (self.indices.len() + self.transparent_indices.len()) / 3
+    }
+
+    pub fn push_triangle(
+        &mut self,
+        corners: [[f32; 3]; 3],
+        normal: [f32; 3],
+        tex_coords: [[f32; 2]; 3],
+        tex_idx: u32,
+    ) {
+        let base = self.vertices.len() as u32;
+        for (position, tc) in corners.into_iter().zip(tex_coords) {
+            self.vertices
+                .push(Vertex::from_position(position, normal, tc, tex_idx));
+        }
+        self.indices.extend_from_slice(&[base, base + 1, base + 2]);
+    }
+
+    // Emits an axis-aligned quad on `face` lying in the plane `depth` along the
+    // face axis and covering `[u, u + width] x [v, v + height]` along the other
+    // two axes (in x -> y -> z -> x order). Quads wind counter-clockwise when
+    // seen from outside.
+    pub fn push_face(
+        &mut self,
+        face: Face,
+        depth: f32,
+        uv: [f32; 2],
+        size: [f32; 2],
+        voxel_type: VoxelType,
+    ) {
+        self.push_lit_face(face, depth, uv, size, voxel_type, [1.0; 4]);
+    }
+
+    // Like `push_face`, with the baked light of each corner given in the
+    // order `(u, v)`, `(u + width, v)`, `(u + width, v + height)`,
+    // `(u, v + height)`.
+    pub fn push_lit_face(
+        &mut self,
+        face: Face,
+        depth: f32,
+        [u, v]: [f32; 2],
+        [width, height]: [f32; 2],
+        voxel_type: VoxelType,
+        mut light: [f32; 4],
+    ) {
+        let corners = quad_corners(face, depth, [u, v], [width, height]);
+        if !face.is_positive() {
+            light.reverse();
+        }
+
+        let tex_idx = texture_index(voxel_type, face);
+        let normal = face.normal().map(|n| n as f32);
+        let base = self.vertices.len() as u32;
+        for (p, light) in corners.into_iter().zip(light) {
+            let mut vertex = Vertex::from_position(p, normal, face_tex_coords(face, p), tex_idx);
+            vertex.set_light(light);
+            self.vertices.push(vertex);
+        }
+        let indices = if voxel_type.is_transparent() {
+            &mut self.transparent_indices
+        } else {
+            &mut self.indices
+        };
+        // Light is interpolated across each triangle, so split the quad along
+        // its brighter diagonal. Otherwise a single occluded corner bleeds
+        // along the shared edge into both triangles, and identical corners
+        // shade differently depending on which way the quad faces.
+        if light[1] + light[3] > light[0] + light[2] {
+            indices.extend_from_slice(&[base, base + 1, base + 3, base + 1, base + 2, base + 3]);
+        } else {
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    // Scales every vertex about the chunk's origin.
+    pub fn scale(&mut self, factor: f32) {
+        for vertex in &mut self.vertices {
+            vertex.scale(factor);
+        }
+    }
+
+    // Adds `other`'s geometry, moved by `offset`.
+    pub fn append(&mut self, other: ChunkMesh, offset: [f32; 3]) {
+        let base = self.vertices.len() as u32;
+        self.vertices
+            .extend(other.vertices.into_iter().map(|mut vertex| {
+                vertex.translate(offset);
+                vertex
+            }));
+        self.indices
+            .extend(other.indices.into_iter().map(|i| base + i));
+        self.transparent_indices
+            .extend(other.transparent_indices.into_iter().map(|i| base + i));
+    }
+
+    // Sets every vertex's smooth normal to the average normal of all the
+    // vertices sharing its position. Flat normals are kept so the shader can
+    // switch between the two without remeshing.
+    pub fn compute_smooth_normals(&mut self) {
+        let key = |p: [f32; 3]| p.map(f32::to_bits);
+        let mut sums: HashMap<[u32; 3], [f32; 3]> = HashMap::new();
+        for vertex in &self.vertices {
+            let sum = sums.entry(key(vertex.position())).or_insert([0.0; 3]);
+            for (s, n) in sum.iter_mut().zip(vertex.normal()) {
+                *s += n;
+            }
+        }
+        for vertex in &mut self.vertices {
+            let [x, y, z] = sums[&key(vertex.position())];
+            let len = (x * x + y * y + z * z).sqrt();
+            if len > f32::EPSILON {
+                vertex.set_smooth_normal([x / len, y / len, z / len]);
+            }
+        }
+    }
+}
+
+pub trait Mesher: Send + Sync {
+    fn mesh(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh;
+
+    // Meshes vertically contiguous sections of a column, bottom first, into
+    // one mesh positioned relative to the lowest section. By default each
+    // section is meshed on its own and the results are stacked.
+    fn mesh_column(&self, sections: &[ChunkNeighborhood]) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        for (i, section) in sections.iter().enumerate() {
+            mesh.append(self.mesh(section), [0.0, (i * CHUNK_SIZE) as f32, 0.0]);
+        }
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+// Whether the face of the voxel at `pos` facing `face` is visible, i.e. the
+// voxel is solid and its neighbor in that direction doesn't hide it.
+fn face_visible(neighborhood: &ChunkNeighborhood, pos: [i32; 3], face: Face) -> bool {
+    let n = face.normal();
+    let this = neighborhood.voxel_type(pos[0], pos[1], pos[2]);
+    let next = neighborhood.voxel_type(pos[0] + n[0], pos[1] + n[1], pos[2] + n[2]);
+    this.is_solid() && !this.face_hidden_by(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{chunk::Chunk, voxel::Voxel};
+    use cgmath::{InnerSpace, Vector3};
+
+    // A single top face lit by `light`.
+    fn lit_quad(light: [f32; 4]) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        mesh.push_lit_face(Face::PosY, 1.0, [0.0; 2], [1.0; 2], VoxelType::Stone, light);
+        mesh
+    }
+
+    // The two triangles of the only quad in `mesh`, as vertex indices.
+    fn triangles(mesh: &ChunkMesh) -> [[u32; 3]; 2] {
+        [0, 1].map(|t| [0, 1, 2].map(|i| mesh.indices[t * 3 + i]))
+    }
+
+    // Front faces are counter-clockwise and backs are culled, so every
+    // triangle has to wind counter-clockwise seen from outside the voxel.
+    #[test]
+    fn every_face_winds_toward_its_normal() {
+        // Both ways of splitting a lit quad, and the unlit one.
+        for light in [[0.2, 1.0, 1.0, 1.0], [1.0, 0.2, 1.0, 1.0], [1.0; 4]] {
+            for face in Face::ALL {
+                let mut mesh = ChunkMesh::new();
+                let depth = face.is_positive() as i32 as f32;
+                mesh.push_lit_face(face, depth, [0.0; 2], [2.0, 3.0], VoxelType::Stone, light);
+                let normal = Vector3::from(face.normal().map(|n| n as f32));
+                for vertex in &mesh.vertices {
+                    assert_eq!(Vector3::from(vertex.normal()), normal, "{:?}", face);
+                }
+                for triangle in mesh.indices.chunks(3) {
+                    let [a, b, c] = [0, 1, 2]
+                        .map(|i| Vector3::from(mesh.vertices[triangle[i] as usize].position()));
+                    let winding = (b - a).cross(c - a).dot(normal);
+                    assert!(winding > 0.0, "{:?} winds inward: {:?}", face, triangle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quads_split_along_their_brighter_diagonal() {
+        // A dark corner, so the split keeps away from it.
+        let mesh = lit_quad([0.2, 1.0, 1.0, 1.0]);
+        assert_eq!(triangles(&mesh), [[0, 1, 3], [1, 2, 3]]);
+
+        let mesh = lit_quad([1.0, 0.2, 1.0, 1.0]);
+        assert_eq!(triangles(&mesh), [[0, 1, 2], [0, 2, 3]]);
+        let lights: Vec<_> = mesh.vertices.iter().map(|v| v.light()).collect();
+        assert_eq!(lights, [1.0, 0.2, 1.0, 1.0]);
+    }
+
+    fn half_filled_chunk() -> Chunk {
+        let mut chunk = Chunk::new(Vector3::new(0, 0, 0));
+        for y in 0..CHUNK_SIZE / 2 {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn every_mesher_meshes_a_half_filled_chunk() {
+        let chunk = half_filled_chunk();
+        let neighborhood = ChunkNeighborhood::from_faces(&chunk, [None; 6]);
+        let meshers: [&dyn Mesher; 3] = [&CulledMesher, &GreedyMesher, &MarchingCubesMesher];
+        for mesher in meshers {
+            let mesh = mesher.mesh(&neighborhood);
+            assert!(!mesh.is_empty());
+            let (min, max) = mesh.bounds().unwrap();
+            assert!(max[1] <= (CHUNK_SIZE / 2) as f32 + 1e-4);
+            assert!(min[1] >= -1e-4);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    ecs::chunk::{ChunkNeighborhood, CHUNK_SIZE},
+    gfx::mesh::{
+        face_visible,
+        lighting::{face_light, SkyMap},
+        ChunkMesh, Face, Mesher,
+    },
+};
+
+// One quad per visible voxel face. Simple and a useful reference for the
+// other meshers, but produces far more geometry than necessary.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CulledMesher;
+
+impl Mesher for CulledMesher {
+    fn mesh(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        let size = CHUNK_SIZE as i32;
+        let voxel_type = |[x, y, z]: [i32; 3]| neighborhood.voxel_type(x, y, z);
+        let sky = SkyMap::new(&voxel_type, [CHUNK_SIZE; 3], size * 2);
+        for y in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    let pos = [x, y, z];
+                    for face in Face::ALL {
+                        if !face_visible(neighborhood, pos, face) {
+                            continue;
+                        }
+                        let axis = face.axis();
+                        let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+                        let depth = pos[axis] + face.is_positive() as i32;
+                        mesh.push_lit_face(
+                            face,
+                            depth as f32,
+                            [pos[ua] as f32, pos[va] as f32],
+                            [1.0, 1.0],
+                            neighborhood.voxel_type(x, y, z),
+                            face_light(&voxel_type, &sky, pos, face),
+                        );
+                    }
+                }
+            }
+        }
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{
+        chunk::Chunk,
+        voxel::{Voxel, VoxelType},
+    };
+    use cgmath::Vector3;
+
+    fn glass_mesh(voxels: impl IntoIterator<Item = [usize; 3]>) -> ChunkMesh {
+        let mut chunk = Chunk::new(Vector3::new(0, 0, 0));
+        for [x, y, z] in voxels {
+            chunk.set(x, y, z, Voxel::new(VoxelType::Glass));
+        }
+        CulledMesher.mesh(&ChunkNeighborhood::from_faces(&chunk, [None; 6]))
+    }
+
+    fn faces(mesh: &ChunkMesh) -> usize {
+        mesh.transparent_indices.len() / 6
+    }
+
+    #[test]
+    fn hollow_glass_shows_its_inside() {
+        let shell = (1..4)
+            .flat_map(|y| (1..4).flat_map(move |z| (1..4).map(move |x| [x, y, z])))
+            .filter(|&p| p != [2, 2, 2]);
+        let mesh = glass_mesh(shell);
+        assert!(mesh.indices.is_empty());
+        // 9 on each side outside, and one facing into the hole from each
+        // side, which is the back wall seen through the front.
+        assert_eq!(faces(&mesh), 6 * 9 + 6);
+        let inward = mesh
+            .vertices
+            .iter()
+            .filter(|v| {
+                let p = v.position();
+                let into_hole = p.map(|c| c - 2.5);
+                p.iter().all(|c| (2.0..=3.0).contains(c))
+                    && (0..3).any(|a| v.normal()[a] * into_hole[a] < 0.0)
+            })
+            .count();
+        assert_eq!(inward, 6 * 4);
+    }
+
+    #[test]
+    fn touching_glass_shares_no_face() {
+        let mesh = glass_mesh([[1, 1, 1], [2, 1, 1]]);
+        assert_eq!(faces(&mesh), 10);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| v.position()[0] != 2.0 || v.normal()[0] == 0.0));
+    }
+}
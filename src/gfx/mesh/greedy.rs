@@ -0,0 +1,273 @@
+use crate::{
+    ecs::{
+        chunk::{ChunkNeighborhood, CHUNK_SIZE},
+        voxel::VoxelType,
+    },
+    gfx::mesh::{
+        lighting::{face_light, SkyMap},
+        ChunkMesh, Face, Mesher,
+    },
+};
+
+// Merges coplanar visible faces of the same voxel type and lighting into
+// larger quads, one slice at a time for each of the six face directions.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GreedyMesher;
+
+impl Mesher for GreedyMesher {
+    fn mesh(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        greedy(&mut mesh, [CHUNK_SIZE; 3], true, |[x, y, z]| {
+            neighborhood.voxel_type(x, y, z)
+        });
+        mesh.compute_smooth_normals();
+        mesh
+    }
+
+    // Meshes the whole column as one tall volume, so side faces also merge
+    // across section boundaries.
+    fn mesh_column(&self, sections: &[ChunkNeighborhood]) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        if sections.is_empty() {
+            return mesh;
+        }
+        let size = CHUNK_SIZE as i32;
+        let last = sections.len() as i32 - 1;
+        greedy(
+            &mut mesh,
+            [CHUNK_SIZE, CHUNK_SIZE * sections.len(), CHUNK_SIZE],
+            true,
+            |[x, y, z]| {
+                // Positions just outside the column are read through the end
+                // sections' neighbors.
+                let section = y.div_euclid(size).clamp(0, last);
+                sections[section as usize].voxel_type(x, y - section * size, z)
+            },
+        );
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+impl GreedyMesher {
+    // Like `mesh` without baked lighting, so faces only split where the
+    // voxel type changes.
+    pub fn mesh_unlit(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        greedy(&mut mesh, [CHUNK_SIZE; 3], false, |[x, y, z]| {
+            neighborhood.voxel_type(x, y, z)
+        });
+        mesh.compute_smooth_normals();
+        mesh
+    }
+
+    // Meshes the chunk with cells of `1 << level` voxels a side, level 0
+    // being full detail, for drawing far away. Levels past one cell for the
+    // whole chunk are clamped. Each cell takes the type of its topmost solid
+    // voxel, so a cell is solid wherever any of its voxels are. Cells in
+    // neighboring chunks only hide faces when all their voxels are opaque,
+    // so a neighbor meshed at a finer level can't leave holes along the
+    // border, only overlapping faces.
+    pub fn mesh_lod(&self, neighborhood: &ChunkNeighborhood, level: u32) -> ChunkMesh {
+        lod(neighborhood, level, true)
+    }
+
+    // Like `mesh_lod` without baked lighting.
+    pub fn mesh_lod_unlit(&self, neighborhood: &ChunkNeighborhood, level: u32) -> ChunkMesh {
+        lod(neighborhood, level, false)
+    }
+}
+
+// Cells around the chunk that are looked up when meshing, which covers the
+// neighbors lighting reads.
+const LOD_BORDER: i32 = 2;
+
+fn lod(neighborhood: &ChunkNeighborhood, level: u32, lit: bool) -> ChunkMesh {
+    let level = level.min(CHUNK_SIZE.trailing_zeros());
+    let scale = 1 << level;
+    let cells = CHUNK_SIZE as i32 / scale;
+    let width = cells + 2 * LOD_BORDER;
+    let index = |[x, y, z]: [i32; 3]| {
+        let [x, y, z] = [x, y, z].map(|v| v + LOD_BORDER);
+        ((y * width + z) * width + x) as usize
+    };
+
+    // Each cell is read many times while meshing, so work them all out once.
+    let mut types = vec![VoxelType::Air; (width * width * width) as usize];
+    for y in -LOD_BORDER..cells + LOD_BORDER {
+        for z in -LOD_BORDER..cells + LOD_BORDER {
+            for x in -LOD_BORDER..cells + LOD_BORDER {
+                let mut top = VoxelType::Air;
+                let mut opaque = true;
+                for dy in (0..scale).rev() {
+                    for dz in 0..scale {
+                        for dx in 0..scale {
+                            let voxel_type = neighborhood.voxel_type(
+                                x * scale + dx,
+                                y * scale + dy,
+                                z * scale + dz,
+                            );
+                            if top == VoxelType::Air {
+                                top = voxel_type;
+                            }
+                            opaque &= voxel_type.is_opaque();
+                        }
+                    }
+                }
+                let inside = [x, y, z].iter().all(|v| (0..cells).contains(v));
+                if inside || opaque {
+                    types[index([x, y, z])] = top;
+                }
+            }
+        }
+    }
+
+    let mut mesh = ChunkMesh::new();
+    let extent = cells as usize;
+    greedy(&mut mesh, [extent; 3], lit, |pos| {
+        if pos
+            .iter()
+            .all(|v| (-LOD_BORDER..cells + LOD_BORDER).contains(v))
+        {
+            types[index(pos)]
+        } else {
+            VoxelType::Air
+        }
+    });
+    mesh.compute_smooth_normals();
+    mesh.scale(scale as f32);
+    mesh
+}
+
+// Greedy meshing of the box `[0, extent)`, reading voxels (including one
+// past each side) through `voxel_type`.
+fn greedy(
+    mesh: &mut ChunkMesh,
+    extent: [usize; 3],
+    lit: bool,
+    voxel_type: impl Fn([i32; 3]) -> VoxelType,
+) {
+    let sky = lit.then(|| SkyMap::new(&voxel_type, extent, (extent[1] + CHUNK_SIZE) as i32));
+    for face in Face::ALL {
+        let axis = face.axis();
+        let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+        let (width_u, width_v) = (extent[ua], extent[va]);
+        let n = face.normal();
+        let mut mask: Vec<Option<(VoxelType, [f32; 4])>> = vec![None; width_u * width_v];
+
+        for slice in 0..extent[axis] {
+            for v in 0..width_v {
+                for u in 0..width_u {
+                    let mut pos = [0; 3];
+                    pos[axis] = slice as i32;
+                    pos[ua] = u as i32;
+                    pos[va] = v as i32;
+                    let this = voxel_type(pos);
+                    let next = voxel_type([pos[0] + n[0], pos[1] + n[1], pos[2] + n[2]]);
+                    mask[v * width_u + u] =
+                        (this.is_solid() && !this.face_hidden_by(next)).then(|| {
+                            let light = sky
+                                .as_ref()
+                                .map_or([1.0; 4], |sky| face_light(&voxel_type, sky, pos, face));
+                            (this, light)
+                        });
+                }
+            }
+
+            let depth = (slice + face.is_positive() as usize) as f32;
+            for v in 0..width_v {
+                let mut u = 0;
+                while u < width_u {
+                    let Some(cell) = mask[v * width_u + u] else {
+                        u += 1;
+                        continue;
+                    };
+
+                    let mut width = 1;
+                    while u + width < width_u && mask[v * width_u + u + width] == Some(cell) {
+                        width += 1;
+                    }
+
+                    let mut height = 1;
+                    'grow: while v + height < width_v {
+                        for du in 0..width {
+                            if mask[(v + height) * width_u + u + du] != Some(cell) {
+                                break 'grow;
+                            }
+                        }
+                        height += 1;
+                    }
+
+                    for dv in 0..height {
+                        for du in 0..width {
+                            mask[(v + dv) * width_u + u + du] = None;
+                        }
+                    }
+
+                    let (voxel_type, light) = cell;
+                    mesh.push_lit_face(
+                        face,
+                        depth,
+                        [u as f32, v as f32],
+                        [width as f32, height as f32],
+                        voxel_type,
+                        light,
+                    );
+                    u += width;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{chunk::Chunk, voxel::Voxel};
+    use cgmath::Vector3;
+
+    fn solid_chunk(y: i32) -> Chunk {
+        let mut chunk = Chunk::new(Vector3::new(0, y, 0));
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                }
+            }
+        }
+        chunk
+    }
+
+    fn quads(mesh: &ChunkMesh) -> usize {
+        mesh.triangle_count() / 2
+    }
+
+    #[test]
+    fn column_meshing_merges_across_sections() {
+        let column: Vec<_> = (0..4).map(solid_chunk).collect();
+        let sections: Vec<_> = (0..column.len())
+            .map(|i| {
+                let above = column.get(i + 1);
+                let below = i.checked_sub(1).map(|i| &column[i]);
+                ChunkNeighborhood::from_faces(&column[i], [None, None, above, below, None, None])
+            })
+            .collect();
+
+        let stacked: usize = sections
+            .iter()
+            .map(|section| quads(&GreedyMesher.mesh(section)))
+            .sum();
+        let merged = GreedyMesher.mesh_column(&sections);
+        // Baked light still splits some faces, but the sides no longer stop
+        // at every section boundary.
+        assert!(
+            quads(&merged) < stacked,
+            "{} vs {}",
+            quads(&merged),
+            stacked
+        );
+        let (min, max) = merged.bounds().unwrap();
+        assert_eq!(min, [0.0; 3]);
+        assert_eq!(max, [16.0, 64.0, 16.0]);
+    }
+}
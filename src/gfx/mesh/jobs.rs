@@ -0,0 +1,229 @@
+use super::{ChunkMesh, GreedyMesher, Mesher};
+use crate::ecs::{
+    chunk::{Chunk, ChunkNeighborhood},
+    World,
+};
+use cgmath::Vector3;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    crate::workers,
+    std::sync::mpsc::{self, Receiver, Sender},
+};
+
+// A job's id, position and level of detail alongside the mesh it built.
+#[cfg(not(target_arch = "wasm32"))]
+type Finished = (u64, Vector3<i32>, u32, ChunkMesh);
+
+// A snapshot of a chunk and its neighbors, taken so it can be meshed while
+// the world keeps changing. The chunks are shared with the world rather than
+// copied, and the world copies any it edits while the job still holds them.
+// Whole neighbors are kept rather than just their border voxels, since
+// skylight looks down entire columns of them.
+struct Job {
+    id: u64,
+    position: Vector3<i32>,
+    lod: u32,
+    chunks: [Option<Arc<Chunk>>; 27],
+    mesher: Arc<dyn Mesher>,
+}
+
+impl Job {
+    fn mesh(&self) -> ChunkMesh {
+        let Some(center) = &self.chunks[13] else {
+            return ChunkMesh::new();
+        };
+        let neighborhood =
+            ChunkNeighborhood::new(center, self.chunks.each_ref().map(Option::as_deref));
+        // Coarser levels are always greedy meshed, whatever the world's
+        // mesher.
+        if self.lod > 0 {
+            GreedyMesher.mesh_lod(&neighborhood, self.lod)
+        } else {
+            self.mesher.mesh(&neighborhood)
+        }
+    }
+}
+
+// A chunk mesh built off the main thread, ready to be uploaded.
+pub struct MeshResult {
+    pub position: Vector3<i32>,
+    pub lod: u32,
+    pub mesh: ChunkMesh,
+}
+
+// Builds chunk meshes on the shared worker threads. Jobs only read the
+// chunks they share with the world and hand back plain meshes, so everything
+// on the GPU is still created on the main thread. On the web, where there
+// are no threads, meshes are built as soon as they're queued instead.
+pub struct MeshJobQueue {
+    #[cfg(not(target_arch = "wasm32"))]
+    mesh_sender: Sender<Finished>,
+    #[cfg(not(target_arch = "wasm32"))]
+    meshes: Receiver<Finished>,
+    next_id: u64,
+    // The newest job queued for each chunk. Results from older jobs are
+    // dropped, so a mesh never replaces one built from newer voxels.
+    latest: HashMap<Vector3<i32>, u64>,
+    ready: VecDeque<MeshResult>,
+    // How many finished meshes may be uploaded per frame, so a burst of
+    // them doesn't all get uploaded at once.
+    pub max_uploads_per_frame: usize,
+}
+
+impl Default for MeshJobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeshJobQueue {
+    pub fn new() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let (mesh_sender, meshes) = mpsc::channel();
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            mesh_sender,
+            #[cfg(not(target_arch = "wasm32"))]
+            meshes,
+            next_id: 0,
+            latest: HashMap::new(),
+            ready: VecDeque::new(),
+            max_uploads_per_frame: 16,
+        }
+    }
+
+    // Whether the chunk has a mesh on the way that hasn't been taken with
+    // `pop_ready` yet.
+    pub fn is_pending(&self, position: Vector3<i32>) -> bool {
+        self.latest.contains_key(&position)
+            || self.ready.iter().any(|result| result.position == position)
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.latest.len() + self.ready.len()
+    }
+
+    // Snapshots the chunk at `position` and its neighbors and queues it to
+    // be meshed at `lod`, replacing any job already queued for it. Missing
+    // neighbors mesh as air. Returns false if the chunk isn't loaded.
+    pub fn queue(&mut self, world: &World, position: Vector3<i32>, lod: u32) -> bool {
+        let Some(chunks) = world.shared_neighborhood(position) else {
+            self.cancel(position);
+            return false;
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.latest.insert(position, id);
+        let job = Job {
+            id,
+            position,
+            lod,
+            chunks,
+            mesher: Arc::clone(world.mesher()),
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mesh_sender = self.mesh_sender.clone();
+            workers::spawn(move || {
+                let mesh = job.mesh();
+                mesh_sender.send((job.id, job.position, job.lod, mesh)).ok();
+            });
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mesh = job.mesh();
+            self.finish(job.id, job.position, job.lod, mesh);
+        }
+        true
+    }
+
+    // Forgets the job queued for `position`, if any. Its mesh is dropped
+    // when it comes back.
+    pub fn cancel(&mut self, position: Vector3<i32>) {
+        self.latest.remove(&position);
+        self.ready.retain(|result| result.position != position);
+    }
+
+    fn finish(&mut self, id: u64, position: Vector3<i32>, lod: u32, mesh: ChunkMesh) {
+        if self.latest.get(&position) == Some(&id) {
+            self.latest.remove(&position);
+            self.ready.push_back(MeshResult {
+                position,
+                lod,
+                mesh,
+            });
+        }
+    }
+
+    // Takes the next finished mesh, oldest first. Limiting how many are
+    // uploaded per frame is up to the caller.
+    pub fn pop_ready(&mut self) -> Option<MeshResult> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let finished = self.meshes.try_iter().collect::<Vec<_>>();
+            for (id, position, lod, mesh) in finished {
+                self.finish(id, position, lod, mesh);
+            }
+        }
+        self.ready.pop_front()
+    }
+
+    // Forgets every job, e.g. when the world is cleared.
+    pub fn clear(&mut self) {
+        self.latest.clear();
+        self.ready.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::voxel::{Voxel, VoxelType};
+
+    fn wait_for(queue: &mut MeshJobQueue) -> MeshResult {
+        let started = std::time::Instant::now();
+        loop {
+            if let Some(result) = queue.pop_ready() {
+                return result;
+            }
+            assert!(started.elapsed().as_secs() < 10, "mesh never came back");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn jobs_mesh_the_world_as_it_was_when_queued() {
+        let mut world = World::new();
+        let position = Vector3::new(0, 0, 0);
+        world.add_chunk(Chunk::new(position));
+        world.set_voxel(Vector3::new(1, 1, 1), Voxel::new(VoxelType::Stone));
+        let mut queue = MeshJobQueue::new();
+        assert!(queue.queue(&world, position, 0));
+        // Only the world's copy of the chunk sees this.
+        world.set_voxel(Vector3::new(8, 8, 8), Voxel::new(VoxelType::Stone));
+
+        let result = wait_for(&mut queue);
+        assert_eq!(result.position, position);
+        assert_eq!(result.mesh.triangle_count(), 12);
+        assert!(world.is_solid(Vector3::new(8, 8, 8)));
+    }
+
+    #[test]
+    fn newer_jobs_replace_older_ones() {
+        let mut world = World::new();
+        let position = Vector3::new(0, 0, 0);
+        world.add_chunk(Chunk::new(position));
+        let mut queue = MeshJobQueue::new();
+        queue.queue(&world, position, 0);
+        world.set_voxel(Vector3::new(1, 1, 1), Voxel::new(VoxelType::Stone));
+        queue.queue(&world, position, 0);
+
+        assert_eq!(wait_for(&mut queue).mesh.triangle_count(), 12);
+        assert_eq!(queue.pending_count(), 0);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{ecs::voxel::VoxelType, gfx::mesh::Face};
+
+// How much a corner with all three neighbors opaque is darkened.
+pub const AO_STRENGTH: f32 = 0.6;
+// Skylight left under an overhang, as a fraction of open sky.
+pub const SHADOW_SKYLIGHT: f32 = 0.5;
+const BORDER: usize = 2;
+
+// Combines skylight with ambient occlusion into the single light term the
+// shader multiplies each vertex's color by. `ao` is the fraction of the
+// corner's neighbors that are opaque, from 0 (open) to 1 (fully occluded).
+pub fn vertex_light(skylight: f32, ao: f32) -> f32 {
+    skylight * (1.0 - AO_STRENGTH * ao)
+}
+
+// The highest opaque voxel of each column of the box `[0, extent)` plus a
+// two voxel border, which covers every cell `face_light` looks at, so
+// skylight lookups don't rescan the column every time.
+pub struct SkyMap {
+    width: usize,
+    tops: Vec<i32>,
+}
+
+impl SkyMap {
+    // Columns are scanned from just below `sky_top`, above which everything
+    // is assumed to be open sky.
+    pub fn new(
+        voxel_type: &impl Fn([i32; 3]) -> VoxelType,
+        extent: [usize; 3],
+        sky_top: i32,
+    ) -> Self {
+        let (width, depth) = (extent[0] + 2 * BORDER, extent[2] + 2 * BORDER);
+        let mut tops = vec![i32::MIN; width * depth];
+        for z in 0..depth {
+            for x in 0..width {
+                let (vx, vz) = (x as i32 - BORDER as i32, z as i32 - BORDER as i32);
+                if let Some(y) = (-1..sky_top)
+                    .rev()
+                    .find(|&y| voxel_type([vx, y, vz]).is_opaque())
+                {
+                    tops[z * width + x] = y;
+                }
+            }
+        }
+        Self { width, tops }
+    }
+
+    pub fn skylight(&self, [x, y, z]: [i32; 3]) -> f32 {
+        let index = (z + BORDER as i32) as usize * self.width + (x + BORDER as i32) as usize;
+        if y > self.tops[index] {
+            1.0
+        } else {
+            SHADOW_SKYLIGHT
+        }
+    }
+}
+
+// Light for the four corners of the face of the voxel at `pos`, in the order
+// `ChunkMesh::push_lit_face` takes them. Each corner looks at the cell in
+// front of the face and the three cells around it that touch the corner.
+pub fn face_light(
+    voxel_type: &impl Fn([i32; 3]) -> VoxelType,
+    sky: &SkyMap,
+    pos: [i32; 3],
+    face: Face,
+) -> [f32; 4] {
+    let axis = face.axis();
+    let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+    let n = face.normal();
+    let front = [pos[0] + n[0], pos[1] + n[1], pos[2] + n[2]];
+    let offset = |du: i32, dv: i32| {
+        let mut p = front;
+        p[ua] += du;
+        p[va] += dv;
+        p
+    };
+
+    [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(du, dv)| {
+        let (su, sv) = (du * 2 - 1, dv * 2 - 1);
+        let cells = [offset(su, 0), offset(0, sv), offset(su, sv)];
+        let opaque = cells.map(|c| voxel_type(c).is_opaque());
+        // With both sides blocked the diagonal can't be seen around them.
+        let hidden = [opaque[0], opaque[1], opaque[2] || (opaque[0] && opaque[1])];
+        let occlusion = hidden.iter().filter(|&&h| h).count();
+
+        let mut skylight = sky.skylight(front);
+        let mut seen = 1;
+        for (cell, hidden) in cells.into_iter().zip(hidden) {
+            if !hidden {
+                skylight += sky.skylight(cell);
+                seen += 1;
+            }
+        }
+        vertex_light(skylight / seen as f32, occlusion as f32 / 3.0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A stone floor at y = 0 under a stone roof at y = 3 if `roofed`, with
+    // the walls of a corner standing on the floor at x = -1 and z = -1.
+    fn room(roofed: bool, walled: bool) -> impl Fn([i32; 3]) -> VoxelType {
+        move |[x, y, z]: [i32; 3]| {
+            let wall = walled && y == 1 && (x == -1 || z == -1);
+            if y == 0 || (roofed && y == 3) || wall {
+                VoxelType::Stone
+            } else {
+                VoxelType::Air
+            }
+        }
+    }
+
+    fn top_light(roofed: bool, walled: bool) -> [f32; 4] {
+        let voxel_type = room(roofed, walled);
+        let sky = SkyMap::new(&voxel_type, [4, 4, 4], 8);
+        face_light(&voxel_type, &sky, [0, 0, 0], Face::PosY)
+    }
+
+    #[test]
+    fn shadowed_occluded_corner_is_darker_by_both_factors() {
+        assert_eq!(top_light(false, false), [1.0; 4]);
+
+        // The first corner is the one between both walls.
+        let corner = top_light(true, true)[0];
+        let expected = SHADOW_SKYLIGHT * (1.0 - AO_STRENGTH);
+        assert!((corner - expected).abs() < 1e-6, "{corner} != {expected}");
+    }
+
+    // Open sky over a floor, with stone in front of the top face of the
+    // voxel at the origin wherever `blocked` says, around its first corner.
+    fn first_corner_with(blocked: &[[i32; 3]]) -> f32 {
+        let voxel_type = |p: [i32; 3]| {
+            if p[1] == 0 || blocked.contains(&p) {
+                VoxelType::Stone
+            } else {
+                VoxelType::Air
+            }
+        };
+        let sky = SkyMap::new(&voxel_type, [4, 4, 4], 8);
+        face_light(&voxel_type, &sky, [0, 0, 0], Face::PosY)[0]
+    }
+
+    #[test]
+    fn corner_ao_counts_the_blocked_neighbors() {
+        let (side_a, side_b, diagonal) = ([0, 1, -1], [-1, 1, 0], [-1, 1, -1]);
+        let level = |occluded: f32| 1.0 - AO_STRENGTH * occluded / 3.0;
+        let cases: [(&[[i32; 3]], f32); 5] = [
+            (&[], level(0.0)),
+            (&[diagonal], level(1.0)),
+            (&[side_a], level(1.0)),
+            (&[side_a, diagonal], level(2.0)),
+            // Both sides hide the diagonal, so it counts as blocked too.
+            (&[side_a, side_b], level(3.0)),
+        ];
+        for (blocked, expected) in cases {
+            let light = first_corner_with(blocked);
+            assert!(
+                (light - expected).abs() < 1e-6,
+                "{:?}: {} != {}",
+                blocked,
+                light,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn ao_and_skylight_apply_on_their_own() {
+        let walled = top_light(false, true)[0];
+        assert!((walled - (1.0 - AO_STRENGTH)).abs() < 1e-6, "{walled}");
+        assert_eq!(top_light(true, false), [SHADOW_SKYLIGHT; 4]);
+    }
+}
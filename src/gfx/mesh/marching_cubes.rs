@@ -0,0 +1,155 @@
+use crate::{
+    ecs::{
+        chunk::{ChunkNeighborhood, CHUNK_SIZE},
+        voxel::VoxelType,
+    },
+    gfx::mesh::{texture_index, ChunkMesh, Face, Mesher},
+};
+
+// Each cell is split into six tetrahedra around its main diagonal (corner 0
+// to corner 7, where bit 0 of a corner index is +x, bit 1 is +y and bit 2 is
+// +z). Marching the tetrahedra gives the same smooth surface as classic
+// marching cubes without needing the 256-case lookup table, and has no
+// ambiguous cases.
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+// Builds a smooth surface through the boundary between solid and empty
+// voxels. Samples sit at voxel centers, so a chunk owns the cells whose
+// lowest corner lies inside it and reads one voxel into its +x/+y/+z
+// neighbors to stay seamless.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MarchingCubesMesher;
+
+struct Corner {
+    position: [f32; 3],
+    voxel_type: VoxelType,
+}
+
+impl Mesher for MarchingCubesMesher {
+    fn mesh(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        let size = CHUNK_SIZE as i32;
+        for y in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    let corners: [Corner; 8] = std::array::from_fn(|i| {
+                        let (cx, cy, cz) = (
+                            x + (i & 1) as i32,
+                            y + ((i >> 1) & 1) as i32,
+                            z + ((i >> 2) & 1) as i32,
+                        );
+                        Corner {
+                            position: [cx as f32 + 0.5, cy as f32 + 0.5, cz as f32 + 0.5],
+                            voxel_type: neighborhood.voxel_type(cx, cy, cz),
+                        }
+                    });
+                    if corners.iter().all(|c| c.voxel_type.is_solid())
+                        || corners.iter().all(|c| !c.voxel_type.is_solid())
+                    {
+                        continue;
+                    }
+                    for tetrahedron in TETRAHEDRA {
+                        march_tetrahedron(&mut mesh, tetrahedron.map(|i| &corners[i]));
+                    }
+                }
+            }
+        }
+        mesh.compute_smooth_normals();
+        mesh
+    }
+}
+
+fn midpoint(a: &Corner, b: &Corner) -> [f32; 3] {
+    std::array::from_fn(|i| (a.position[i] + b.position[i]) * 0.5)
+}
+
+fn centroid(corners: &[&Corner]) -> [f32; 3] {
+    let n = corners.len() as f32;
+    std::array::from_fn(|i| corners.iter().map(|c| c.position[i]).sum::<f32>() / n)
+}
+
+fn march_tetrahedron(mesh: &mut ChunkMesh, corners: [&Corner; 4]) {
+    let (inside, outside): (Vec<&Corner>, Vec<&Corner>) =
+        corners.iter().partition(|c| c.voxel_type.is_solid());
+    let voxel_type = match inside.first() {
+        Some(corner) if !outside.is_empty() => corner.voxel_type,
+        _ => return,
+    };
+
+    // Triangles must face from the solid side towards the empty side.
+    let (a, b) = (centroid(&inside), centroid(&outside));
+    let outward = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+
+    match (inside.as_slice(), outside.as_slice()) {
+        ([a], [b, c, d]) | ([b, c, d], [a]) => {
+            emit(
+                mesh,
+                [midpoint(a, b), midpoint(a, c), midpoint(a, d)],
+                outward,
+                voxel_type,
+            );
+        }
+        ([a, b], [c, d]) => {
+            let (ac, ad, bd, bc) = (
+                midpoint(a, c),
+                midpoint(a, d),
+                midpoint(b, d),
+                midpoint(b, c),
+            );
+            emit(mesh, [ac, ad, bd], outward, voxel_type);
+            emit(mesh, [ac, bd, bc], outward, voxel_type);
+        }
+        _ => {}
+    }
+}
+
+fn emit(
+    mesh: &mut ChunkMesh,
+    mut triangle: [[f32; 3]; 3],
+    outward: [f32; 3],
+    voxel_type: VoxelType,
+) {
+    let [p0, p1, p2] = triangle;
+    let e1 = [p1[0] - p0[0], p1[1] - p0[1], p1[2] - p0[2]];
+    let e2 = [p2[0] - p0[0], p2[1] - p0[1], p2[2] - p0[2]];
+    let mut normal = [
+        e1[1] * e2[2] - e1[2] * e2[1],
+        e1[2] * e2[0] - e1[0] * e2[2],
+        e1[0] * e2[1] - e1[1] * e2[0],
+    ];
+    if normal[0] * outward[0] + normal[1] * outward[1] + normal[2] * outward[2] < 0.0 {
+        triangle.swap(1, 2);
+        normal = normal.map(|n| -n);
+    }
+
+    // Project the texture along the dominant axis of the normal.
+    let axis = (0..3)
+        .max_by(|&i, &j| normal[i].abs().total_cmp(&normal[j].abs()))
+        .unwrap_or(1);
+    let face = match (axis, normal[axis] >= 0.0) {
+        (0, _) => Face::PosX,
+        (1, true) => Face::PosY,
+        (1, false) => Face::NegY,
+        _ => Face::PosZ,
+    };
+    let tex_coords = triangle.map(|p| match axis {
+        0 => [p[2], -p[1]],
+        1 => [p[0], p[2]],
+        _ => [p[0], -p[1]],
+    });
+    let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    let normal = normal.map(|n| n / len.max(f32::EPSILON));
+    mesh.push_triangle(
+        triangle,
+        normal,
+        tex_coords,
+        texture_index(voxel_type, face),
+    );
+}
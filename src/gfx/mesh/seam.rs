@@ -0,0 +1,257 @@
+use crate::{
+    ecs::{chunk::CHUNK_SIZE, World},
+    gfx::mesh::{ChunkMesh, Face, Mesher},
+};
+use anyhow::{ensure, Result};
+use cgmath::Vector3;
+use std::collections::HashMap;
+
+const EPSILON: f32 = 1e-4;
+
+// A voxel face on a chunk boundary that's covered the wrong number of
+// times. `found > expected` means overlapping faces (z-fighting) and
+// `found < expected` a hole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeamIssue {
+    // World position of the voxel on the low side of the boundary.
+    pub position: Vector3<i32>,
+    pub face: Face,
+    pub expected: usize,
+    pub found: usize,
+}
+
+// Meshes the face-adjacent chunks `a` and `b` with `mesher` and checks that
+// every voxel face on their shared boundary is covered exactly once when it
+// should be visible and not at all otherwise. Only meaningful for meshers
+// that emit axis-aligned faces on voxel boundaries.
+pub fn check_seam(
+    world: &World,
+    mesher: &dyn Mesher,
+    a: Vector3<i32>,
+    b: Vector3<i32>,
+) -> Result<Vec<SeamIssue>> {
+    let d = b - a;
+    ensure!(
+        d.x.abs() + d.y.abs() + d.z.abs() == 1,
+        "chunks {:?} and {:?} aren't face neighbors",
+        a,
+        b
+    );
+    let (Some(na), Some(nb)) = (world.neighborhood(a), world.neighborhood(b)) else {
+        anyhow::bail!("both chunks must be loaded");
+    };
+    let size = CHUNK_SIZE as i32;
+    let axis = (0..3).find(|&i| d[i] != 0).unwrap();
+    let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+    // The boundary plane, in `a`'s local coordinates.
+    let plane = if d[axis] > 0 { size } else { 0 };
+
+    // Coverage of each boundary cell by faces pointing up or down the axis.
+    let mut coverage: HashMap<(i32, i32, bool), usize> = HashMap::new();
+    let b_offset = (d * size).cast::<f32>().unwrap();
+    for (mesh, offset) in [
+        (mesher.mesh(&na), Vector3::new(0.0, 0.0, 0.0)),
+        (mesher.mesh(&nb), b_offset),
+    ] {
+        rasterize_plane(&mesh, offset, axis, plane as f32, &mut coverage);
+    }
+
+    let origin = a * size;
+    let mut issues = vec![];
+    for v in 0..size {
+        for u in 0..size {
+            let mut low = origin;
+            low[axis] += plane - 1;
+            low[ua] += u;
+            low[va] += v;
+            let mut high = low;
+            high[axis] += 1;
+            let voxel_type = |p| world.voxel_at(p).unwrap_or_default().voxel_type();
+            let (low_type, high_type) = (voxel_type(low), voxel_type(high));
+
+            for (positive, visible) in [
+                (
+                    true,
+                    low_type.is_solid() && !low_type.face_hidden_by(high_type),
+                ),
+                (
+                    false,
+                    high_type.is_solid() && !high_type.face_hidden_by(low_type),
+                ),
+            ] {
+                let expected = visible as usize;
+                let found = coverage.get(&(u, v, positive)).copied().unwrap_or(0);
+                if found != expected {
+                    let face = match (axis, positive) {
+                        (0, true) => Face::PosX,
+                        (0, false) => Face::NegX,
+                        (1, true) => Face::PosY,
+                        (1, false) => Face::NegY,
+                        (_, true) => Face::PosZ,
+                        (_, false) => Face::NegZ,
+                    };
+                    issues.push(SeamIssue {
+                        position: low,
+                        face,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+    }
+    Ok(issues)
+}
+
+// Counts how many of `mesh`'s triangles lying in the plane `axis = plane`
+// cover the center of each unit cell of it.
+fn rasterize_plane(
+    mesh: &ChunkMesh,
+    offset: Vector3<f32>,
+    axis: usize,
+    plane: f32,
+    coverage: &mut HashMap<(i32, i32, bool), usize>,
+) {
+    let (ua, va) = ((axis + 1) % 3, (axis + 2) % 3);
+    let triangles = mesh.indices.chunks_exact(3);
+    for triangle in triangles.chain(mesh.transparent_indices.chunks_exact(3)) {
+        let vertices = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+        let corners = vertices.map(|v| Vector3::from(v.position()) + offset);
+        if corners.iter().any(|c| (c[axis] - plane).abs() > EPSILON) {
+            continue;
+        }
+        let positive = vertices[0].normal()[axis] > 0.0;
+        let points = corners.map(|c| [c[ua], c[va]]);
+        let min =
+            [0, 1].map(|i| points.iter().map(|p| p[i]).fold(f32::MAX, f32::min).floor() as i32);
+        let max =
+            [0, 1].map(|i| points.iter().map(|p| p[i]).fold(f32::MIN, f32::max).ceil() as i32);
+        for v in min[1]..max[1] {
+            for u in min[0]..max[0] {
+                if contains(points, [u as f32 + 0.5, v as f32 + 0.5]) {
+                    *coverage.entry((u, v, positive)).or_default() += 1;
+                }
+            }
+        }
+    }
+}
+
+// Whether `p` is inside the 2D triangle, regardless of its winding. Points
+// on an edge count for exactly one of the two triangles sharing it, so a quad
+// split along its diagonal covers every cell once.
+fn contains([a, mut b, mut c]: [[f32; 2]; 3], p: [f32; 2]) -> bool {
+    let cross = |a: [f32; 2], b: [f32; 2], p: [f32; 2]| {
+        (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+    };
+    if cross(a, b, c) < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+    }
+    [(a, b), (b, c), (c, a)].into_iter().all(|(from, to)| {
+        let e = cross(from, to, p);
+        let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+        e > 0.0 || (e == 0.0 && (dy > 0.0 || (dy == 0.0 && dx < 0.0)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ecs::{
+            chunk::{Chunk, ChunkNeighborhood},
+            voxel::{Voxel, VoxelType},
+        },
+        gfx::mesh::GreedyMesher,
+    };
+
+    // Meshes each chunk as if it had no neighbors, so solid chunks both
+    // draw the faces between them.
+    struct IsolatedMesher;
+
+    impl Mesher for IsolatedMesher {
+        fn mesh(&self, neighborhood: &ChunkNeighborhood) -> ChunkMesh {
+            GreedyMesher.mesh(&ChunkNeighborhood::from_faces(
+                neighborhood.center(),
+                [None; 6],
+            ))
+        }
+    }
+
+    // Leaves out everything, so every face that should be drawn is a hole.
+    struct EmptyMesher;
+
+    impl Mesher for EmptyMesher {
+        fn mesh(&self, _: &ChunkNeighborhood) -> ChunkMesh {
+            ChunkMesh::new()
+        }
+    }
+
+    // Two chunks side by side along x, the first solid below `height` and
+    // the second solid below `height - 4`, so the boundary has both shared
+    // and exposed faces.
+    fn world(height: usize) -> World {
+        let mut world = World::new();
+        for (x, top) in [(0, height), (1, height - 4)] {
+            let mut chunk = Chunk::new(Vector3::new(x, 0, 0));
+            for y in 0..top {
+                for z in 0..CHUNK_SIZE {
+                    for x in 0..CHUNK_SIZE {
+                        chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                    }
+                }
+            }
+            world.add_chunk(chunk);
+        }
+        world
+    }
+
+    #[test]
+    fn correct_boundaries_pass() {
+        let world = world(10);
+        let (a, b) = (Vector3::new(0, 0, 0), Vector3::new(1, 0, 0));
+        assert_eq!(check_seam(&world, &GreedyMesher, a, b).unwrap(), []);
+        assert_eq!(check_seam(&world, &GreedyMesher, b, a).unwrap(), []);
+    }
+
+    #[test]
+    fn overlapping_faces_are_flagged() {
+        let world = world(10);
+        let issues = check_seam(
+            &world,
+            &IsolatedMesher,
+            Vector3::new(0, 0, 0),
+            Vector3::new(1, 0, 0),
+        )
+        .unwrap();
+        // Both faces of each cell in the 6 layers solid on either side.
+        assert_eq!(issues.len(), 2 * 6 * CHUNK_SIZE);
+        assert!(issues
+            .iter()
+            .all(|issue| issue.expected == 0 && issue.found == 1));
+        assert!(issues.iter().all(|issue| issue.position.y < 6));
+    }
+
+    #[test]
+    fn holes_are_flagged() {
+        let world = world(10);
+        let issues = check_seam(
+            &world,
+            &EmptyMesher,
+            Vector3::new(0, 0, 0),
+            Vector3::new(1, 0, 0),
+        )
+        .unwrap();
+        // The first chunk's 4 layers that stick out past the second.
+        assert_eq!(issues.len(), 4 * CHUNK_SIZE);
+        assert!(issues
+            .iter()
+            .all(|issue| issue.face == Face::PosX && issue.expected == 1 && issue.found == 0));
+    }
+
+    #[test]
+    fn only_face_neighbors_can_be_checked() {
+        let world = world(10);
+        let diagonal = Vector3::new(1, 1, 0);
+        assert!(check_seam(&world, &GreedyMesher, Vector3::new(0, 0, 0), diagonal).is_err());
+    }
+}
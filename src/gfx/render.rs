@@ -1,4 +1,17 @@
+pub mod atlas;
 pub mod camera;
+pub mod chunk_borders;
+pub mod config;
+pub mod debug;
+pub mod frame_time;
+pub mod frustum;
+pub mod held;
+pub mod hud;
+pub mod light;
+pub mod present;
+pub mod readback;
 pub mod renderer;
+pub mod screenshot;
+pub mod text;
 pub mod texture;
 pub mod vertex;
\ No newline at end of file
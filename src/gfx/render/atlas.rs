@@ -0,0 +1,108 @@
+use crate::{
+    ecs::voxel::VoxelType,
+    gfx::{
+        mesh::{face_tex_coords, quad_corners, texture_index, Face},
+        render::texture::Texture,
+    },
+};
+use anyhow::{ensure, Result};
+
+// The voxel textures. Tiles are square and stacked vertically in the
+// image, and each becomes a layer of one array texture, so merged faces
+// can repeat a tile without bleeding into its neighbors.
+pub struct TextureAtlas {
+    texture: Texture,
+    tile_count: u32,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl TextureAtlas {
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Self> {
+        let texture = Texture::from_bytes(device, queue, bytes, label)?;
+        let tile_count = texture.texture.depth_or_array_layers();
+        let needed = VoxelType::ALL
+            .iter()
+            .flat_map(|&voxel_type| Face::ALL.map(|face| texture_index(voxel_type, face)))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        ensure!(
+            tile_count >= needed,
+            "{} has {} tiles but voxels use {}",
+            label,
+            tile_count,
+            needed
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // This should match the filterable field of the
+                    // corresponding Texture entry above.
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("texture_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some("diffuse_bind_group"),
+        });
+
+        Ok(Self {
+            texture,
+            tile_count,
+            bind_group_layout,
+            bind_group,
+        })
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.tile_count
+    }
+
+    // The tile drawn on `face` of `voxel_type`, e.g. grass has separate top,
+    // side and bottom tiles.
+    pub fn tile_for(&self, voxel_type: VoxelType, face: Face) -> u32 {
+        texture_index(voxel_type, face)
+    }
+
+    // Texture coordinates of a single voxel face's corners, in the order the
+    // mesher emits them. They span one whole tile; which tile is `tile_for`.
+    pub fn uv_for(&self, _voxel_type: VoxelType, face: Face) -> [[f32; 2]; 4] {
+        quad_corners(face, 0.0, [0.0, 0.0], [1.0, 1.0]).map(|p| face_tex_coords(face, p))
+    }
+}
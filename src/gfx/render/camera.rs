@@ -1,3 +1,5 @@
+use super::frustum::Frustum;
+use crate::input::{default_action, Action};
 use cgmath::*;
 use instant::Duration;
 use std::f32::consts::FRAC_PI_2;
@@ -13,7 +15,10 @@ pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
 );
 
 const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
+// How far the camera turns per pixel of mouse movement at a sensitivity of 1.
+const RADIANS_PER_PIXEL: f32 = 1.0 / 60.0;
 
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub position: Point3<f32>,
     yaw: Rad<f32>,
@@ -33,21 +38,68 @@ impl Camera {
         }
     }
 
-    pub fn calc_matrix(&self) -> Matrix4<f32> {
+    pub fn yaw(&self) -> Rad<f32> {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> Rad<f32> {
+        self.pitch
+    }
+
+    // Unit vector the camera is looking along.
+    pub fn forward(&self) -> Vector3<f32> {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        Vector3::new(cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw).normalize()
+    }
+
+    // Turns the camera's local axes into world ones, with -z looking along
+    // `forward`.
+    pub fn rotation(&self) -> Quaternion<f32> {
+        Quaternion::from_angle_y(-self.yaw) * Quaternion::from_angle_x(self.pitch)
+    }
+
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.position, self.forward(), Vector3::unit_y())
+    }
+
+    pub fn view_proj(&self, projection: &Projection) -> Matrix4<f32> {
+        projection.calc_matrix() * self.calc_matrix()
+    }
+
+    pub fn frustum(&self, projection: &Projection) -> Frustum {
+        Frustum::from_view_proj(self.view_proj(projection))
+    }
+}
+
+// Which extent of the view the field of view angle describes. A horizontal
+// FOV keeps the sideways view constant on wide screens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FovMode {
+    Vertical(Rad<f32>),
+    Horizontal(Rad<f32>),
+}
+
+impl FovMode {
+    pub fn vertical(self, aspect: f32) -> Rad<f32> {
+        match self {
+            FovMode::Vertical(fovy) => fovy,
+            FovMode::Horizontal(fovx) => Rad(2.0 * ((fovx.0 * 0.5).tan() / aspect).atan()),
+        }
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw).normalize(),
-            Vector3::unit_y(),
-        )
+    pub fn horizontal(self, aspect: f32) -> Rad<f32> {
+        match self {
+            FovMode::Vertical(fovy) => Rad(2.0 * ((fovy.0 * 0.5).tan() * aspect).atan()),
+            FovMode::Horizontal(fovx) => fovx,
+        }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Projection {
     aspect: f32,
-    fovy: Rad<f32>,
+    fov: FovMode,
     znear: f32,
     zfar: f32,
 }
@@ -56,18 +108,53 @@ impl Projection {
     pub fn new<F: Into<Rad<f32>>>(width: u32, height: u32, fovy: F, znear: f32, zfar: f32) -> Self {
         Self {
             aspect: width as f32 / height as f32,
-            fovy: fovy.into(),
+            fov: FovMode::Vertical(fovy.into()),
             znear,
             zfar,
         }
     }
 
+    // The vertical FOV is derived from the aspect ratio on every use, so a
+    // horizontal FOV stays fixed across resizes. A zero height (a minimized
+    // window) keeps the old aspect ratio.
     pub fn resize(&mut self, width: u32, height: u32) {
-        self.aspect = width as f32 / height as f32;
+        if height > 0 {
+            self.set_aspect(width as f32 / height as f32);
+        }
+    }
+
+    // Width over height.
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    // Ratios that aren't positive and finite are ignored.
+    pub fn set_aspect(&mut self, aspect: f32) {
+        if aspect.is_finite() && aspect > 0.0 {
+            self.aspect = aspect;
+        }
+    }
+
+    pub fn fov(&self) -> FovMode {
+        self.fov
+    }
+
+    pub fn set_fov(&mut self, fov: FovMode) {
+        self.fov = fov;
+    }
+
+    pub fn fovy(&self) -> Rad<f32> {
+        self.fov.vertical(self.aspect)
+    }
+
+    // Distance at which one world unit spans the full viewport height, i.e.
+    // the vertical scale of the projection matrix.
+    pub fn focal_length(&self) -> f32 {
+        1.0 / (self.fovy().0 * 0.5).tan()
     }
 
     pub fn calc_matrix(&self) -> Matrix4<f32> {
-        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        OPENGL_TO_WGPU_MATRIX * perspective(self.fovy(), self.aspect, self.znear, self.zfar)
     }
 }
 
@@ -78,6 +165,12 @@ pub struct CameraUniform {
     pub view_proj: [[f32; 4]; 4],
 }
 
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
@@ -88,7 +181,7 @@ impl CameraUniform {
 
     pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
         self.view_position = camera.position.to_homogeneous().into();
-        self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into()
+        self.view_proj = camera.view_proj(projection).into()
     }
 }
 
@@ -124,44 +217,50 @@ impl CameraController {
         }
     }
 
-    pub fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
-        let amount = if state == ElementState::Pressed {
-            1.0
-        } else {
-            0.0
+    // Voxels per second when flying or walking.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    // Like `process_action`, for a key under the default bindings. Returns
+    // whether the key moves the camera, so the caller can skip handling it
+    // otherwise.
+    pub fn process_keyboard(&mut self, key: VirtualKeyCode, pressed: bool) -> bool {
+        default_action(key).is_some_and(|action| self.process_action(action, pressed))
+    }
+
+    // Starts or stops moving for a movement action. Returns whether the
+    // action was one the controller handles.
+    pub fn process_action(&mut self, action: Action, held: bool) -> bool {
+        self.process_axis(action, if held { 1.0 } else { 0.0 })
+    }
+
+    // Like `process_action` with an analog amount from 0 (stopped) to 1
+    // (full speed), e.g. from a controller stick.
+    pub fn process_axis(&mut self, action: Action, amount: f32) -> bool {
+        let amount = amount.clamp(0.0, 1.0);
+        let target = match action {
+            Action::MoveForward => &mut self.amount_forward,
+            Action::MoveBack => &mut self.amount_backward,
+            Action::MoveLeft => &mut self.amount_left,
+            Action::MoveRight => &mut self.amount_right,
+            Action::MoveUp => &mut self.amount_up,
+            Action::MoveDown => &mut self.amount_down,
+            _ => return false,
         };
-        match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
-                self.amount_forward = amount;
-                true
-            }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
-                self.amount_backward = amount;
-                true
-            }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
-                self.amount_left = amount;
-                true
-            }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
-                self.amount_right = amount;
-                true
-            }
-            VirtualKeyCode::Space => {
-                self.amount_up = amount;
-                true
-            }
-            VirtualKeyCode::LShift => {
-                self.amount_down = amount;
-                true
-            }
-            _ => false,
-        }
+        *target = amount;
+        true
     }
 
+    // Several motion events can arrive per frame, so deltas are summed here
+    // and applied all at once on the next update.
     pub fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-        self.rotate_horizontal = mouse_dx as f32;
-        self.rotate_vertical = mouse_dy as f32;
+        self.rotate_horizontal += mouse_dx as f32;
+        self.rotate_vertical += mouse_dy as f32;
     }
 
     pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
@@ -172,6 +271,21 @@ impl CameraController {
         };
     }
 
+    // Rotates the camera and returns the horizontal velocity the movement
+    // keys ask for along with whether jump is held, for walking instead of
+    // flying the camera around.
+    pub fn update_walking(&mut self, camera: &mut Camera) -> (Vector3<f32>, bool) {
+        let (yaw_sin, yaw_cos) = camera.yaw.0.sin_cos();
+        let right = Vector3::new(yaw_cos, 0.0, yaw_sin).normalize();
+        let forward = Vector3::new(yaw_sin, 0.0, -yaw_cos).normalize();
+        let walk = (forward * (self.amount_forward - self.amount_backward)
+            + right * (self.amount_right - self.amount_left))
+            * self.speed;
+        self.scroll = 0.0;
+        self.rotate_camera(camera);
+        (walk, self.amount_up > 0.0)
+    }
+
     pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
         let dt = dt.as_secs_f32();
 
@@ -196,13 +310,16 @@ impl CameraController {
         // modify the y coordinate directly.
         camera.position.y += (self.amount_up - self.amount_down) * self.speed * dt;
 
-        // Rotate
-        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
-        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+        self.rotate_camera(camera);
+    }
+
+    // The accumulated deltas are already the whole frame's movement, so they
+    // aren't scaled by the frame time.
+    fn rotate_camera(&mut self, camera: &mut Camera) {
+        let scale = self.sensitivity * RADIANS_PER_PIXEL;
+        camera.yaw += Rad(self.rotate_horizontal) * scale;
+        camera.pitch += Rad(-self.rotate_vertical) * scale;
 
-        // If process_mouse isn't called every frame, these values
-        // will not get set to zero, and the camera will rotate
-        // when moving in a non cardinal direction.
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
 
@@ -214,3 +331,77 @@ impl CameraController {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ULTRAWIDE: f32 = 21.0 / 9.0;
+
+    fn assert_deg(angle: Rad<f32>, expected: f32) {
+        let Deg(degrees) = angle.into();
+        assert!(
+            (degrees - expected).abs() < 1e-3,
+            "{} != {}",
+            degrees,
+            expected
+        );
+    }
+
+    #[test]
+    fn fov_converts_between_extents_at_21_9() {
+        let horizontal = FovMode::Horizontal(Deg(90.0).into());
+        assert_deg(horizontal.vertical(ULTRAWIDE), 46.397_18);
+        assert_deg(horizontal.horizontal(ULTRAWIDE), 90.0);
+
+        let vertical = FovMode::Vertical(Deg(70.0).into());
+        assert_deg(vertical.horizontal(ULTRAWIDE), 117.061_45);
+        // Converting back gives the original angle.
+        let there = FovMode::Horizontal(vertical.horizontal(ULTRAWIDE));
+        assert_deg(there.vertical(ULTRAWIDE), 70.0);
+    }
+
+    #[test]
+    fn horizontal_fov_survives_resizes() {
+        let mut projection = Projection::new(2520, 1080, Deg(45.0), 0.1, 100.0);
+        projection.set_fov(FovMode::Horizontal(Deg(90.0).into()));
+        assert_deg(projection.fovy(), 46.397_18);
+
+        projection.resize(1920, 1080);
+        assert_deg(projection.fov().horizontal(projection.aspect()), 90.0);
+        assert_deg(projection.fovy(), 58.715_51);
+    }
+
+    #[test]
+    fn invalid_aspect_ratios_are_ignored() {
+        let mut projection = Projection::new(1600, 900, Deg(45.0), 0.1, 100.0);
+        for aspect in [0.0, f32::NAN, -1.0] {
+            projection.set_aspect(aspect);
+            assert_eq!(projection.aspect(), 1600.0 / 900.0);
+        }
+    }
+
+    #[test]
+    fn mouse_motion_is_summed_over_the_frame() {
+        let start = || Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let mut split = CameraController::new(4.0, 1.0);
+        for (dx, dy) in [(3.0, -1.0), (5.0, 2.0), (-2.0, 4.0)] {
+            split.process_mouse(dx, dy);
+        }
+        let mut whole = CameraController::new(4.0, 1.0);
+        whole.process_mouse(6.0, 5.0);
+
+        let (mut a, mut b) = (start(), start());
+        // However long the frame took.
+        split.update_camera(&mut a, Duration::from_millis(5));
+        whole.update_camera(&mut b, Duration::from_millis(50));
+        assert!((a.yaw() - b.yaw()).0.abs() < 1e-6);
+        assert!((a.pitch() - b.pitch()).0.abs() < 1e-6);
+        assert!(a.yaw().0 > 0.0 && a.pitch().0 < 0.0);
+
+        // Applied once, not again on the next frame.
+        let yaw = a.yaw();
+        split.update_camera(&mut a, Duration::from_millis(5));
+        assert_eq!(a.yaw(), yaw);
+    }
+}
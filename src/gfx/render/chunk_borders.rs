@@ -0,0 +1,103 @@
+use crate::ecs::{chunk::CHUNK_SIZE, World};
+use cgmath::{EuclideanSpace, Point3, Vector3};
+use std::collections::HashSet;
+
+// Where a loaded chunk is on its way to the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkState {
+    // Loaded but without a mesh on the GPU yet.
+    Generated,
+    Meshed,
+    // Waiting to be remeshed.
+    Dirty,
+    // All air, so there's nothing to mesh.
+    Empty,
+}
+
+impl ChunkState {
+    pub fn color(self) -> [f32; 3] {
+        match self {
+            ChunkState::Generated => [0.2, 0.5, 1.0],
+            ChunkState::Meshed => [0.2, 0.9, 0.3],
+            ChunkState::Dirty => [1.0, 0.3, 0.2],
+            ChunkState::Empty => [0.5, 0.5, 0.5],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkBorder {
+    pub position: Vector3<i32>,
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+    pub state: ChunkState,
+}
+
+// The bounds of every loaded chunk in world space, one per chunk.
+// `is_meshed` says whether the chunk at a position has a mesh uploaded.
+pub fn chunk_borders(world: &World, is_meshed: impl Fn(Vector3<i32>) -> bool) -> Vec<ChunkBorder> {
+    let dirty = world.dirty_chunks().copied().collect::<HashSet<_>>();
+    world
+        .chunks()
+        .map(|chunk| {
+            let position = chunk.position();
+            let state = if dirty.contains(&position) {
+                ChunkState::Dirty
+            } else if chunk.is_empty() {
+                ChunkState::Empty
+            } else if is_meshed(position) {
+                ChunkState::Meshed
+            } else {
+                ChunkState::Generated
+            };
+            let min = Point3::from_vec((position * CHUNK_SIZE as i32).cast::<f32>().unwrap());
+            ChunkBorder {
+                position,
+                min,
+                max: min + Vector3::new(1.0, 1.0, 1.0) * CHUNK_SIZE as f32,
+                state,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{
+        chunk::Chunk,
+        voxel::{Voxel, VoxelType},
+    };
+
+    #[test]
+    fn one_border_per_loaded_chunk() {
+        let mut world = World::new();
+        for x in 0..4 {
+            let mut chunk = Chunk::new(Vector3::new(x, 0, 0));
+            if x > 0 {
+                chunk.set(0, 0, 0, Voxel::new(VoxelType::Stone));
+            }
+            world.add_chunk(chunk);
+        }
+        world.drain_dirty().for_each(drop);
+        world.mark_dirty(Vector3::new(3, 0, 0));
+
+        let mut borders = chunk_borders(&world, |p| p.x == 1);
+        assert_eq!(borders.len(), world.chunks().count());
+        borders.sort_by_key(|b| b.position.x);
+        let states: Vec<_> = borders.iter().map(|b| b.state).collect();
+        assert_eq!(
+            states,
+            [
+                ChunkState::Empty,
+                ChunkState::Meshed,
+                ChunkState::Generated,
+                ChunkState::Dirty
+            ]
+        );
+        assert_eq!(borders[1].min, Point3::new(16.0, 0.0, 0.0));
+        assert_eq!(borders[1].max, Point3::new(32.0, 16.0, 16.0));
+
+        assert!(chunk_borders(&World::new(), |_| false).is_empty());
+    }
+}
@@ -0,0 +1,84 @@
+use super::present::PresentMode;
+use crate::input::KeyBindings;
+
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    // How the surface is composited with whatever is behind it. Anything
+    // other than `Opaque` lets a web page show through the canvas.
+    pub alpha_mode: wgpu::CompositeAlphaMode,
+    pub present_mode: PresentMode,
+    // Check every remeshed chunk's borders for overlapping or missing faces
+    // and log what's found. Expensive, and only done in debug builds.
+    pub check_seams: bool,
+    pub key_bindings: KeyBindings,
+    // MSAA samples per pixel: 1 (off), 2 or 4. Falls back to 1 if the
+    // surface format or depth buffer can't be multisampled that much.
+    pub sample_count: u32,
+    // How many frames `Renderer::fps` and `frame_time_ms` average over.
+    pub frame_time_window: usize,
+    // The graphics APIs wgpu may pick an adapter from. If none of them has
+    // one, every backend is tried instead.
+    pub backends: wgpu::Backends,
+    // Whether to prefer the discrete or the integrated GPU when there are
+    // both.
+    pub power_preference: wgpu::PowerPreference,
+    // How many chunk columns out from the camera's are generated and drawn.
+    // See `Renderer::set_render_distance`.
+    pub render_distance: u32,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            present_mode: PresentMode::default(),
+            check_seams: false,
+            key_bindings: KeyBindings::default(),
+            sample_count: 1,
+            frame_time_window: 60,
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::default(),
+            render_distance: 4,
+        }
+    }
+}
+
+impl RendererConfig {
+    // The defaults, with the backends and power preference taken from
+    // `VOXL_BACKEND` (e.g. `vulkan` or `dx12,gl`) and `VOXL_POWER_PREFERENCE`
+    // (`high-performance` or `low-power`) where they're set. Values that
+    // can't be parsed are logged and ignored.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("VOXL_BACKEND") {
+            match parse_backends(&value) {
+                Some(backends) => config.backends = backends,
+                None => log::warn!("unknown VOXL_BACKEND {:?}, using every backend", value),
+            }
+        }
+        if let Ok(value) = std::env::var("VOXL_POWER_PREFERENCE") {
+            match parse_power_preference(&value) {
+                Some(preference) => config.power_preference = preference,
+                None => log::warn!(
+                    "unknown VOXL_POWER_PREFERENCE {:?}, using the default",
+                    value
+                ),
+            }
+        }
+        config
+    }
+}
+
+// A comma separated list of backends like `vulkan,gl`, in any case.
+pub fn parse_backends(s: &str) -> Option<wgpu::Backends> {
+    let backends = wgpu::util::parse_backends_from_comma_list(&s.to_lowercase());
+    (!backends.is_empty()).then_some(backends)
+}
+
+pub fn parse_power_preference(s: &str) -> Option<wgpu::PowerPreference> {
+    match s.to_lowercase().as_str() {
+        "high-performance" | "high" => Some(wgpu::PowerPreference::HighPerformance),
+        "low-power" | "low" => Some(wgpu::PowerPreference::LowPower),
+        _ => None,
+    }
+}
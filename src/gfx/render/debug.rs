@@ -0,0 +1,264 @@
+use crate::gfx::render::texture::Texture;
+use cgmath::Point3;
+use wgpu::util::DeviceExt;
+
+const INITIAL_CAPACITY: usize = 1024;
+
+// How thick debug lines are drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineWidth {
+    // Constant width on screen regardless of distance.
+    Pixels(f32),
+    // Width in world units, so lines get thinner with distance like geometry.
+    World(f32),
+}
+
+impl Default for LineWidth {
+    fn default() -> Self {
+        LineWidth::Pixels(2.0)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DebugVertex {
+    start: [f32; 3],
+    end: [f32; 3],
+    // x picks the endpoint (0 = start, 1 = end), y the side of the line.
+    corner: [f32; 2],
+    color: [f32; 3],
+}
+
+impl DebugVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        const ATTRIBUTE_LAYOUT: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32x3,
+            2 => Float32x2,
+            3 => Float32x3,
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<DebugVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTE_LAYOUT,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineUniform {
+    viewport: [f32; 2],
+    width: f32,
+    world_space: u32,
+    // Pixels covered by one world unit at a view depth of one.
+    pixels_per_unit: f32,
+    _padding: [f32; 3],
+}
+
+// Immediate-mode line drawing for debug visualizations. Lines are queued
+// during the frame, uploaded once in `prepare` and cleared after drawing.
+// Each line is expanded into a screen-aligned quad in the vertex shader so
+// it can be wider than the one pixel line primitives are limited to.
+pub struct DebugRenderer {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    vertices: Vec<DebugVertex>,
+    num_vertices: u32,
+    line_buffer: wgpu::Buffer,
+    line_bind_group: wgpu::BindGroup,
+    pub line_width: LineWidth,
+}
+
+impl DebugRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Debug Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../../res/shaders/debug.wgsl").into(),
+            ),
+        });
+
+        let line_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Debug Line Buffer"),
+            contents: bytemuck::bytes_of(&<LineUniform as bytemuck::Zeroable>::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let line_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("debug_line_bind_group_layout"),
+            });
+        let line_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &line_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: line_buffer.as_entire_binding(),
+            }],
+            label: Some("debug_line_bind_group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &line_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Debug Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[DebugVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Quads face either way depending on the view, so don't cull.
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            // Lines are depth tested against the scene but don't occlude
+            // each other.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer: Self::create_vertex_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            vertices: vec![],
+            num_vertices: 0,
+            line_buffer,
+            line_bind_group,
+            line_width: LineWidth::default(),
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Debug Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<DebugVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn line(&mut self, a: Point3<f32>, b: Point3<f32>, color: [f32; 3]) {
+        let corners = [
+            [0.0, -1.0],
+            [1.0, -1.0],
+            [1.0, 1.0],
+            [0.0, -1.0],
+            [1.0, 1.0],
+            [0.0, 1.0],
+        ];
+        self.vertices
+            .extend(corners.into_iter().map(|corner| DebugVertex {
+                start: a.into(),
+                end: b.into(),
+                corner,
+                color,
+            }));
+    }
+
+    pub fn aabb(&mut self, min: Point3<f32>, max: Point3<f32>, color: [f32; 3]) {
+        let corner = |i: usize| {
+            Point3::new(
+                if i & 1 == 0 { min.x } else { max.x },
+                if i & 2 == 0 { min.y } else { max.y },
+                if i & 4 == 0 { min.z } else { max.z },
+            )
+        };
+        // Every pair of corners that differ in exactly one bit forms an edge.
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    // `focal_length` is the vertical scale of the projection, used to turn
+    // world-space widths into pixels.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        viewport: [u32; 2],
+        focal_length: f32,
+    ) {
+        let (width, world_space) = match self.line_width {
+            LineWidth::Pixels(width) => (width, 0),
+            LineWidth::World(width) => (width, 1),
+        };
+        let uniform = LineUniform {
+            viewport: viewport.map(|v| v as f32),
+            width,
+            world_space,
+            pixels_per_unit: viewport[1] as f32 * 0.5 * focal_length,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(&self.line_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.num_vertices = self.vertices.len() as u32;
+        self.vertices.clear();
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.num_vertices == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.line_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
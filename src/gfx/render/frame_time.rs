@@ -0,0 +1,65 @@
+use instant::Duration;
+use std::collections::VecDeque;
+
+// Rolling average over the last few frame times.
+#[derive(Debug, Clone)]
+pub struct FrameTimer {
+    samples: VecDeque<Duration>,
+    window: usize,
+    total: Duration,
+}
+
+impl FrameTimer {
+    // Averages over the last `window` frames, at least one.
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window.max(1)),
+            window: window.max(1),
+            total: Duration::ZERO,
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // Shrinking the window drops the oldest samples straight away.
+    pub fn set_window(&mut self, window: usize) {
+        self.window = window.max(1);
+        self.trim();
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        self.samples.push_back(frame_time);
+        self.total += frame_time;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.samples.len() > self.window {
+            self.total -= self.samples.pop_front().unwrap();
+        }
+    }
+
+    // Zero until a frame has been recorded.
+    pub fn average(&self) -> Duration {
+        match self.samples.len() {
+            0 => Duration::ZERO,
+            len => self.total / len as u32,
+        }
+    }
+
+    pub fn frame_time_ms(&self) -> f32 {
+        self.average().as_secs_f32() * 1000.0
+    }
+
+    // Zero until a frame with a non-zero time has been recorded.
+    pub fn fps(&self) -> f32 {
+        let average = self.average().as_secs_f32();
+        if average > 0.0 {
+            1.0 / average
+        } else {
+            0.0
+        }
+    }
+}
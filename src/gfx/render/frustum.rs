@@ -0,0 +1,127 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+// The volume a camera sees, described by its view-projection matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    view_proj: Matrix4<f32>,
+    // Left, right, bottom, top, near and far, as `(normal, distance)` with
+    // the normals pointing inwards.
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    pub fn from_view_proj(view_proj: Matrix4<f32>) -> Self {
+        let row = |i| view_proj.row(i);
+        // wgpu's clip space depth runs from 0 rather than -1, so the near
+        // plane is just the z row.
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ]
+        .map(|plane| plane / plane.truncate().magnitude());
+        Self { view_proj, planes }
+    }
+
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        self.view_proj
+    }
+
+    pub fn planes(&self) -> [Vector4<f32>; 6] {
+        self.planes
+    }
+
+    // Whether any of the box could be visible. Only the box's corner
+    // furthest along each plane's normal is tested, so a box that straddles
+    // a plane counts as inside, and one near a frustum corner can pass
+    // without actually touching it.
+    pub fn intersects_aabb(&self, min: Point3<f32>, max: Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            let furthest = Vector3::new(
+                if plane.x >= 0.0 { max.x } else { min.x },
+                if plane.y >= 0.0 { max.y } else { min.y },
+                if plane.z >= 0.0 { max.z } else { min.z },
+            );
+            plane.truncate().dot(furthest) + plane.w >= 0.0
+        })
+    }
+
+    // World space corners of the near plane followed by the far plane, each
+    // going bottom left, bottom right, top right, top left as seen by the
+    // camera. All zero if the matrix can't be inverted.
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let Some(inverse) = self.view_proj.invert() else {
+            return [Point3::new(0.0, 0.0, 0.0); 8];
+        };
+        // wgpu's clip space depth runs from 0 at the near plane to 1 at the far one.
+        let mut corners = [Point3::new(0.0, 0.0, 0.0); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let (x, y) = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)][i % 4];
+            let z = (i / 4) as f32;
+            let p = inverse * Vector4::new(x, y, z, 1.0);
+            *corner = Point3::new(p.x / p.w, p.y / p.w, p.z / p.w);
+        }
+        corners
+    }
+
+    // Pairs of `corners` indices forming the frustum's 12 edges.
+    pub const EDGES: [(usize, usize); 12] = [
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 0),
+        (4, 5),
+        (5, 6),
+        (6, 7),
+        (7, 4),
+        (0, 4),
+        (1, 5),
+        (2, 6),
+        (3, 7),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::render::camera::{Camera, Projection};
+    use cgmath::Deg;
+
+    fn assert_near(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn identity_corners_are_the_clip_volume() {
+        let corners = Frustum::from_view_proj(Matrix4::identity()).corners();
+        let square = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        for (i, corner) in corners.into_iter().enumerate() {
+            let (x, y) = square[i % 4];
+            assert_near(corner, Point3::new(x, y, (i / 4) as f32));
+        }
+    }
+
+    #[test]
+    fn camera_corners_are_unprojected_clip_corners() {
+        // A square 90 degree view down -z reaches as far sideways as it
+        // does forwards.
+        let camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let projection = Projection::new(1, 1, Deg(90.0), 1.0, 10.0);
+        let frustum = camera.frustum(&projection);
+        let square = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+        for (i, corner) in frustum.corners().into_iter().enumerate() {
+            let (x, y) = square[i % 4];
+            let depth = if i < 4 { 1.0 } else { 10.0 };
+            assert_near(corner, Point3::new(x * depth, y * depth, -depth));
+        }
+
+        let unit = Vector3::new(0.5, 0.5, 0.5);
+        let inside = Point3::new(0.0, 0.0, -5.0);
+        assert!(frustum.intersects_aabb(inside - unit, inside + unit));
+        let behind = Point3::new(0.0, 0.0, 5.0);
+        assert!(!frustum.intersects_aabb(behind - unit, behind + unit));
+    }
+}
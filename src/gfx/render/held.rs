@@ -0,0 +1,168 @@
+use super::camera::Camera;
+use crate::{
+    ecs::{
+        transform::{Transform, TransformId, Transforms},
+        voxel::VoxelType,
+    },
+    gfx::mesh::{ChunkMesh, Face},
+};
+use cgmath::{EuclideanSpace, Quaternion, Rotation3, Vector3};
+use instant::Duration;
+
+// The slice of the depth range the held block is drawn into. The world uses
+// all of it, so anything nearer than this is practically at the near plane
+// and the block never sinks into walls in front of the camera.
+pub const DEPTH_RANGE: f32 = 0.05;
+
+// Where the block sits in front of the camera, in the camera's own space.
+const OFFSET: Vector3<f32> = Vector3::new(0.45, -0.4, -0.8);
+const SCALE: f32 = 0.3;
+const BOB_AMPLITUDE: f32 = 0.03;
+// Radians of bob per unit walked.
+const BOB_RATE: f32 = 2.5;
+// How quickly the bob fades in and out when starting or stopping.
+const BOB_SETTLE: f32 = 8.0;
+
+// The block in the player's hand, attached to the camera so it moves with
+// the view and bobbing as the player walks.
+pub struct HeldItem {
+    pub item: Option<VoxelType>,
+    camera: TransformId,
+    model: TransformId,
+    bob_phase: f32,
+    bob_weight: f32,
+}
+
+impl HeldItem {
+    pub fn new(transforms: &mut Transforms) -> Self {
+        let camera = transforms.spawn(Transform::default());
+        let model = transforms.spawn(Transform::default());
+        transforms
+            .set_parent(model, Some(camera))
+            .expect("new transforms can't form a cycle");
+        Self {
+            item: None,
+            camera,
+            model,
+            bob_phase: 0.0,
+            bob_weight: 0.0,
+        }
+    }
+
+    // Follows the camera. The block's own transform is relative to this.
+    pub fn camera_transform(&self) -> TransformId {
+        self.camera
+    }
+
+    // The block's transform, for a mesh spanning `[-0.5, 0.5]` on each axis.
+    pub fn model_transform(&self) -> TransformId {
+        self.model
+    }
+
+    // Moves the block along with `camera`, bobbing it by `walked`, how far
+    // the camera moved along the ground this frame. World transforms pick
+    // this up on the next `Transforms::propagate`.
+    pub fn update(
+        &mut self,
+        transforms: &mut Transforms,
+        camera: &Camera,
+        walked: f32,
+        dt: Duration,
+    ) {
+        let target = if walked > 0.0 { 1.0 } else { 0.0 };
+        let blend = (dt.as_secs_f32() * BOB_SETTLE).min(1.0);
+        self.bob_weight += (target - self.bob_weight) * blend;
+        self.bob_phase = (self.bob_phase + walked * BOB_RATE) % std::f32::consts::TAU;
+
+        let (sin, cos) = self.bob_phase.sin_cos();
+        let bob = Vector3::new(cos, -sin.abs(), 0.0) * BOB_AMPLITUDE * self.bob_weight;
+        transforms.set_local(
+            self.camera,
+            Transform {
+                translation: camera.position.to_vec(),
+                rotation: camera.rotation(),
+                scale: 1.0,
+            },
+        );
+        transforms.set_local(
+            self.model,
+            Transform {
+                translation: OFFSET + bob,
+                rotation: Quaternion::from_angle_y(cgmath::Deg(-30.0)),
+                scale: SCALE,
+            },
+        );
+    }
+}
+
+// A unit cube of `voxel_type` centered on the origin.
+pub fn held_mesh(voxel_type: VoxelType) -> ChunkMesh {
+    let mut cube = ChunkMesh::new();
+    for face in Face::ALL {
+        let depth = face.is_positive() as i32 as f32;
+        cube.push_face(face, depth, [0.0, 0.0], [1.0, 1.0], voxel_type);
+    }
+    let mut mesh = ChunkMesh::new();
+    mesh.append(cube, [-0.5; 3]);
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Deg, InnerSpace, Rotation};
+
+    const FRAME: Duration = Duration::from_micros(16_667);
+
+    fn model_origin(transforms: &Transforms, held: &HeldItem) -> Vector3<f32> {
+        transforms
+            .world(held.model_transform())
+            .unwrap()
+            .w
+            .truncate()
+    }
+
+    #[test]
+    fn held_model_follows_the_camera() {
+        let mut transforms = Transforms::new();
+        let mut held = HeldItem::new(&mut transforms);
+        for camera in [
+            Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0)),
+            Camera::new((100.0, 64.0, -20.0), Deg(0.0), Deg(0.0)),
+            Camera::new((100.0, 64.0, -20.0), Deg(135.0), Deg(-40.0)),
+        ] {
+            held.update(&mut transforms, &camera, 0.0, FRAME);
+            transforms.propagate();
+            let expected = camera.position.to_vec() + camera.rotation().rotate_vector(OFFSET);
+            let origin = model_origin(&transforms, &held);
+            assert!(
+                (origin - expected).magnitude() < 1e-4,
+                "{:?} != {:?}",
+                origin,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn walking_bobs_the_model_and_standing_settles_it() {
+        let mut transforms = Transforms::new();
+        let mut held = HeldItem::new(&mut transforms);
+        let camera = Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        held.update(&mut transforms, &camera, 0.0, FRAME);
+        transforms.propagate();
+        let rest = model_origin(&transforms, &held);
+
+        for _ in 0..30 {
+            held.update(&mut transforms, &camera, 0.1, FRAME);
+        }
+        transforms.propagate();
+        assert!((model_origin(&transforms, &held) - rest).magnitude() > 1e-3);
+
+        for _ in 0..120 {
+            held.update(&mut transforms, &camera, 0.0, FRAME);
+        }
+        transforms.propagate();
+        assert!((model_origin(&transforms, &held) - rest).magnitude() < 1e-3);
+    }
+}
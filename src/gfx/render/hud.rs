@@ -0,0 +1,68 @@
+use crate::ecs::tool::ToolMode;
+use cgmath::{Point3, Vector3};
+
+// Length of each crosshair line and how thick it is, in pixels.
+const CROSSHAIR_SIZE: u32 = 16;
+const CROSSHAIR_THICKNESS: u32 = 2;
+// Opaque, since the lines overlap in the middle.
+pub const CROSSHAIR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+pub const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+// Gap between the window's top left corner and the readout, in pixels.
+pub const TEXT_MARGIN: f32 = 8.0;
+
+// The crosshair's horizontal and vertical lines as (top left, size) in
+// pixels. Everything is whole pixels so the lines stay crisp at any window
+// size, which puts the crosshair half a pixel off center in odd sized
+// windows.
+pub fn crosshair_rects([width, height]: [u32; 2]) -> [([f32; 2], [f32; 2]); 2] {
+    let (cx, cy) = (width / 2, height / 2);
+    let (half_size, half_thickness) = (CROSSHAIR_SIZE / 2, CROSSHAIR_THICKNESS / 2);
+    let horizontal = (
+        [
+            cx.saturating_sub(half_size) as f32,
+            cy.saturating_sub(half_thickness) as f32,
+        ],
+        [CROSSHAIR_SIZE as f32, CROSSHAIR_THICKNESS as f32],
+    );
+    let vertical = (
+        [
+            cx.saturating_sub(half_thickness) as f32,
+            cy.saturating_sub(half_size) as f32,
+        ],
+        [CROSSHAIR_THICKNESS as f32, CROSSHAIR_SIZE as f32],
+    );
+    [horizontal, vertical]
+}
+
+// What the debug readout shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HudInfo {
+    pub position: Point3<f32>,
+    pub chunk: Vector3<i32>,
+    pub target: Option<Vector3<i32>>,
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    pub tool: ToolMode,
+}
+
+impl HudInfo {
+    pub fn text(&self) -> String {
+        let target = match self.target {
+            Some(t) => format!("{} {} {}", t.x, t.y, t.z),
+            None => "none".to_string(),
+        };
+        format!(
+            "{:.0} fps ({:.1} ms)\nxyz: {:.2} {:.2} {:.2}\nchunk: {} {} {}\ntarget: {}\ntool: {}",
+            self.fps,
+            self.frame_time_ms,
+            self.position.x,
+            self.position.y,
+            self.position.z,
+            self.chunk.x,
+            self.chunk.y,
+            self.chunk.z,
+            target,
+            self.tool.name()
+        )
+    }
+}
@@ -0,0 +1,66 @@
+use cgmath::{InnerSpace, Vector3};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shading {
+    // Per-face normals, giving every voxel face a uniform brightness.
+    #[default]
+    Flat,
+    // Normals averaged across faces sharing a vertex for a softer look.
+    Smooth,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightUniform {
+    direction: [f32; 3],
+    smooth_shading: u32,
+    color: [f32; 3],
+    _padding: u32,
+}
+
+impl Default for LightUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LightUniform {
+    pub fn new() -> Self {
+        Self {
+            direction: [0.0, 1.0, 0.0],
+            smooth_shading: 0,
+            color: [1.0; 3],
+            _padding: 0,
+        }
+    }
+
+    // Points towards the sun.
+    pub fn direction(&self) -> Vector3<f32> {
+        self.direction.into()
+    }
+
+    pub fn set_direction(&mut self, direction: Vector3<f32>) {
+        self.direction = direction.normalize().into();
+    }
+
+    pub fn color(&self) -> [f32; 3] {
+        self.color
+    }
+
+    // Linear RGB the sunlit part of the shading is tinted by.
+    pub fn set_color(&mut self, color: [f32; 3]) {
+        self.color = color;
+    }
+
+    pub fn shading(&self) -> Shading {
+        if self.smooth_shading == 0 {
+            Shading::Flat
+        } else {
+            Shading::Smooth
+        }
+    }
+
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.smooth_shading = (shading == Shading::Smooth) as u32;
+    }
+}
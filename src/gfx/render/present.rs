@@ -0,0 +1,203 @@
+use instant::Duration;
+
+// How frames are handed to the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresentMode {
+    // Wait for the display's refresh. Never tears, but adds latency.
+    #[default]
+    Vsync,
+    // Present as soon as a frame is done, tearing if it lands mid-refresh.
+    Immediate,
+    // Mailbox while frames keep up with the refresh rate, dropping to a
+    // tearing mode while they're behind rather than waiting a whole refresh.
+    Adaptive,
+}
+
+// Frames in a row that have to miss (or make) the refresh interval before
+// switching, so a single hitch doesn't flip the mode back and forth.
+const BEHIND_FRAMES: u32 = 3;
+const CAUGHT_UP_FRAMES: u32 = 60;
+// Slack either side of the refresh interval.
+const TOLERANCE: f32 = 0.05;
+
+// Picks the surface present mode each frame for `PresentMode::Adaptive`.
+#[derive(Debug, Clone)]
+pub struct AdaptivePresent {
+    synced: wgpu::PresentMode,
+    behind: wgpu::PresentMode,
+    refresh_interval: Duration,
+    is_behind: bool,
+    streak: u32,
+}
+
+impl AdaptivePresent {
+    // Mailbox falls back to Fifo when unsupported, and Immediate to
+    // FifoRelaxed (which only tears when late) and then to the synced mode.
+    pub fn new(supported: &[wgpu::PresentMode], refresh_interval: Duration) -> Self {
+        let pick = |preferred: &[wgpu::PresentMode], fallback| {
+            preferred
+                .iter()
+                .copied()
+                .find(|mode| supported.contains(mode))
+                .unwrap_or(fallback)
+        };
+        let synced = pick(&[wgpu::PresentMode::Mailbox], wgpu::PresentMode::Fifo);
+        let behind = pick(
+            &[wgpu::PresentMode::Immediate, wgpu::PresentMode::FifoRelaxed],
+            synced,
+        );
+        Self {
+            synced,
+            behind,
+            refresh_interval,
+            is_behind: false,
+            streak: 0,
+        }
+    }
+
+    pub fn synced_mode(&self) -> wgpu::PresentMode {
+        self.synced
+    }
+
+    pub fn behind_mode(&self) -> wgpu::PresentMode {
+        self.behind
+    }
+
+    pub fn current(&self) -> wgpu::PresentMode {
+        if self.is_behind {
+            self.behind
+        } else {
+            self.synced
+        }
+    }
+
+    // Feeds the last frame's time and returns the new present mode if it
+    // should change.
+    pub fn frame(&mut self, frame_time: Duration) -> Option<wgpu::PresentMode> {
+        let interval = self.refresh_interval.as_secs_f32();
+        let frame_time = frame_time.as_secs_f32();
+        let towards_other = if self.is_behind {
+            frame_time < interval * (1.0 - TOLERANCE)
+        } else {
+            frame_time > interval * (1.0 + TOLERANCE)
+        };
+        self.streak = if towards_other { self.streak + 1 } else { 0 };
+
+        let needed = if self.is_behind {
+            CAUGHT_UP_FRAMES
+        } else {
+            BEHIND_FRAMES
+        };
+        if self.streak < needed {
+            return None;
+        }
+        let previous = self.current();
+        self.is_behind = !self.is_behind;
+        self.streak = 0;
+        (self.current() != previous).then(|| self.current())
+    }
+}
+
+// The surface present mode for a fixed (non-adaptive) choice, or Fifo if
+// that isn't supported, since every surface has to support it.
+pub fn fixed_present_mode(mode: PresentMode, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    let wanted = match mode {
+        PresentMode::Vsync | PresentMode::Adaptive => wgpu::PresentMode::Fifo,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+    };
+    if supported.contains(&wanted) {
+        wanted
+    } else {
+        log::warn!(
+            "surface doesn't support present mode {:?}, using Fifo",
+            wanted
+        );
+        wgpu::PresentMode::Fifo
+    }
+}
+
+// The configured alpha mode if the surface supports it, or else the first
+// one it does. `supported` is never empty for a usable surface.
+pub fn supported_alpha_mode(
+    mode: wgpu::CompositeAlphaMode,
+    supported: &[wgpu::CompositeAlphaMode],
+) -> wgpu::CompositeAlphaMode {
+    if supported.contains(&mode) {
+        mode
+    } else {
+        log::warn!(
+            "surface doesn't support alpha mode {:?}, using {:?}",
+            mode,
+            supported[0]
+        );
+        supported[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::{CompositeAlphaMode, PresentMode::*};
+
+    const REFRESH: Duration = Duration::from_micros(16_667);
+    const FAST: Duration = Duration::from_millis(10);
+    const SLOW: Duration = Duration::from_millis(25);
+
+    #[test]
+    fn fifo_only_settles_on_fifo_either_way() {
+        let mut adaptive = AdaptivePresent::new(&[Fifo], REFRESH);
+        assert_eq!(adaptive.synced_mode(), Fifo);
+        assert_eq!(adaptive.behind_mode(), Fifo);
+        // Falling behind flips the state, but there's no other mode to use.
+        for _ in 0..BEHIND_FRAMES * 2 {
+            assert_eq!(adaptive.frame(SLOW), None);
+        }
+        assert_eq!(adaptive.current(), Fifo);
+    }
+
+    #[test]
+    fn fifo_and_immediate_tear_only_while_behind() {
+        let adaptive = AdaptivePresent::new(&[Fifo, Immediate], REFRESH);
+        assert_eq!(adaptive.synced_mode(), Fifo);
+        assert_eq!(adaptive.behind_mode(), Immediate);
+        assert_eq!(adaptive.current(), Fifo);
+    }
+
+    #[test]
+    fn modes_switch_only_after_a_streak() {
+        let mut adaptive = AdaptivePresent::new(&[Fifo, Immediate], REFRESH);
+        for _ in 1..BEHIND_FRAMES {
+            assert_eq!(adaptive.frame(SLOW), None);
+        }
+        // A frame on time resets the streak.
+        assert_eq!(adaptive.frame(FAST), None);
+        for _ in 1..BEHIND_FRAMES {
+            assert_eq!(adaptive.frame(SLOW), None);
+        }
+        assert_eq!(adaptive.frame(SLOW), Some(Immediate));
+        assert_eq!(adaptive.current(), Immediate);
+
+        for _ in 1..CAUGHT_UP_FRAMES {
+            assert_eq!(adaptive.frame(FAST), None);
+        }
+        assert_eq!(adaptive.current(), Immediate);
+        assert_eq!(adaptive.frame(FAST), Some(Fifo));
+        assert_eq!(adaptive.current(), Fifo);
+    }
+
+    #[test]
+    fn configured_alpha_mode_is_used_when_supported() {
+        let supported = [
+            CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PreMultiplied,
+        ];
+        assert_eq!(
+            supported_alpha_mode(CompositeAlphaMode::PreMultiplied, &supported),
+            CompositeAlphaMode::PreMultiplied
+        );
+        assert_eq!(
+            supported_alpha_mode(CompositeAlphaMode::PostMultiplied, &supported),
+            CompositeAlphaMode::Opaque
+        );
+    }
+}
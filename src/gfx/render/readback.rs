@@ -0,0 +1,199 @@
+use anyhow::{anyhow, Result};
+use std::{iter, sync::mpsc};
+
+// A texture being copied back to the CPU. The copy finishes in the
+// background as the device is polled, e.g. by `Renderer::poll` each frame,
+// and `try_take` hands the pixels over once it has.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    mapped: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl Readback {
+    // Starts copying a 4-byte-per-pixel texture.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let unpadded_bytes_per_row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(iter::once(encoder.finish()));
+
+        let (sender, mapped) = mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).ok();
+            });
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra: matches!(
+                texture.format(),
+                wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+            ),
+            mapped,
+        }
+    }
+
+    // The pixels as tightly packed RGBA rows, or `None` while the copy is
+    // still in flight.
+    pub fn try_take(&self) -> Option<Result<Vec<u8>>> {
+        match self.mapped.try_recv() {
+            Ok(result) => Some(result.map_err(Into::into).and_then(|()| self.pixels())),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                Some(Err(anyhow!("readback buffer was dropped before mapping")))
+            }
+        }
+    }
+
+    // Blocks until the copy is done.
+    pub fn wait(self, device: &wgpu::Device) -> Result<Vec<u8>> {
+        device.poll(wgpu::Maintain::Wait);
+        self.mapped.recv()??;
+        self.pixels()
+    }
+
+    fn pixels(&self) -> Result<Vec<u8>> {
+        let unpadded_bytes_per_row = 4 * self.width as usize;
+        let mut pixels = Vec::with_capacity(unpadded_bytes_per_row * self.height as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        if self.bgra {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Ok(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::render::renderer::Renderer;
+
+    // An odd width, so rows are padded in the buffer.
+    const WIDTH: u32 = 3;
+    const HEIGHT: u32 = 2;
+
+    fn texture_with(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        pixels: &[u8],
+    ) -> wgpu::Texture {
+        let size = wgpu::Extent3d {
+            width: WIDTH,
+            height: HEIGHT,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * WIDTH),
+                rows_per_image: None,
+            },
+            size,
+        );
+        texture
+    }
+
+    // Polls without blocking, the way the renderer does each frame, until
+    // the pixels arrive.
+    fn poll_until_done(device: &wgpu::Device, readback: &Readback) -> Vec<u8> {
+        for _ in 0..1000 {
+            device.poll(wgpu::Maintain::Poll);
+            if let Some(pixels) = readback.try_take() {
+                return pixels.unwrap();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("readback never completed");
+    }
+
+    #[test]
+    fn readback_completes_after_polling() {
+        let Some((device, queue)) = Renderer::test_device() else {
+            return;
+        };
+        let pixels: Vec<u8> = (0..4 * WIDTH * HEIGHT).map(|i| i as u8 * 10).collect();
+        let texture = texture_with(&device, &queue, wgpu::TextureFormat::Rgba8Unorm, &pixels);
+        let readback = Readback::new(&device, &queue, &texture, WIDTH, HEIGHT);
+        assert_eq!(poll_until_done(&device, &readback), pixels);
+    }
+
+    #[test]
+    fn bgra_textures_come_back_as_rgba() {
+        let Some((device, queue)) = Renderer::test_device() else {
+            return;
+        };
+        let bgra = [30, 20, 10, 255].repeat((WIDTH * HEIGHT) as usize);
+        let texture = texture_with(&device, &queue, wgpu::TextureFormat::Bgra8Unorm, &bgra);
+        let readback = Readback::new(&device, &queue, &texture, WIDTH, HEIGHT);
+        let rgba = [10, 20, 30, 255].repeat((WIDTH * HEIGHT) as usize);
+        assert_eq!(poll_until_done(&device, &readback), rgba);
+    }
+}
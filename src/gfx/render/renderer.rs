@@ -1,25 +1,69 @@
-use crate::gfx::render::{
-    camera,
-    camera::{Camera, CameraController, CameraUniform, Projection},
-    texture::Texture,
-    vertex::{create_vertices, Vertex},
+use crate::{
+    ecs::{
+        chunk::CHUNK_SIZE, item::ITEM_SIZE, terrain::TerrainGenerator, tool::ToolMode,
+        voxel::VoxelType, World,
+    },
+    gfx::{
+        mesh::{jobs::MeshJobQueue, seam, ChunkMesh, Face},
+        render::{
+            atlas::TextureAtlas,
+            camera,
+            camera::{Camera, CameraController, CameraUniform, Projection},
+            chunk_borders::chunk_borders,
+            config::RendererConfig,
+            debug::{DebugRenderer, LineWidth},
+            frame_time::FrameTimer,
+            frustum::Frustum,
+            held::{self, HeldItem},
+            hud::{self, HudInfo},
+            light::{LightUniform, Shading},
+            present::{fixed_present_mode, supported_alpha_mode, AdaptivePresent, PresentMode},
+            readback::Readback,
+            screenshot,
+            text::{GlyphAtlas, TextRenderer},
+            texture::Texture,
+            vertex::{create_vertices, Vertex},
+        },
+    },
+    input::{
+        Action, DoubleTap, GamepadAxis, GamepadBindings, GamepadButton, GamepadState, InputState,
+        KeyBindings,
+    },
 };
+use anyhow::{anyhow, ensure, Result};
 use cgmath::prelude::*;
-use std::iter;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    iter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 use wgpu::util::DeviceExt;
 use winit::{event::*, window::Window};
 
-struct Instance {
-    position: cgmath::Vector3<f32>,
-    rotation: cgmath::Quaternion<f32>,
+// Where one copy of a mesh is drawn, turned and scaled around the mesh's
+// own origin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub position: cgmath::Vector3<f32>,
+    pub rotation: cgmath::Quaternion<f32>,
+    pub scale: f32,
 }
 
 impl Instance {
+    pub fn matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::from_translation(self.position)
+            * cgmath::Matrix4::from(self.rotation)
+            * cgmath::Matrix4::from_scale(self.scale)
+    }
+
     fn to_raw(&self) -> InstanceRaw {
         InstanceRaw {
-            model: (cgmath::Matrix4::from_translation(self.position)
-                * cgmath::Matrix4::from(self.rotation))
-            .into(),
+            model: self.matrix().into(),
         }
     }
 }
@@ -77,120 +121,349 @@ const INSTANCE_DISPLACEMENT: cgmath::Vector3<f32> = cgmath::Vector3::new(
     NUM_INSTANCES_PER_ROW as f32 * 0.5,
 );
 
-pub struct Renderer {
-    pub surface: wgpu::Surface,
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
-    pub config: wgpu::SurfaceConfiguration,
-    pub size: winit::dpi::PhysicalSize<u32>,
-    pub render_pipeline: wgpu::RenderPipeline,
-    pub vertex_buffer: wgpu::Buffer,
-    pub num_vertices: u32,
-    pub index_buffer: wgpu::Buffer,
-    pub num_indices: u32,
-    pub camera: Camera,
-    pub projection: Projection,
-    pub camera_uniform: CameraUniform,
-    pub camera_buffer: wgpu::Buffer,
-    pub camera_bind_group: wgpu::BindGroup,
-    pub camera_controller: CameraController,
-    instances: Vec<Instance>,
+// The cube mesh is 2 units wide and spans z in [-2, 0], so items need to be
+// shifted back by one unit before scaling to spin around their center.
+const CUBE_CENTER: cgmath::Vector3<f32> = cgmath::Vector3::new(0.0, 0.0, -1.0);
+const INITIAL_ITEM_CAPACITY: usize = 64;
+// Render distances `Renderer::set_render_distance` accepts, in chunks.
+const MIN_RENDER_DISTANCE: u32 = 1;
+const MAX_RENDER_DISTANCE: u32 = 32;
+// How many columns past the render distance chunks stay loaded, so walking
+// back and forth across the edge doesn't regenerate them.
+const UNLOAD_MARGIN: i32 = 2;
+// Assumed when the monitor doesn't report its refresh rate.
+const DEFAULT_REFRESH_INTERVAL: instant::Duration = instant::Duration::from_micros(16_667);
+const SELECTION_COLOR: [f32; 3] = [1.0, 0.9, 0.2];
+const TARGET_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+// wgpu 0.15 has no device lost callback, and its errors can't be matched on
+// since they wrap wgpu-core's by message. This is in the message of
+// wgpu-core 0.15's `DeviceError::Lost`, which every call on a lost device
+// fails with. Recheck it when upgrading wgpu, the tests below pin it.
+const DEVICE_LOST_MESSAGE: &str = "device is lost";
+
+// A GPU buffer that's rewritten in place while its contents fit and only
+// reallocated when they outgrow it.
+struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+    // Capacity and used length, in bytes.
+    capacity: u64,
+    len: u64,
+}
+
+impl GrowableBuffer {
+    fn new(
+        device: &wgpu::Device,
+        label: &'static str,
+        usage: wgpu::BufferUsages,
+        contents: &[u8],
+    ) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: usage | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            buffer,
+            label,
+            usage,
+            capacity: contents.len() as u64,
+            len: contents.len() as u64,
+        }
+    }
+
+    // Returns whether the buffer had to be reallocated.
+    fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[u8]) -> bool {
+        let len = contents.len() as u64;
+        if len > self.capacity {
+            *self = Self::new(device, self.label, self.usage, contents);
+            return true;
+        }
+        queue.write_buffer(&self.buffer, 0, contents);
+        self.len = len;
+        false
+    }
+
+    fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..self.len)
+    }
+}
+
+// GPU copy of a chunk's mesh. The instance buffer holds the chunk's model
+// matrix so meshes can stay in chunk-local coordinates.
+struct GpuChunk {
+    position: cgmath::Vector3<i32>,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    // The index buffer holds the opaque indices followed by the transparent
+    // ones.
+    num_indices: u32,
+    num_transparent_indices: u32,
+    vertex_count: usize,
+    // Around the mesh, relative to the chunk. Column meshes reach up into
+    // the chunks above.
+    bounds: ([f32; 3], [f32; 3]),
+    // The level of detail the mesh was built at.
+    lod: u32,
     instance_buffer: wgpu::Buffer,
-    diffuse_bind_group: wgpu::BindGroup,
-    depth_texture: Texture,
-    pub mouse_pressed: bool,
 }
 
-impl Renderer {
-    pub async fn new(window: &Window) -> Self {
-        let size = window.inner_size();
+// A mesh uploaded with `Renderer::upload_mesh`, to draw with
+// `Renderer::draw_instanced`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MeshHandle(usize);
 
-        // let instance = wgpu::Instance::default();
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        // let surface = unsafe { instance.create_surface(window) }.expect("surface couldn't be created");
-        let surface = unsafe { instance.create_surface(window) }.expect("failed to create surface");
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+// A mesh drawn with one call however many copies of it are queued. The mesh
+// is kept on the CPU too, to upload again if the device is lost.
+struct InstancedMesh {
+    mesh: ChunkMesh,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    instance_buffer: GrowableBuffer,
+    // Queued since the last frame, in render space.
+    queued: Vec<InstanceRaw>,
+    num_instances: u32,
+}
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
+impl InstancedMesh {
+    fn new(device: &wgpu::Device, mesh: ChunkMesh) -> Self {
+        let vertex_buffer = GrowableBuffer::new(
+            device,
+            "Instanced Mesh Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&mesh.vertices),
+        );
+        let index_buffer = GrowableBuffer::new(
+            device,
+            "Instanced Mesh Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(&GpuChunk::indices(&mesh)),
+        );
+        let instance_buffer = GrowableBuffer::new(
+            device,
+            "Instanced Mesh Instance Buffer",
+            wgpu::BufferUsages::VERTEX,
+            &[],
+        );
+        Self {
+            mesh,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            queued: Vec::new(),
+            num_instances: 0,
+        }
+    }
 
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let swapchain_format = swapchain_capabilities.formats[0];
+    // Uploads the instances queued for this frame and starts the next one
+    // empty.
+    fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.num_instances = self.queued.len() as u32;
+        if !self.queued.is_empty() {
+            self.instance_buffer
+                .write(device, queue, bytemuck::cast_slice(&self.queued));
+        }
+        self.queued.clear();
+    }
 
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: swapchain_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            view_formats: vec![],
+    // Draws the opaque or transparent faces of every instance, with the
+    // matching pipeline already set.
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, transparent: bool) {
+        let (opaque, transparent_len) = (
+            self.mesh.indices.len() as u32,
+            self.mesh.transparent_indices.len() as u32,
+        );
+        let indices = match transparent {
+            false => 0..opaque,
+            true => opaque..opaque + transparent_len,
         };
-        surface.configure(&device, &config);
-
-        surface.configure(&device, &config);
-        let diffuse_bytes = include_bytes!("../../../res/textures/atlas.png");
-        let diffuse_texture =
-            Texture::from_bytes(&device, &queue, diffuse_bytes, "atlas.png").unwrap();
+        if self.num_instances == 0 || indices.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice());
+        render_pass.set_index_buffer(self.index_buffer.slice(), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(indices, 0, 0..self.num_instances);
+    }
+}
 
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2Array,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        // This should match the filterable field of the
-                        // corresponding Texture entry above.
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
+// Totals over every chunk mesh currently on the GPU.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderStats {
+    pub chunks: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+}
 
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view), // CHANGED!
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler), // CHANGED!
-                },
-            ],
-            label: Some("diffuse_bind_group"),
+impl GpuChunk {
+    fn new(
+        device: &wgpu::Device,
+        position: cgmath::Vector3<i32>,
+        origin: cgmath::Vector3<i32>,
+        mesh: &ChunkMesh,
+    ) -> Self {
+        let vertex_buffer = GrowableBuffer::new(
+            device,
+            "Chunk Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            bytemuck::cast_slice(&mesh.vertices),
+        );
+        let index_buffer = GrowableBuffer::new(
+            device,
+            "Chunk Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            bytemuck::cast_slice(&Self::indices(mesh)),
+        );
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Chunk Instance Buffer"),
+            contents: bytemuck::cast_slice(&[Self::instance(position, origin)]),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
+        Self {
+            position,
+            vertex_buffer,
+            index_buffer,
+            num_indices: mesh.indices.len() as u32,
+            num_transparent_indices: mesh.transparent_indices.len() as u32,
+            vertex_count: mesh.vertex_count(),
+            bounds: mesh.bounds().unwrap_or_default(),
+            lod: 0,
+            instance_buffer,
+        }
+    }
+
+    fn indices(mesh: &ChunkMesh) -> Vec<u32> {
+        [&mesh.indices[..], &mesh.transparent_indices[..]].concat()
+    }
+
+    // The chunk's model matrix in render space. The offset from the origin is
+    // computed in integers so it stays exact however far out the chunk is.
+    fn instance(position: cgmath::Vector3<i32>, origin: cgmath::Vector3<i32>) -> InstanceRaw {
+        Instance {
+            position: (position * CHUNK_SIZE as i32 - origin)
+                .cast::<f32>()
+                .unwrap(),
+            rotation: cgmath::Quaternion::one(),
+            scale: 1.0,
+        }
+        .to_raw()
+    }
+
+    fn set_origin(&self, queue: &wgpu::Queue, origin: cgmath::Vector3<i32>) {
+        queue.write_buffer(
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&[Self::instance(self.position, origin)]),
+        );
+    }
+
+    // Moves a pooled chunk's buffers to another chunk and fills them with
+    // its mesh.
+    fn reuse(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        position: cgmath::Vector3<i32>,
+        origin: cgmath::Vector3<i32>,
+        mesh: &ChunkMesh,
+    ) {
+        self.position = position;
+        self.lod = 0;
+        self.set_origin(queue, origin);
+        self.upload(device, queue, mesh);
+    }
+
+    // Bytes of GPU memory held by the chunk's buffers.
+    fn capacity(&self) -> u64 {
+        self.vertex_buffer.capacity + self.index_buffer.capacity + self.instance_buffer.size()
+    }
+
+    // Replaces the mesh, reusing the existing buffers when it fits so
+    // remeshing (e.g. on a LOD change) doesn't churn GPU memory.
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, mesh: &ChunkMesh) {
+        self.vertex_buffer
+            .write(device, queue, bytemuck::cast_slice(&mesh.vertices));
+        self.index_buffer
+            .write(device, queue, bytemuck::cast_slice(&Self::indices(mesh)));
+        self.num_indices = mesh.indices.len() as u32;
+        self.num_transparent_indices = mesh.transparent_indices.len() as u32;
+        self.vertex_count = mesh.vertex_count();
+        self.bounds = mesh.bounds().unwrap_or_default();
+    }
+}
+
+// What `Renderer::with_config` and `Renderer::headless` each set up before
+// building the rest of the renderer.
+struct Gpu {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    window: Option<Arc<Window>>,
+    surface: Option<wgpu::Surface>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    device_lost: Arc<AtomicBool>,
+}
+
+// A device from `Renderer::request_new_device`, to be handed to
+// `Renderer::replace_device`.
+pub struct NewDevice {
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+// A screenshot on its way back from the GPU, written to `path` once it
+// arrives.
+struct PendingScreenshot {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    readback: Readback,
+}
+
+// What the renderer keeps on the GPU device, all of which is gone if the
+// device is lost. Built in one place so `Renderer::recreate_device` makes
+// exactly what `Renderer::with_config` did. The text renderer is built on
+// its own, as `Renderer::set_font` does.
+struct DeviceResources {
+    render_pipeline: wgpu::RenderPipeline,
+    transparent_pipeline: wgpu::RenderPipeline,
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    light_buffer: wgpu::Buffer,
+    light_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    cube_instance: Instance,
+    item_instance_buffer: wgpu::Buffer,
+    atlas: TextureAtlas,
+    depth_texture: Texture,
+    msaa_texture: Option<Texture>,
+    debug_renderer: DebugRenderer,
+}
+
+impl DeviceResources {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        camera_uniform: &CameraUniform,
+        light_uniform: &LightUniform,
+        item_instance_capacity: usize,
+    ) -> Result<Self> {
+        let atlas = TextureAtlas::from_bytes(
+            device,
+            queue,
+            include_bytes!("../../../res/textures/atlas.png"),
+            "atlas.png",
+        )?;
 
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader"),
@@ -199,17 +472,9 @@ impl Renderer {
             ),
         });
 
-        let camera = camera::Camera::new((-5.0, 5.0, 10.0), cgmath::Deg(0.0), cgmath::Deg(-20.0));
-        let projection =
-            camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 100.0);
-        let camera_controller = camera::CameraController::new(15.0, 1.0);
-
-        let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&camera, &projection);
-
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[camera_uniform]),
+            contents: bytemuck::cast_slice(&[*camera_uniform]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -237,58 +502,123 @@ impl Renderer {
             label: Some("camera_bind_group"),
         });
 
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&[*light_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let light_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("light_bind_group_layout"),
+            });
+
+        let light_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &light_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+            label: Some("light_bind_group"),
+        });
+
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
+                bind_group_layouts: &[
+                    &atlas.bind_group_layout,
+                    &camera_bind_group_layout,
+                    &light_bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",                          // 1.
-                buffers: &[Vertex::desc(), InstanceRaw::desc()], // 2.
-            },
-            fragment: Some(wgpu::FragmentState {
-                // 3.
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    // 4.
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList, // 1.
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw, // 2.
-                cull_mode: Some(wgpu::Face::Back),
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let create_pipeline = |label, blend, cull_mode, depth_write_enabled, polygon_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",                          // 1.
+                    buffers: &[Vertex::desc(), InstanceRaw::desc()], // 2.
+                },
+                fragment: Some(wgpu::FragmentState {
+                    // 3.
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        // 4.
+                        format: config.format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList, // 1.
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw, // 2.
+                    cull_mode,
+                    // Line requires Features::POLYGON_MODE_LINE
+                    polygon_mode,
+                    // Requires Features::DEPTH_CLIP_CONTROL
+                    unclipped_depth: false,
+                    // Requires Features::CONSERVATIVE_RASTERIZATION
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let render_pipeline = create_pipeline(
+            "Render Pipeline",
+            wgpu::BlendState::REPLACE,
+            Some(wgpu::Face::Back),
+            true,
+            wgpu::PolygonMode::Fill,
+        );
+        // Transparent faces are seen from both sides, so the back wall of a
+        // glass box shows through the front one. They still test against the
+        // depth buffer but don't write to it, leaving what's behind visible.
+        let transparent_pipeline = create_pipeline(
+            "Transparent Render Pipeline",
+            wgpu::BlendState::ALPHA_BLENDING,
+            None,
+            false,
+            wgpu::PolygonMode::Fill,
+        );
+        let wireframe_pipeline = device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+            .then(|| {
+                create_pipeline(
+                    "Wireframe Render Pipeline",
+                    wgpu::BlendState::REPLACE,
+                    Some(wgpu::Face::Back),
+                    true,
+                    wgpu::PolygonMode::Line,
+                )
+            });
 
         let (vertices, indices) = create_vertices();
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -329,7 +659,11 @@ impl Renderer {
                         cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(0.0))
                     };
 
-                    Instance { position, rotation }
+                    Instance {
+                        position,
+                        rotation,
+                        scale: 1.0,
+                    }
                 })
             })
             .collect::<Vec<_>>();
@@ -338,113 +672,1845 @@ impl Renderer {
         let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Instance Buffer"),
             contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
+        // Only the first instance is drawn.
+        let cube_instance = instances[0].clone();
 
-        let depth_texture = Texture::create_depth_texture(&device, &config, "depth_texture");
+        let item_instance_buffer =
+            Renderer::create_item_instance_buffer(device, item_instance_capacity);
 
-        Self {
-            surface,
+        let depth_texture =
+            Texture::create_depth_texture(device, config, sample_count, "depth_texture");
+        let msaa_texture = (sample_count > 1).then(|| {
+            Texture::create_multisampled_texture(device, config, sample_count, "msaa_texture")
+        });
+        let debug_renderer = DebugRenderer::new(
             device,
-            queue,
-            config,
-            size,
+            config.format,
+            sample_count,
+            &camera_bind_group_layout,
+        );
+        Ok(Self {
             render_pipeline,
+            transparent_pipeline,
+            wireframe_pipeline,
             vertex_buffer,
             num_vertices,
-            camera,
-            projection,
-            camera_uniform,
-            camera_buffer,
-            camera_bind_group,
-            camera_controller,
             index_buffer,
             num_indices,
-            instances,
+            camera_buffer,
+            camera_bind_group,
+            camera_bind_group_layout,
+            light_buffer,
+            light_bind_group,
             instance_buffer,
-            diffuse_bind_group,
+            cube_instance,
+            item_instance_buffer,
+            atlas,
             depth_texture,
-            mouse_pressed: false,
-        }
+            msaa_texture,
+            debug_renderer,
+        })
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.projection.resize(new_size.width, new_size.height);
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture =
-                Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
+    // The requested MSAA sample count, or 1 if the device can't multisample
+    // the surface and depth formats that much.
+    fn sample_count(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        requested_samples: u32,
+    ) -> u32 {
+        if ![1, 2, 4].contains(&requested_samples) {
+            log::warn!(
+                "MSAA sample count {} isn't 1, 2 or 4, using 1",
+                requested_samples
+            );
+            1
+        } else if [format, Texture::DEPTH_FORMAT].into_iter().all(|format| {
+            Texture::supports_sample_count(adapter, device, format, requested_samples)
+        }) {
+            requested_samples
+        } else {
+            log::warn!(
+                "{:?} can't be rendered with {}x MSAA, using 1",
+                format,
+                requested_samples
+            );
+            1
         }
     }
+}
 
-    #[allow(unused_variables)]
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        virtual_keycode: Some(key),
-                        state,
-                        ..
-                    },
-                ..
-            } => self.camera_controller.process_keyboard(*key, *state),
-            WindowEvent::MouseWheel { delta, .. } => {
-                self.camera_controller.process_scroll(delta);
-                true
-            }
-            WindowEvent::MouseInput {
-                button: MouseButton::Left,
-                state,
-                ..
-            } => {
-                self.mouse_pressed = *state == ElementState::Pressed;
-                true
-            }
-            _ => false,
-        }
-    }
+pub struct Renderer {
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    // Reused when the device has to be recreated.
+    power_preference: wgpu::PowerPreference,
+    // Set once the device is lost, after which nothing more is submitted to
+    // it until `recreate_device`.
+    device_lost: Arc<AtomicBool>,
+    // The window the surface presents to, held for as long as the surface
+    // so it can't be dropped out from under it. Both are None for renderers
+    // made with `headless`.
+    window: Option<Arc<Window>>,
+    pub surface: Option<wgpu::Surface>,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub config: wgpu::SurfaceConfiguration,
+    pub size: winit::dpi::PhysicalSize<u32>,
+    pub render_pipeline: wgpu::RenderPipeline,
+    transparent_pipeline: wgpu::RenderPipeline,
+    // Draws chunks as lines, if the device supports it.
+    wireframe_pipeline: Option<wgpu::RenderPipeline>,
+    wireframe: bool,
+    pub vertex_buffer: wgpu::Buffer,
+    pub num_vertices: u32,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+    pub camera: Camera,
+    pub projection: Projection,
+    pub camera_uniform: CameraUniform,
+    pub camera_buffer: wgpu::Buffer,
+    pub camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    pub camera_controller: CameraController,
+    light_uniform: LightUniform,
+    // Overrides the sun's position from the world's time of day.
+    sun_direction: Option<cgmath::Vector3<f32>>,
+    light_buffer: wgpu::Buffer,
+    light_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    cube_instance: Instance,
+    item_instance_buffer: wgpu::Buffer,
+    chunks: HashMap<cgmath::Vector3<i32>, GpuChunk>,
+    // Buffers of chunks that were unloaded or emptied, handed to the next
+    // chunks that need some instead of creating new ones.
+    chunk_pool: Vec<GpuChunk>,
+    // At most this many are kept in `chunk_pool`, the rest are freed.
+    pub max_pooled_chunks: usize,
+    // How far chunks are generated and drawn, in chunk columns from the
+    // camera's. See `set_render_distance`.
+    render_distance: u32,
+    instanced_meshes: Vec<InstancedMesh>,
+    column_meshing: bool,
+    // Chunk meshes being built off the main thread. Columns are still
+    // meshed on the main thread.
+    pub mesh_jobs: MeshJobQueue,
+    // Distances in voxels from the camera to a chunk's center past which
+    // it's meshed at each coarser level of detail, in increasing order.
+    // Empty keeps every chunk at full detail. Column meshes are always at
+    // full detail.
+    pub lod_distances: Vec<f32>,
+    world_epoch: u64,
+    stats: RenderStats,
+    frame_timer: FrameTimer,
+    // Chunks the last frame drew and skipped for being outside the view.
+    pub chunks_drawn: usize,
+    pub chunks_culled: usize,
+    check_seams: bool,
+    show_chunk_borders: bool,
+    show_hud: bool,
+    // The voxel under the crosshair as of the last update.
+    targeted_voxel: Option<cgmath::Vector3<i32>>,
+    adaptive_present: Option<AdaptivePresent>,
+    refresh_interval: instant::Duration,
+    held_item: HeldItem,
+    // Drawn with its own instance buffer, which holds the block's world
+    // transform rather than a chunk position.
+    held_mesh: Option<GpuChunk>,
+    origin: cgmath::Vector3<i32>,
+    item_instance_capacity: usize,
+    num_item_instances: u32,
+    atlas: TextureAtlas,
+    depth_texture: Texture,
+    sample_count: u32,
+    // Where passes draw before resolving into the surface, with MSAA on.
+    msaa_texture: Option<Texture>,
+    debug_renderer: DebugRenderer,
+    text_renderer: TextRenderer,
+    pub input: InputState,
+    pub key_bindings: KeyBindings,
+    pub gamepads: GamepadState,
+    pub gamepad_bindings: GamepadBindings,
+    // How fast the right stick turns the camera at full tilt, in pixels of
+    // mouse movement per second.
+    pub gamepad_look_speed: f32,
+    pub fly_toggle: DoubleTap,
+    // Whether the break button is held down. Looking around doesn't depend
+    // on it, since the cursor is grabbed.
+    break_held: bool,
+    // Fresh clicks since the last update, as opposed to buttons being held.
+    break_pressed: bool,
+    place_pressed: bool,
+    ui_open: bool,
+    // Set by the fullscreen key until the window owner picks it up, since
+    // the renderer doesn't own the window.
+    fullscreen_requested: bool,
+    // Where the next presented frame is saved, if a screenshot was asked for.
+    screenshot_requested: Option<PathBuf>,
+    screenshots: Vec<PendingScreenshot>,
+    pub world: World,
+}
 
-    pub fn update(&mut self, dt: instant::Duration) {
-        self.camera_controller.update_camera(&mut self.camera, dt);
-        self.camera_uniform
-            .update_view_proj(&self.camera, &self.projection);
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
+impl Renderer {
+    pub async fn new(window: Arc<Window>) -> Self {
+        Self::with_config(window, RendererConfig::from_env()).await
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    pub async fn with_config(window: Arc<Window>, renderer_config: RendererConfig) -> Self {
+        let size = window.inner_size();
 
-        {
+        let power_preference = renderer_config.power_preference;
+        let instance = Self::create_instance(renderer_config.backends);
+        // let surface = unsafe { instance.create_surface(window) }.expect("surface couldn't be created");
+        let surface =
+            unsafe { instance.create_surface(window.as_ref()) }.expect("failed to create surface");
+        let found = Self::request_device(&instance, Some(&surface), power_preference).await;
+        let (instance, surface, (adapter, device, queue)) = match found {
+            Ok(found) => (instance, surface, found),
+            Err(err) if renderer_config.backends != wgpu::Backends::all() => {
+                log::warn!(
+                    "{} with backends {:?}, trying every backend",
+                    err,
+                    renderer_config.backends
+                );
+                // Some platforms only allow one surface per window.
+                drop(surface);
+                let instance = Self::create_instance(wgpu::Backends::all());
+                let surface = unsafe { instance.create_surface(window.as_ref()) }
+                    .expect("failed to create surface");
+                let found = Self::request_device(&instance, Some(&surface), power_preference)
+                    .await
+                    .expect("failed to get a GPU device");
+                (instance, surface, found)
+            }
+            Err(err) => panic!("failed to get a GPU device: {}", err),
+        };
+        let device_lost = Self::watch_for_device_loss(&device);
+
+        let swapchain_capabilities = surface.get_capabilities(&adapter);
+        let swapchain_format = swapchain_capabilities.formats[0];
+        let alpha_mode = supported_alpha_mode(
+            renderer_config.alpha_mode,
+            &swapchain_capabilities.alpha_modes,
+        );
+
+        let refresh_interval = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .map_or(DEFAULT_REFRESH_INTERVAL, |millihertz| {
+                instant::Duration::from_secs_f64(1000.0 / millihertz as f64)
+            });
+        let supported_present_modes = &swapchain_capabilities.present_modes;
+        let adaptive_present = (renderer_config.present_mode == PresentMode::Adaptive)
+            .then(|| AdaptivePresent::new(supported_present_modes, refresh_interval));
+        let present_mode = match &adaptive_present {
+            Some(adaptive) => {
+                log::info!(
+                    "adaptive present: {:?} at {:?} per refresh, {:?} when behind",
+                    adaptive.synced_mode(),
+                    refresh_interval,
+                    adaptive.behind_mode()
+                );
+                adaptive.current()
+            }
+            None => fixed_present_mode(renderer_config.present_mode, supported_present_modes),
+        };
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: Self::surface_usage(&adapter),
+            format: swapchain_format,
+            width: size.width,
+            height: size.height,
+            present_mode,
+            alpha_mode,
+            view_formats: vec![],
+        };
+        surface.configure(&device, &config);
+
+        Self::from_gpu(
+            Gpu {
+                instance,
+                adapter,
+                window: Some(window),
+                surface: Some(surface),
+                device,
+                queue,
+                device_lost,
+            },
+            config,
+            refresh_interval,
+            adaptive_present,
+            renderer_config,
+        )
+        .expect("failed to create GPU resources")
+    }
+
+    // A renderer without a window, e.g. for tools and tests. There's nothing
+    // to present to, so `render` always fails, but `render_to_texture`
+    // works.
+    pub async fn headless(
+        renderer_config: RendererConfig,
+        width: u32,
+        height: u32,
+    ) -> Result<Self> {
+        ensure!(width > 0 && height > 0, "size must be non-zero");
+        let instance = Self::create_instance(renderer_config.backends);
+        let (adapter, device, queue) =
+            Self::request_device(&instance, None, renderer_config.power_preference).await?;
+        let device_lost = Self::watch_for_device_loss(&device);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: renderer_config.alpha_mode,
+            view_formats: vec![],
+        };
+        Self::from_gpu(
+            Gpu {
+                instance,
+                adapter,
+                window: None,
+                surface: None,
+                device,
+                queue,
+                device_lost,
+            },
+            config,
+            DEFAULT_REFRESH_INTERVAL,
+            None,
+            renderer_config,
+        )
+    }
+
+    // Everything past picking the device and configuring the surface, which
+    // is all `with_config` and `headless` do differently.
+    fn from_gpu(
+        gpu: Gpu,
+        config: wgpu::SurfaceConfiguration,
+        refresh_interval: instant::Duration,
+        adaptive_present: Option<AdaptivePresent>,
+        renderer_config: RendererConfig,
+    ) -> Result<Self> {
+        let Gpu {
+            instance,
+            adapter,
+            window,
+            surface,
+            device,
+            queue,
+            device_lost,
+        } = gpu;
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
+        let power_preference = renderer_config.power_preference;
+        let mut world = World::new();
+        world.spawn_player();
+        let player = &world.player;
+        let camera = camera::Camera::new(player.eye(), player.yaw, player.pitch);
+        let projection =
+            camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 100.0);
+        let camera_controller = camera::CameraController::new(15.0, 1.0);
+
+        let mut camera_uniform = CameraUniform::new();
+        let mut render_camera = camera.clone();
+        render_camera.position = world.to_render_space(camera.position);
+        camera_uniform.update_view_proj(&render_camera, &projection);
+
+        let light_uniform = LightUniform::new();
+        let sample_count = DeviceResources::sample_count(
+            &adapter,
+            &device,
+            config.format,
+            renderer_config.sample_count,
+        );
+        let DeviceResources {
+            render_pipeline,
+            transparent_pipeline,
+            wireframe_pipeline,
+            vertex_buffer,
+            num_vertices,
+            index_buffer,
+            num_indices,
+            camera_buffer,
+            camera_bind_group,
+            camera_bind_group_layout,
+            light_buffer,
+            light_bind_group,
+            instance_buffer,
+            cube_instance,
+            item_instance_buffer,
+            atlas,
+            depth_texture,
+            msaa_texture,
+            debug_renderer,
+        } = DeviceResources::new(
+            &device,
+            &queue,
+            &config,
+            sample_count,
+            &camera_uniform,
+            &light_uniform,
+            INITIAL_ITEM_CAPACITY,
+        )?;
+        let text_renderer = TextRenderer::new(
+            &device,
+            &queue,
+            config.format,
+            sample_count,
+            GlyphAtlas::bitmap(),
+        );
+
+        let held_item = HeldItem::new(&mut world.transforms);
+        let render_distance = renderer_config.render_distance;
+
+        let mut renderer = Self {
+            instance,
+            adapter,
+            power_preference,
+            device_lost,
+            window,
+            surface,
+            device,
+            queue,
+            config,
+            size,
+            render_pipeline,
+            transparent_pipeline,
+            wireframe_pipeline,
+            wireframe: false,
+            vertex_buffer,
+            num_vertices,
+            camera,
+            projection,
+            camera_uniform,
+            camera_buffer,
+            camera_bind_group,
+            camera_bind_group_layout,
+            camera_controller,
+            light_uniform,
+            sun_direction: None,
+            light_buffer,
+            light_bind_group,
+            index_buffer,
+            num_indices,
+            instance_buffer,
+            cube_instance,
+            item_instance_buffer,
+            chunks: HashMap::new(),
+            chunk_pool: Vec::new(),
+            max_pooled_chunks: 256,
+            render_distance: 0,
+            instanced_meshes: Vec::new(),
+            column_meshing: false,
+            mesh_jobs: MeshJobQueue::new(),
+            lod_distances: vec![64.0, 128.0],
+            world_epoch: 0,
+            stats: RenderStats::default(),
+            frame_timer: FrameTimer::new(renderer_config.frame_time_window),
+            chunks_drawn: 0,
+            chunks_culled: 0,
+            check_seams: renderer_config.check_seams,
+            show_chunk_borders: false,
+            show_hud: true,
+            targeted_voxel: None,
+            adaptive_present,
+            refresh_interval,
+            held_item,
+            held_mesh: None,
+            origin: cgmath::Vector3::new(0, 0, 0),
+            item_instance_capacity: INITIAL_ITEM_CAPACITY,
+            num_item_instances: 0,
+            atlas,
+            depth_texture,
+            sample_count,
+            msaa_texture,
+            debug_renderer,
+            text_renderer,
+            input: InputState::new(),
+            key_bindings: renderer_config.key_bindings,
+            gamepads: GamepadState::new(),
+            gamepad_bindings: GamepadBindings::default(),
+            gamepad_look_speed: 180.0,
+            fly_toggle: DoubleTap::default(),
+            break_held: false,
+            break_pressed: false,
+            place_pressed: false,
+            fullscreen_requested: false,
+            ui_open: false,
+            screenshot_requested: None,
+            screenshots: Vec::new(),
+            world,
+        };
+        renderer.set_render_distance(render_distance);
+        Ok(renderer)
+    }
+
+    // Surfaces are copied from for screenshots where the backend allows it.
+    // GL surfaces can only be rendered to, and there's no asking the adapter.
+    fn surface_usage(adapter: &wgpu::Adapter) -> wgpu::TextureUsages {
+        match adapter.get_info().backend {
+            wgpu::Backend::Gl => wgpu::TextureUsages::RENDER_ATTACHMENT,
+            _ => wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        }
+    }
+
+    fn create_instance(backends: wgpu::Backends) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        })
+    }
+
+    // Not an `async fn`, so the future doesn't borrow the instance or the
+    // surface and can be awaited while the renderer is still in use.
+    fn request_device(
+        instance: &wgpu::Instance,
+        surface: Option<&wgpu::Surface>,
+        power_preference: wgpu::PowerPreference,
+    ) -> impl Future<Output = Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)>> {
+        let has_surface = surface.is_some();
+        let adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        });
+        async move {
+            let adapter = adapter.await.ok_or_else(|| {
+                if has_surface {
+                    anyhow!("no adapter can present to the surface")
+                } else {
+                    anyhow!("no adapter found")
+                }
+            })?;
+            let info = adapter.get_info();
+            log::info!(
+                "using {} ({:?}) with {:?}",
+                info.name,
+                info.device_type,
+                info.backend
+            );
+
+            let (device, queue) = adapter
+                .request_device(
+                    &wgpu::DeviceDescriptor {
+                        // Lets MSAA use every sample count the adapter supports
+                        // rather than only the guaranteed ones, and chunks be
+                        // drawn as wireframes.
+                        features: adapter.features()
+                            & (wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                                | wgpu::Features::POLYGON_MODE_LINE),
+                        limits: wgpu::Limits::default(),
+                        label: None,
+                    },
+                    None,
+                )
+                .await?;
+            Ok((adapter, device, queue))
+        }
+    }
+
+    // A device on whatever adapter is around, for testing the pieces the
+    // renderer is made of. `None` if there isn't one.
+    #[cfg(test)]
+    pub(crate) fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = Self::create_instance(wgpu::Backends::all());
+        let found = Self::request_device(&instance, None, wgpu::PowerPreference::default());
+        match pollster::block_on(found) {
+            Ok((_, device, queue)) => Some((device, queue)),
+            Err(err) => {
+                eprintln!("skipping, no GPU: {}", err);
+                None
+            }
+        }
+    }
+
+    // wgpu panics on any error nothing else caught, which includes every
+    // call on a device that's been lost. Loss is flagged instead, so the
+    // renderer can move to a new device. Any other error is still a bug
+    // and panics like before.
+    fn watch_for_device_loss(device: &wgpu::Device) -> Arc<AtomicBool> {
+        let lost = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&lost);
+        device.on_uncaptured_error(Box::new(move |error| {
+            Self::handle_device_error(&flag, error)
+        }));
+        lost
+    }
+
+    fn handle_device_error(lost: &AtomicBool, error: wgpu::Error) {
+        if !error.to_string().contains(DEVICE_LOST_MESSAGE) {
+            panic!("wgpu error: {}", error);
+        }
+        if !lost.swap(true, Ordering::Relaxed) {
+            log::error!("GPU device lost: {}", error);
+        }
+    }
+
+    // Whether the device was lost, e.g. to a driver reset or the GPU being
+    // unplugged. `render` fails with `SurfaceError::Lost` until
+    // `recreate_device` is called.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    fn create_item_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Item Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Remeshes dirty chunks through the world's mesher and uploads the result.
+    fn update_chunks(&mut self) {
+        if self.world_epoch != self.world.epoch() {
+            self.world_epoch = self.world.epoch();
+            self.clear_chunks();
+            self.mesh_jobs.clear();
+            self.stats = RenderStats::default();
+        }
+        // GPU chunks are keyed differently in the two modes, so switching
+        // starts over.
+        if self.column_meshing != self.world.config.column_meshing {
+            self.column_meshing = self.world.config.column_meshing;
+            self.clear_chunks();
+            self.mesh_jobs.clear();
+            self.world.mark_all_dirty();
+        }
+
+        if !self.column_meshing {
+            // Chunks already being remeshed get their new level of detail
+            // once the mesh comes back.
+            let stale = self
+                .chunks
+                .values()
+                .filter(|chunk| {
+                    chunk.lod != self.lod_for(chunk.position)
+                        && !self.mesh_jobs.is_pending(chunk.position)
+                })
+                .map(|chunk| chunk.position)
+                .collect::<Vec<_>>();
+            for position in stale {
+                self.world.mark_dirty(position);
+            }
+        }
+
+        let dirty: Vec<_> = self.world.drain_dirty().collect();
+        let mut uploaded = vec![];
+        if self.column_meshing {
+            let columns = dirty.iter().map(|p| (p.x, p.z)).collect::<HashSet<_>>();
+            for (x, z) in columns {
+                self.update_column(x, z);
+            }
+        } else {
+            for &position in &dirty {
+                let lod = self.lod_for(position);
+                if !self.mesh_jobs.queue(&self.world, position, lod) {
+                    self.upload_chunk_mesh(position, &ChunkMesh::new());
+                }
+            }
+            while uploaded.len() < self.mesh_jobs.max_uploads_per_frame {
+                let Some(result) = self.mesh_jobs.pop_ready() else {
+                    break;
+                };
+                self.upload_chunk_mesh(result.position, &result.mesh);
+                if let Some(chunk) = self.chunks.get_mut(&result.position) {
+                    chunk.lod = result.lod;
+                }
+                uploaded.push(result.position);
+            }
+            if cfg!(debug_assertions) && self.check_seams {
+                self.log_seam_issues(&uploaded);
+            }
+        }
+        if dirty.is_empty() && uploaded.is_empty() {
+            return;
+        }
+        // Only changes when chunks are remeshed, so it's cheaper to total
+        // here than every frame.
+        self.stats = RenderStats::default();
+        for chunk in self.chunks.values() {
+            self.stats.chunks += 1;
+            self.stats.vertices += chunk.vertex_count;
+            self.stats.triangles +=
+                (chunk.num_indices + chunk.num_transparent_indices) as usize / 3;
+        }
+    }
+
+    // The level of detail to mesh the chunk at, from the camera's distance
+    // to its center.
+    fn lod_for(&self, position: cgmath::Vector3<i32>) -> u32 {
+        let center = (position.cast::<f32>().unwrap() + cgmath::Vector3::from_value(0.5))
+            * CHUNK_SIZE as f32;
+        let distance = self
+            .camera
+            .position
+            .distance(cgmath::Point3::from_vec(center));
+        self.lod_distances.iter().filter(|&&d| distance > d).count() as u32
+    }
+
+    // `camera` moved into render space, relative to the world's origin.
+    fn render_camera(&self, camera: &Camera) -> Camera {
+        let mut camera = camera.clone();
+        camera.position = self.world.to_render_space(camera.position);
+        camera
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    // Averaged over the last `RendererConfig::frame_time_window` updates.
+    // Both are zero before the first one.
+    pub fn fps(&self) -> f32 {
+        self.frame_timer.fps()
+    }
+
+    pub fn frame_time_ms(&self) -> f32 {
+        self.frame_timer.frame_time_ms()
+    }
+
+    pub fn set_frame_time_window(&mut self, frames: usize) {
+        self.frame_timer.set_window(frames);
+    }
+
+    fn log_seam_issues(&self, positions: &[cgmath::Vector3<i32>]) {
+        for &a in positions {
+            for face in Face::ALL {
+                let b = a + cgmath::Vector3::from(face.normal());
+                if self.world.neighborhood(b).is_none() {
+                    continue;
+                }
+                match seam::check_seam(&self.world, self.world.mesher().as_ref(), a, b) {
+                    Ok(issues) => {
+                        for issue in issues {
+                            log::warn!("seam between chunks {:?} and {:?}: {:?}", a, b, issue);
+                        }
+                    }
+                    Err(err) => log::warn!("seam check failed: {}", err),
+                }
+            }
+        }
+    }
+
+    // Remeshes every run of vertically contiguous chunks in a column. Each
+    // run is stored under the position of its lowest chunk.
+    fn update_column(&mut self, x: i32, z: i32) {
+        let mut ys = self
+            .world
+            .chunk_positions()
+            .filter(|p| p.x == x && p.z == z)
+            .map(|p| p.y)
+            .collect::<Vec<_>>();
+        ys.sort_unstable();
+        let runs = ys
+            .chunk_by(|a, b| b - a == 1)
+            .map(|run| run.to_vec())
+            .collect::<Vec<_>>();
+
+        // Runs that split or merged leave stale entries behind.
+        let stale = self
+            .chunks
+            .keys()
+            .filter(|p| p.x == x && p.z == z && !runs.iter().any(|run| run[0] == p.y))
+            .copied()
+            .collect::<Vec<_>>();
+        for position in stale {
+            self.remove_chunk(position);
+        }
+        for run in runs {
+            let sections = run
+                .iter()
+                .filter_map(|&y| self.world.neighborhood(cgmath::Vector3::new(x, y, z)))
+                .collect::<Vec<_>>();
+            let mesh = self.world.mesher().mesh_column(&sections);
+            self.upload_chunk_mesh(cgmath::Vector3::new(x, run[0], z), &mesh);
+        }
+    }
+
+    fn upload_chunk_mesh(&mut self, position: cgmath::Vector3<i32>, mesh: &ChunkMesh) {
+        if mesh.is_empty() {
+            self.remove_chunk(position);
+        } else if let Some(chunk) = self.chunks.get_mut(&position) {
+            chunk.upload(&self.device, &self.queue, mesh);
+        } else {
+            let chunk = match self.chunk_pool.pop() {
+                Some(mut chunk) => {
+                    chunk.reuse(&self.device, &self.queue, position, self.origin, mesh);
+                    chunk
+                }
+                None => GpuChunk::new(&self.device, position, self.origin, mesh),
+            };
+            self.chunks.insert(position, chunk);
+        }
+    }
+
+    // Drops the chunk's mesh, keeping its buffers for another chunk if the
+    // pool has room.
+    fn remove_chunk(&mut self, position: cgmath::Vector3<i32>) {
+        if let Some(chunk) = self.chunks.remove(&position) {
+            if self.chunk_pool.len() < self.max_pooled_chunks {
+                self.chunk_pool.push(chunk);
+            }
+        }
+    }
+
+    fn clear_chunks(&mut self) {
+        let positions = self.chunks.keys().copied().collect::<Vec<_>>();
+        for position in positions {
+            self.remove_chunk(position);
+        }
+    }
+
+    // How many chunks' buffers are waiting in the pool to be reused, and how
+    // many bytes of GPU memory they hold.
+    pub fn chunk_pool_size(&self) -> (usize, u64) {
+        let bytes = self.chunk_pool.iter().map(GpuChunk::capacity).sum();
+        (self.chunk_pool.len(), bytes)
+    }
+
+    pub fn render_distance(&self) -> u32 {
+        self.render_distance
+    }
+
+    // Sets how many chunk columns out from the camera's chunks are
+    // generated and drawn, clamped to 1 to 32. Chunks are unloaded a couple
+    // of columns farther out.
+    pub fn set_render_distance(&mut self, distance: u32) {
+        self.render_distance = distance.clamp(MIN_RENDER_DISTANCE, MAX_RENDER_DISTANCE);
+        // Streaming covers columns less than `stream_radius` away.
+        let stream_radius = self.render_distance as i32 + 1;
+        self.world.config.stream_radius = stream_radius;
+        self.world.config.unload_radius = stream_radius + UNLOAD_MARGIN;
+    }
+
+    fn update_items(&mut self) {
+        let scale = ITEM_SIZE * 0.5;
+        let item_data = self
+            .world
+            .items()
+            .map(|item| {
+                let rotation = cgmath::Quaternion::from_angle_y(cgmath::Rad(item.spin()));
+                let position = self.world.to_render_space(item.position).to_vec()
+                    - rotation.rotate_vector(CUBE_CENTER * scale);
+                Instance {
+                    position,
+                    rotation,
+                    scale,
+                }
+                .to_raw()
+            })
+            .collect::<Vec<_>>();
+
+        if item_data.len() > self.item_instance_capacity {
+            self.item_instance_capacity = item_data.len().next_power_of_two();
+            self.item_instance_buffer =
+                Self::create_item_instance_buffer(&self.device, self.item_instance_capacity);
+        }
+        self.queue.write_buffer(
+            &self.item_instance_buffer,
+            0,
+            bytemuck::cast_slice(&item_data),
+        );
+        self.num_item_instances = item_data.len() as u32;
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.projection.resize(new_size.width, new_size.height);
+            self.configure_surface();
+            self.recreate_size_dependent_targets();
+            // A frame rendered before the next update would otherwise still
+            // use the old aspect ratio.
+            self.upload_camera();
+        }
+    }
+
+    fn upload_camera(&mut self) {
+        self.camera_uniform
+            .update_view_proj(&self.render_camera(&self.camera), &self.projection);
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
+    // Rebuilds every render target that has to match the surface size. Any
+    // new size-dependent target belongs here so they can't get out of sync.
+    fn recreate_size_dependent_targets(&mut self) {
+        self.depth_texture = Texture::create_depth_texture(
+            &self.device,
+            &self.config,
+            self.sample_count,
+            "depth_texture",
+        );
+        if self.sample_count > 1 {
+            self.msaa_texture = Some(Texture::create_multisampled_texture(
+                &self.device,
+                &self.config,
+                self.sample_count,
+                "msaa_texture",
+            ));
+        }
+    }
+
+    // The surface's present mode right now. Changes over time with
+    // `PresentMode::Adaptive`.
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    // The present modes the surface supports, always including Fifo.
+    pub fn supported_present_modes(&self) -> Vec<wgpu::PresentMode> {
+        self.surface.as_ref().map_or_else(
+            || vec![wgpu::PresentMode::Fifo],
+            |surface| surface.get_capabilities(&self.adapter).present_modes,
+        )
+    }
+
+    fn configure_surface(&self) {
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    // Switches to a fixed present mode, turning off `PresentMode::Adaptive`.
+    // Unsupported modes fall back to Fifo. Returns the mode in use.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) -> wgpu::PresentMode {
+        let mode = if self.supported_present_modes().contains(&mode) {
+            mode
+        } else {
+            log::warn!(
+                "surface doesn't support present mode {:?}, using Fifo",
+                mode
+            );
+            wgpu::PresentMode::Fifo
+        };
+        self.adaptive_present = None;
+        self.config.present_mode = mode;
+        self.configure_surface();
+        mode
+    }
+
+    // Goes back to picking the present mode from how frames keep up.
+    pub fn set_adaptive_present(&mut self) {
+        let adaptive = AdaptivePresent::new(&self.supported_present_modes(), self.refresh_interval);
+        self.config.present_mode = adaptive.current();
+        self.adaptive_present = Some(adaptive);
+        self.configure_surface();
+    }
+
+    // Steps through the supported present modes, from vsync to uncapped,
+    // and returns the new one. Toggled with F9.
+    pub fn cycle_present_mode(&mut self) -> wgpu::PresentMode {
+        const ORDER: [wgpu::PresentMode; 4] = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::FifoRelaxed,
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Immediate,
+        ];
+        let supported = self.supported_present_modes();
+        let modes: Vec<_> = ORDER
+            .into_iter()
+            .filter(|m| supported.contains(m))
+            .collect();
+        let next = match modes.iter().position(|&m| m == self.config.present_mode) {
+            Some(i) => modes[(i + 1) % modes.len()],
+            None => wgpu::PresentMode::Fifo,
+        };
+        let mode = self.set_present_mode(next);
+        log::info!("present mode {:?}", mode);
+        mode
+    }
+
+    pub fn alpha_mode(&self) -> wgpu::CompositeAlphaMode {
+        self.config.alpha_mode
+    }
+
+    // A transparent surface is cleared to transparent black, which is the
+    // same whether the compositor expects premultiplied alpha or not.
+    fn clear_color(&self) -> wgpu::Color {
+        match self.config.alpha_mode {
+            wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied => {
+                wgpu::Color::TRANSPARENT
+            }
+            _ => self.world.time.sky_color(),
+        }
+    }
+
+    pub fn max_chunks_per_frame_generate(&self) -> usize {
+        self.world.streamer.max_inserts_per_frame
+    }
+
+    // Caps how many newly generated chunks are added to the world each frame.
+    // Chunks over the cap wait for the next frame.
+    pub fn set_max_chunks_per_frame_generate(&mut self, max: usize) {
+        self.world.streamer.max_inserts_per_frame = max;
+    }
+
+    pub fn chunk_insert_budget_ms(&self) -> f32 {
+        self.world.streamer.insert_budget.as_secs_f32() * 1000.0
+    }
+
+    // Caps how long adding generated chunks to the world may take each
+    // frame, on top of the count limit.
+    pub fn set_chunk_insert_budget_ms(&mut self, ms: f32) {
+        self.world.streamer.insert_budget = instant::Duration::from_secs_f32(ms.max(0.0) / 1000.0);
+    }
+
+    pub fn held_item(&self) -> Option<VoxelType> {
+        self.held_item.item
+    }
+
+    // Shows `item` in the corner of the view, or nothing with `None`.
+    pub fn set_held_item(&mut self, item: Option<VoxelType>) {
+        if self.held_item.item == item {
+            return;
+        }
+        self.held_item.item = item;
+        self.upload_held_mesh();
+    }
+
+    fn upload_held_mesh(&mut self) {
+        self.held_mesh = self.held_item.item.map(|voxel_type| {
+            let origin = cgmath::Vector3::new(0, 0, 0);
+            GpuChunk::new(&self.device, origin, origin, &held::held_mesh(voxel_type))
+        });
+    }
+
+    pub fn show_chunk_borders(&self) -> bool {
+        self.show_chunk_borders
+    }
+
+    // Outlines every loaded chunk, colored by whether it's meshed, waiting
+    // to be remeshed or empty. Toggled with F7.
+    pub fn set_show_chunk_borders(&mut self, show: bool) {
+        self.show_chunk_borders = show;
+    }
+
+    pub fn show_hud(&self) -> bool {
+        self.show_hud
+    }
+
+    // Shows the position, chunk, targeted voxel and frame rate in the top
+    // left of the window. Toggled with F3.
+    pub fn set_show_hud(&mut self, show: bool) {
+        self.show_hud = show;
+    }
+
+    pub fn targeted_voxel(&self) -> Option<cgmath::Vector3<i32>> {
+        self.targeted_voxel
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    // Switches chunks between solid and wireframe, returning whether they're
+    // now drawn as wireframes. Stays solid if the device can't draw lines.
+    // Toggled with F8.
+    pub fn toggle_wireframe(&mut self) -> bool {
+        if self.wireframe_pipeline.is_none() {
+            log::warn!("wireframe rendering isn't supported by this device");
+            return false;
+        }
+        self.wireframe = !self.wireframe;
+        self.wireframe
+    }
+
+    pub fn ui_open(&self) -> bool {
+        self.ui_open
+    }
+
+    // While a UI panel (e.g. the inventory) is open the crosshair is hidden
+    // and the cursor is released so it can be used to interact with the UI.
+    pub fn set_ui_open(&mut self, open: bool) {
+        self.ui_open = open;
+        if open {
+            self.break_held = false;
+        }
+    }
+
+    pub fn crosshair_visible(&self) -> bool {
+        !self.ui_open
+    }
+
+    // Whether the fullscreen key was pressed since the last call. Toggled
+    // with F11.
+    pub fn take_fullscreen_request(&mut self) -> bool {
+        std::mem::take(&mut self.fullscreen_requested)
+    }
+
+    pub fn wants_cursor_capture(&self) -> bool {
+        !self.ui_open
+    }
+
+    pub fn save_world<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.world.save(path)
+    }
+
+    // Replaces the world with a save, with the camera back where it was when
+    // it was saved. The world's config and mesher aren't saved, so they carry
+    // over from the current world.
+    pub fn load_world<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut world = World::load(path)?;
+        world.config = self.world.config.clone();
+        // Only the seed is saved. The rest of the generator's settings are
+        // kept, like the config, so terrain past the saved chunks still
+        // matches what was there.
+        world.generator = TerrainGenerator {
+            seed: world.generator.seed,
+            ..self.world.generator.clone()
+        };
+        world.set_mesher(Arc::clone(self.world.mesher()));
+        self.world = world;
+        // The held item's transforms lived in the old world.
+        let item = self.held_item.item;
+        self.held_item = HeldItem::new(&mut self.world.transforms);
+        self.held_item.item = item;
+
+        let player = &self.world.player;
+        self.camera = Camera::new(player.eye(), player.yaw, player.pitch);
+        self.world_epoch = self.world.epoch();
+        self.clear_chunks();
+        self.mesh_jobs.clear();
+        self.stats = RenderStats::default();
+        self.targeted_voxel = None;
+        self.upload_camera();
+        Ok(())
+    }
+
+    // Rebuilds the surface for a new window, e.g. when the canvas element is
+    // replaced on the web. The device and everything uploaded to it are kept.
+    // The old surface may already be lost, so it's only ever dropped, before
+    // the old window is let go of.
+    pub fn recreate_surface(&mut self, window: Arc<Window>) -> Result<()> {
+        let surface = unsafe { self.instance.create_surface(window.as_ref()) }?;
+        ensure!(
+            self.adapter.is_surface_supported(&surface),
+            "adapter can't present to the new surface"
+        );
+        // Pipelines are built for the current format, so it has to carry over.
+        let capabilities = surface.get_capabilities(&self.adapter);
+        ensure!(
+            capabilities.formats.contains(&self.config.format),
+            "new surface doesn't support format {:?}",
+            self.config.format
+        );
+
+        let size = window.inner_size();
+        self.surface = Some(surface);
+        self.window = Some(window);
+        if size.width > 0 && size.height > 0 {
+            self.resize(size);
+        } else {
+            self.configure_surface();
+        }
+        Ok(())
+    }
+
+    // Moves everything onto a new device after the old one was lost, e.g. to
+    // a GPU reset or an adapter being removed. The surface is kept and
+    // reconfigured, and chunk meshes are rebuilt from the world, which never
+    // leaves the CPU. Also works while the device is fine.
+    pub async fn recreate_device(&mut self) -> Result<()> {
+        let new_device = self.request_new_device().await?;
+        self.replace_device(new_device)
+    }
+
+    // The first half of `recreate_device`. The future doesn't borrow the
+    // renderer, so it can be spawned rather than blocked on, which the web
+    // doesn't allow.
+    pub fn request_new_device(&self) -> impl Future<Output = Result<NewDevice>> {
+        let found =
+            Self::request_device(&self.instance, self.surface.as_ref(), self.power_preference);
+        async move {
+            let (adapter, device, queue) = found.await?;
+            Ok(NewDevice {
+                adapter,
+                device,
+                queue,
+            })
+        }
+    }
+
+    // The second half of `recreate_device`, once the new device is there.
+    pub fn replace_device(&mut self, new_device: NewDevice) -> Result<()> {
+        let NewDevice {
+            adapter,
+            device,
+            queue,
+        } = new_device;
+        let device_lost = Self::watch_for_device_loss(&device);
+
+        // A different adapter may not support everything the last one did.
+        let mut config = self.config.clone();
+        if let Some(surface) = &self.surface {
+            let capabilities = surface.get_capabilities(&adapter);
+            ensure!(
+                !capabilities.formats.is_empty(),
+                "new adapter can't present to the surface"
+            );
+            if !capabilities.formats.contains(&config.format) {
+                config.format = capabilities.formats[0];
+            }
+            config.alpha_mode = supported_alpha_mode(config.alpha_mode, &capabilities.alpha_modes);
+            if self.adaptive_present.is_some() {
+                let adaptive =
+                    AdaptivePresent::new(&capabilities.present_modes, self.refresh_interval);
+                config.present_mode = adaptive.current();
+                self.adaptive_present = Some(adaptive);
+            } else if !capabilities.present_modes.contains(&config.present_mode) {
+                config.present_mode = wgpu::PresentMode::Fifo;
+            }
+            config.usage = Self::surface_usage(&adapter);
+            surface.configure(&device, &config);
+        }
+
+        let sample_count =
+            DeviceResources::sample_count(&adapter, &device, config.format, self.sample_count);
+        let resources = DeviceResources::new(
+            &device,
+            &queue,
+            &config,
+            sample_count,
+            &self.camera_uniform,
+            &self.light_uniform,
+            self.item_instance_capacity,
+        )?;
+        let text_renderer = TextRenderer::new(
+            &device,
+            &queue,
+            config.format,
+            sample_count,
+            self.text_renderer.atlas().clone(),
+        );
+        let line_width = self.debug_renderer.line_width;
+        log::info!("recreated GPU device");
+
+        self.adapter = adapter;
+        self.device = device;
+        self.queue = queue;
+        self.device_lost = device_lost;
+        self.config = config;
+        self.sample_count = sample_count;
+        self.render_pipeline = resources.render_pipeline;
+        self.transparent_pipeline = resources.transparent_pipeline;
+        self.wireframe_pipeline = resources.wireframe_pipeline;
+        self.wireframe &= self.wireframe_pipeline.is_some();
+        self.vertex_buffer = resources.vertex_buffer;
+        self.num_vertices = resources.num_vertices;
+        self.index_buffer = resources.index_buffer;
+        self.num_indices = resources.num_indices;
+        self.camera_buffer = resources.camera_buffer;
+        self.camera_bind_group = resources.camera_bind_group;
+        self.camera_bind_group_layout = resources.camera_bind_group_layout;
+        self.light_buffer = resources.light_buffer;
+        self.light_bind_group = resources.light_bind_group;
+        self.instance_buffer = resources.instance_buffer;
+        self.cube_instance = resources.cube_instance;
+        self.item_instance_buffer = resources.item_instance_buffer;
+        self.atlas = resources.atlas;
+        self.depth_texture = resources.depth_texture;
+        self.msaa_texture = resources.msaa_texture;
+        self.debug_renderer = resources.debug_renderer;
+        self.debug_renderer.line_width = line_width;
+        self.text_renderer = text_renderer;
+
+        self.upload_held_mesh();
+        self.instanced_meshes = std::mem::take(&mut self.instanced_meshes)
+            .into_iter()
+            .map(|mesh| InstancedMesh::new(&self.device, mesh.mesh))
+            .collect();
+        // Pooled buffers belonged to the old device too.
+        self.chunks.clear();
+        self.chunk_pool.clear();
+        self.mesh_jobs.clear();
+        self.stats = RenderStats::default();
+        self.world.mark_all_dirty();
+        Ok(())
+    }
+
+    pub fn shading(&self) -> Shading {
+        self.light_uniform.shading()
+    }
+
+    // Both sets of normals are baked into every chunk mesh, so switching
+    // only updates the light uniform.
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.light_uniform.set_shading(shading);
+        self.write_light_uniform();
+    }
+
+    // Fixes the direction towards the sun instead of following the time of
+    // day. It's normalized, so it only needs to be non-zero.
+    pub fn set_sun_direction(&mut self, direction: cgmath::Vector3<f32>) {
+        if direction.magnitude2() > 0.0 {
+            self.sun_direction = Some(direction);
+        }
+    }
+
+    // Lets the time of day move the sun again.
+    pub fn clear_sun_direction(&mut self) {
+        self.sun_direction = None;
+    }
+
+    pub fn sun_color(&self) -> [f32; 3] {
+        self.light_uniform.color()
+    }
+
+    pub fn set_sun_color(&mut self, color: [f32; 3]) {
+        self.light_uniform.set_color(color);
+        self.write_light_uniform();
+    }
+
+    fn write_light_uniform(&self) {
+        self.queue.write_buffer(
+            &self.light_buffer,
+            0,
+            bytemuck::cast_slice(&[self.light_uniform]),
+        );
+    }
+
+    // Handles a window event and returns whether it was used. Keys go
+    // through `key_bindings`, except that `Action::Quit` is left to the
+    // caller.
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                // Toggles only react to the initial press, not to key repeats.
+                let just_pressed = self.input.process(*key, *state);
+                match self.key_bindings.action(*key) {
+                    Some(action) => self.handle_action(action, just_pressed),
+                    None => false,
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.camera_controller.process_scroll(delta);
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => {
+                self.break_held = *state == ElementState::Pressed && !self.ui_open;
+                self.break_pressed |= self.break_held;
+                true
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Right,
+                state: ElementState::Pressed,
+                ..
+            } => {
+                self.place_pressed |= !self.ui_open;
+                true
+            }
+            WindowEvent::Focused(false) => {
+                self.input.clear();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn is_action_held(&self, action: Action) -> bool {
+        self.key_bindings.is_held(&self.input, action)
+            || self.gamepad_bindings.is_held(&self.gamepads, action)
+    }
+
+    // Controller events, fed from a gamepad backend like gilrs. Bound
+    // buttons trigger the same actions as keys. Returns whether the button
+    // did anything.
+    pub fn gamepad_button(&mut self, id: usize, button: GamepadButton, pressed: bool) -> bool {
+        let just_pressed = self.gamepads.process_button(id, button, pressed);
+        match self.gamepad_bindings.action(button) {
+            Some(action) => self.handle_action(action, just_pressed),
+            None => false,
+        }
+    }
+
+    pub fn gamepad_axis(&mut self, id: usize, axis: GamepadAxis, value: f32) {
+        self.gamepads.process_axis(id, axis, value);
+    }
+
+    pub fn gamepad_connected(&mut self, id: usize) {
+        log::info!("gamepad {} connected", id);
+        self.gamepads.connect(id);
+    }
+
+    // Lets go of everything the controller was holding, so the player
+    // doesn't keep walking after it's unplugged.
+    pub fn gamepad_disconnected(&mut self, id: usize) {
+        log::info!("gamepad {} disconnected", id);
+        for button in self.gamepads.disconnect(id) {
+            if let Some(action) = self.gamepad_bindings.action(button) {
+                self.handle_action(action, false);
+            }
+        }
+        self.apply_gamepad_movement();
+    }
+
+    // Walks as far as the left stick is pushed, or at full speed in a
+    // direction whose key or button is held.
+    fn apply_gamepad_movement(&mut self) {
+        let [x, y] = self.gamepads.left_stick();
+        let directions = [
+            (Action::MoveForward, y),
+            (Action::MoveBack, -y),
+            (Action::MoveRight, x),
+            (Action::MoveLeft, -x),
+        ];
+        for (action, amount) in directions {
+            let amount = if self.is_action_held(action) {
+                1.0
+            } else {
+                amount
+            };
+            self.camera_controller.process_axis(action, amount);
+        }
+    }
+
+    fn handle_action(&mut self, action: Action, just_pressed: bool) -> bool {
+        match action {
+            Action::MoveForward
+            | Action::MoveBack
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::MoveUp
+            | Action::MoveDown => {
+                // Double tapping jump toggles flying. The taps still reach
+                // the camera controller, so single taps jump as usual.
+                if just_pressed
+                    && action == Action::MoveUp
+                    && self.fly_toggle.press(instant::Instant::now())
+                {
+                    self.world.player.flying = !self.world.player.flying;
+                }
+                // Checked across every bound key and button, so letting go
+                // of one of two for the same direction doesn't stop moving.
+                let held = self.is_action_held(action);
+                self.camera_controller.process_action(action, held)
+            }
+            Action::SelectSlot(slot) => {
+                if just_pressed {
+                    self.world.inventory.select_slot(slot);
+                }
+                true
+            }
+            Action::ToggleInventory => {
+                if just_pressed {
+                    self.set_ui_open(!self.ui_open);
+                }
+                true
+            }
+            Action::ToggleChunkBorders => {
+                if just_pressed {
+                    self.show_chunk_borders = !self.show_chunk_borders;
+                }
+                true
+            }
+            Action::ToggleHud => {
+                if just_pressed {
+                    self.show_hud = !self.show_hud;
+                }
+                true
+            }
+            Action::ToggleFullscreen => {
+                self.fullscreen_requested |= just_pressed;
+                true
+            }
+            Action::ToggleWireframe => {
+                if just_pressed {
+                    self.toggle_wireframe();
+                }
+                true
+            }
+            Action::CyclePresentMode => {
+                if just_pressed {
+                    self.cycle_present_mode();
+                }
+                true
+            }
+            Action::CycleTool => {
+                if just_pressed {
+                    let tool = self.world.cycle_tool();
+                    log::info!("switched to the {} tool", tool.name());
+                }
+                true
+            }
+            Action::Screenshot => {
+                if just_pressed {
+                    self.request_screenshot(".");
+                }
+                true
+            }
+            Action::IncreaseRenderDistance | Action::DecreaseRenderDistance => {
+                if just_pressed {
+                    let distance = match action {
+                        Action::IncreaseRenderDistance => self.render_distance + 1,
+                        _ => self.render_distance - 1,
+                    };
+                    self.set_render_distance(distance);
+                    log::info!("render distance {}", self.render_distance);
+                }
+                true
+            }
+            Action::Quit => false,
+        }
+    }
+
+    pub fn update(&mut self, dt: instant::Duration) {
+        self.poll();
+        self.frame_timer.record(dt);
+        if let Some(mode) = self.adaptive_present.as_mut().and_then(|p| p.frame(dt)) {
+            log::info!("switching present mode to {:?}", mode);
+            self.config.present_mode = mode;
+            self.configure_surface();
+        }
+        if self.gamepads.is_connected() {
+            self.apply_gamepad_movement();
+            if !self.ui_open {
+                let [x, y] = self.gamepads.right_stick();
+                let speed = self.gamepad_look_speed * dt.as_secs_f32();
+                // Stick up is positive but mouse movement up is negative.
+                self.camera_controller
+                    .process_mouse((x * speed) as f64, (-y * speed) as f64);
+            }
+        }
+        // The player may have been teleported since the last frame.
+        self.camera.position = self.world.player.eye();
+        let start = self.camera.position;
+        if self.world.player.flying {
+            self.camera_controller.update_camera(&mut self.camera, dt);
+            self.world.player.set_eye(self.camera.position);
+        } else {
+            let (walk, jump) = self.camera_controller.update_walking(&mut self.camera);
+            self.world.update_player(dt, walk, jump);
+            self.camera.position = self.world.player.eye();
+        }
+        self.world.player.yaw = self.camera.yaw();
+        self.world.player.pitch = self.camera.pitch();
+        self.update_interaction(dt);
+        self.world.update(dt, self.camera.position);
+        let camera_chunk = self
+            .camera
+            .position
+            .map(|v| (v / CHUNK_SIZE as f32).floor() as i32)
+            .to_vec();
+        self.world.stream_chunks(camera_chunk);
+        self.world.evict_chunks(camera_chunk);
+        if self.origin != self.world.origin() {
+            self.origin = self.world.origin();
+            for chunk in self.chunks.values() {
+                chunk.set_origin(&self.queue, self.origin);
+            }
+            let cube = Instance {
+                position: self.cube_instance.position - self.origin.cast::<f32>().unwrap(),
+                ..self.cube_instance
+            };
+            self.queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&[cube.to_raw()]),
+            );
+        }
+        // Before remeshing, so chunks waiting on it show up as dirty.
+        if self.show_chunk_borders {
+            self.draw_chunk_borders();
+        }
+        self.update_chunks();
+        self.update_items();
+        self.draw_hud(camera_chunk);
+        self.update_held_item(start, dt);
+        if let Some(corner) = self.world.box_selection.corner() {
+            // The box the next click would select, out to the targeted voxel.
+            let target = self.targeted_voxel.unwrap_or(corner);
+            if let Some((min, max)) = self.world.box_selection.preview(target) {
+                let min = cgmath::Point3::from_vec(min.cast::<f32>().unwrap());
+                let max = cgmath::Point3::from_vec(max.cast::<f32>().unwrap());
+                self.draw_debug_box(
+                    min,
+                    max + cgmath::Vector3::new(1.0, 1.0, 1.0),
+                    SELECTION_COLOR,
+                );
+            }
+        }
+        self.upload_camera();
+        let sun_direction = self
+            .sun_direction
+            .unwrap_or_else(|| self.world.time.sun_direction());
+        self.light_uniform.set_direction(sun_direction);
+        self.write_light_uniform();
+        self.input.end_frame();
+    }
+
+    fn draw_chunk_borders(&mut self) {
+        // Column meshes are stored under the lowest chunk of each run.
+        let is_meshed = |p: cgmath::Vector3<i32>| {
+            self.chunks.contains_key(&p)
+                || (self.column_meshing
+                    && self
+                        .chunks
+                        .keys()
+                        .any(|c| c.x == p.x && c.z == p.z && c.y <= p.y))
+        };
+        for border in chunk_borders(&self.world, is_meshed) {
+            self.draw_debug_box(border.min, border.max, border.state.color());
+        }
+    }
+
+    // Keeps the held block in front of the camera. Runs after the world
+    // update so it uses the same origin as the rest of this frame.
+    fn update_held_item(&mut self, start: cgmath::Point3<f32>, dt: instant::Duration) {
+        let moved = self.camera.position - start;
+        let walked = cgmath::Vector2::new(moved.x, moved.z).magnitude();
+        let camera = self.render_camera(&self.camera);
+        self.held_item
+            .update(&mut self.world.transforms, &camera, walked, dt);
+        self.world.transforms.propagate();
+
+        if let (Some(mesh), Some(model)) = (
+            &self.held_mesh,
+            self.world
+                .transforms
+                .world(self.held_item.model_transform()),
+        ) {
+            self.queue.write_buffer(
+                &mesh.instance_buffer,
+                0,
+                bytemuck::cast_slice(&[InstanceRaw {
+                    model: model.into(),
+                }]),
+            );
+        }
+    }
+
+    // Breaks or places voxels at the crosshair and outlines the targeted one.
+    fn update_interaction(&mut self, dt: instant::Duration) {
+        let (break_pressed, place_pressed) = (self.break_pressed, self.place_pressed);
+        self.break_pressed = false;
+        self.place_pressed = false;
+        self.targeted_voxel = None;
+        if self.ui_open {
+            self.world.mining.reset();
+            return;
+        }
+        let hit = self
+            .world
+            .target_voxel(self.camera.position, self.camera.forward());
+        self.targeted_voxel = hit.map(|hit| hit.voxel);
+        match self.world.tool_mode {
+            ToolMode::Single => {
+                self.world.update_breaking(
+                    dt,
+                    hit.map(|hit| hit.voxel),
+                    self.break_held,
+                    break_pressed,
+                );
+                if let (Some(hit), true) = (hit, place_pressed) {
+                    self.world.place_against(hit);
+                }
+            }
+            // Clicks pick the box's corners instead. Breaking picks the
+            // targeted voxel and clears the box, placing picks the cell in
+            // front of it and fills the box.
+            ToolMode::BoxFill => {
+                self.world.mining.reset();
+                match hit {
+                    Some(hit) if break_pressed => {
+                        self.world.box_tool_click(hit.voxel, true);
+                    }
+                    Some(hit) if place_pressed => {
+                        self.world.box_tool_click(hit.voxel + hit.normal, false);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Some(hit) = hit {
+            let min = cgmath::Point3::from_vec(hit.voxel.cast::<f32>().unwrap());
+            self.draw_debug_box(min, min + cgmath::Vector3::new(1.0, 1.0, 1.0), TARGET_COLOR);
+        }
+    }
+
+    // Queues the crosshair and, if shown, the debug readout for this frame.
+    // Both are laid out in pixels at the current window size.
+    fn draw_hud(&mut self, camera_chunk: cgmath::Vector3<i32>) {
+        if self.crosshair_visible() {
+            for (pos, size) in hud::crosshair_rects([self.config.width, self.config.height]) {
+                self.text_renderer
+                    .draw_rect(pos, size, hud::CROSSHAIR_COLOR);
+            }
+        }
+        if self.show_hud {
+            let info = HudInfo {
+                position: self.camera.position,
+                chunk: camera_chunk,
+                target: self.targeted_voxel,
+                fps: self.fps(),
+                frame_time_ms: self.frame_time_ms(),
+                tool: self.world.tool_mode,
+            };
+            let margin = hud::TEXT_MARGIN;
+            self.text_renderer
+                .draw(&info.text(), [margin, margin], 2.0, hud::TEXT_COLOR);
+        }
+    }
+
+    // Queues a wireframe box to be drawn this frame. Boxes are inflated
+    // slightly so they don't z-fight with the voxel faces they outline.
+    pub fn draw_debug_box(
+        &mut self,
+        min: cgmath::Point3<f32>,
+        max: cgmath::Point3<f32>,
+        color: [f32; 3],
+    ) {
+        let inflate = cgmath::Vector3::new(0.002, 0.002, 0.002);
+        let (min, max) = (
+            self.world.to_render_space(min),
+            self.world.to_render_space(max),
+        );
+        self.debug_renderer
+            .aabb(min - inflate, max + inflate, color);
+    }
+
+    pub fn draw_debug_line(
+        &mut self,
+        a: cgmath::Point3<f32>,
+        b: cgmath::Point3<f32>,
+        color: [f32; 3],
+    ) {
+        let (a, b) = (self.world.to_render_space(a), self.world.to_render_space(b));
+        self.debug_renderer.line(a, b, color);
+    }
+
+    // Outlines `frustum`, e.g. one taken from a detached camera to check
+    // what it would cull.
+    pub fn draw_debug_frustum(&mut self, frustum: &Frustum, color: [f32; 3]) {
+        let corners = frustum.corners();
+        for (a, b) in Frustum::EDGES {
+            self.draw_debug_line(corners[a], corners[b], color);
+        }
+    }
+
+    // Uploads a mesh, e.g. a torch or a plant, to draw any number of copies
+    // of with `draw_instanced`. It stays uploaded for as long as the
+    // renderer lives.
+    pub fn upload_mesh(&mut self, mesh: ChunkMesh) -> MeshHandle {
+        self.instanced_meshes
+            .push(InstancedMesh::new(&self.device, mesh));
+        MeshHandle(self.instanced_meshes.len() - 1)
+    }
+
+    // Queues a copy of `mesh` for this frame at each of `instances`, with
+    // positions in world space. However many are queued, each mesh is drawn
+    // with a single call.
+    pub fn draw_instanced(&mut self, mesh: MeshHandle, instances: &[Instance]) {
+        let world = &self.world;
+        let queued = &mut self.instanced_meshes[mesh.0].queued;
+        queued.extend(instances.iter().map(|instance| {
+            let position = world.to_render_space(cgmath::Point3::from_vec(instance.position));
+            Instance {
+                position: position.to_vec(),
+                ..*instance
+            }
+            .to_raw()
+        }));
+    }
+
+    // How many copies of `mesh` are queued for the next frame.
+    pub fn queued_instances(&self, mesh: MeshHandle) -> usize {
+        self.instanced_meshes[mesh.0].queued.len()
+    }
+
+    // Queues screen-space text for this frame, with `pos` in pixels from the
+    // top left of the window.
+    pub fn draw_text(&mut self, text: &str, pos: [f32; 2], scale: f32, color: [f32; 4]) {
+        self.text_renderer.draw(text, pos, scale, color);
+    }
+
+    // Swaps the glyph atlas, e.g. for one rasterized from a TrueType font.
+    pub fn set_font(&mut self, atlas: GlyphAtlas) {
+        self.text_renderer = TextRenderer::new(
+            &self.device,
+            &self.queue,
+            self.config.format,
+            self.sample_count,
+            atlas,
+        );
+    }
+
+    pub fn outline_width(&self) -> LineWidth {
+        self.debug_renderer.line_width
+    }
+
+    // Applies to the selection outline and every other debug line.
+    pub fn set_outline_width(&mut self, width: LineWidth) {
+        self.debug_renderer.line_width = width;
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.is_device_lost() {
+            return Err(wgpu::SurfaceError::Lost);
+        }
+        self.debug_renderer.prepare(
+            &self.device,
+            &self.queue,
+            [self.config.width, self.config.height],
+            self.projection.focal_length(),
+        );
+        self.text_renderer.prepare(
+            &self.device,
+            &self.queue,
+            [self.config.width, self.config.height],
+        );
+        for mesh in &mut self.instanced_meshes {
+            mesh.prepare(&self.device, &self.queue);
+        }
+        // Headless renderers have nothing to present to.
+        let Some(surface) = &self.surface else {
+            return Err(wgpu::SurfaceError::Outdated);
+        };
+        let output = surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        let render_camera = self.render_camera(&self.camera);
+        let frustum = render_camera.frustum(&self.projection);
+        let (drawn, culled) = {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[
                     // This is what @location(0) in the fragment shader targets
                     Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
+                        view: self.msaa_texture.as_ref().map_or(&view, |msaa| &msaa.view),
+                        resolve_target: self.msaa_texture.as_ref().map(|_| &view),
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.0,
-                                g: 0.0,
-                                b: 0.0,
-                                a: 1.0,
-                            }),
+                            load: wgpu::LoadOp::Clear(self.clear_color()),
                             store: true,
                         },
                     }),
@@ -458,19 +2524,926 @@ impl Renderer {
                     stencil_ops: None,
                 }),
             });
+            let counts = self.draw_scene(
+                &mut render_pass,
+                &self.camera_bind_group,
+                &frustum,
+                render_camera.position,
+            );
+            self.draw_held_item(&mut render_pass);
+            self.debug_renderer
+                .draw(&mut render_pass, &self.camera_bind_group);
+            self.text_renderer.draw_queued(&mut render_pass);
+            counts
+        };
+        self.chunks_drawn = drawn;
+        self.chunks_culled = culled;
+
+        // Submitting to a lost device panics, and it can be lost partway
+        // through the frame.
+        if self.is_device_lost() {
+            return Err(wgpu::SurfaceError::Lost);
+        }
+        self.queue.submit(iter::once(encoder.finish()));
+        if let Some(path) = self.screenshot_requested.take() {
+            match self.capture_frame(&output.texture) {
+                Ok(readback) => self.screenshots.push(PendingScreenshot {
+                    path,
+                    width: self.config.width,
+                    height: self.config.height,
+                    readback,
+                }),
+                Err(err) => log::error!("couldn't take a screenshot: {}", err),
+            }
+        }
+        output.present();
+
+        Ok(())
+    }
+
+    // Returns how many chunks were drawn and how many were culled for being
+    // outside `frustum` or past the render distance from `eye`. Transparent
+    // chunks are drawn farthest from `eye` first. Both have to be in render
+    // space.
+    fn draw_scene<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+        frustum: &Frustum,
+        eye: cgmath::Point3<f32>,
+    ) -> (usize, usize) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_bind_group(0, &self.atlas.bind_group, &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        // render_pass.draw_indexed(0..self.num_indices, 0, 0..self.instances.len() as _);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+
+        // Each chunk's mesh bounds in render space.
+        let bounds = |chunk: &GpuChunk| {
+            let offset = (chunk.position * CHUNK_SIZE as i32 - self.origin)
+                .cast::<f32>()
+                .unwrap();
+            let (min, max) = chunk.bounds;
+            (
+                cgmath::Point3::from(min) + offset,
+                cgmath::Point3::from(max) + offset,
+            )
+        };
+        let eye_chunk = (eye.to_vec() + self.origin.cast::<f32>().unwrap())
+            .map(|v| (v / CHUNK_SIZE as f32).floor() as i32);
+        let in_range = |chunk: &GpuChunk| {
+            let d = chunk.position - eye_chunk;
+            d.x.unsigned_abs().max(d.z.unsigned_abs()) <= self.render_distance
+        };
+        let visible: Vec<_> = self
+            .chunks
+            .values()
+            .filter(|chunk| {
+                let (min, max) = bounds(chunk);
+                in_range(chunk) && frustum.intersects_aabb(min, max)
+            })
+            .collect();
+        // Wireframes draw transparent faces with the rest, so they aren't
+        // hidden behind the opaque ones.
+        let wireframe_pipeline = self.wireframe_pipeline.as_ref().filter(|_| self.wireframe);
+        if let Some(pipeline) = wireframe_pipeline {
+            render_pass.set_pipeline(pipeline);
+        }
+        for chunk in &visible {
+            let num_indices = match wireframe_pipeline {
+                Some(_) => chunk.num_indices + chunk.num_transparent_indices,
+                None => chunk.num_indices,
+            };
+            render_pass.set_vertex_buffer(0, chunk.vertex_buffer.slice());
+            render_pass.set_vertex_buffer(1, chunk.instance_buffer.slice(..));
+            render_pass.set_index_buffer(chunk.index_buffer.slice(), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..num_indices, 0, 0..1);
+        }
+
+        if self.num_item_instances > 0 {
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
             render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            // render_pass.draw_indexed(0..self.num_indices, 0, 0..self.instances.len() as _);
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+            render_pass.set_vertex_buffer(1, self.item_instance_buffer.slice(..));
+            render_pass.draw_indexed(0..self.num_indices, 0, 0..self.num_item_instances);
+        }
+        render_pass.set_pipeline(&self.render_pipeline);
+        for mesh in &self.instanced_meshes {
+            mesh.draw(render_pass, false);
+        }
+
+        if wireframe_pipeline.is_some() {
+            return (visible.len(), self.chunks.len() - visible.len());
         }
+        // Back to front, so nearer transparent chunks blend over farther ones
+        // and the order doesn't change from frame to frame. Faces inside a
+        // chunk keep the order they were meshed in. Opaque faces were all
+        // drawn first, so anything they hide stays hidden.
+        let mut transparent: Vec<_> = visible
+            .iter()
+            .filter(|chunk| chunk.num_transparent_indices > 0)
+            .map(|&chunk| {
+                let (min, max) = bounds(chunk);
+                (min.midpoint(max).distance2(eye), chunk)
+            })
+            .collect();
+        transparent.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        render_pass.set_pipeline(&self.transparent_pipeline);
+        for (_, chunk) in transparent {
+            let indices = chunk.num_indices..chunk.num_indices + chunk.num_transparent_indices;
+            render_pass.set_vertex_buffer(0, chunk.vertex_buffer.slice());
+            render_pass.set_vertex_buffer(1, chunk.instance_buffer.slice(..));
+            render_pass.set_index_buffer(chunk.index_buffer.slice(), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(indices, 0, 0..1);
+        }
+        for mesh in &self.instanced_meshes {
+            mesh.draw(render_pass, true);
+        }
+        (visible.len(), self.chunks.len() - visible.len())
+    }
+
+    // Draws the held block over the world, squeezed into the front of the
+    // depth range so it only has to depth test against itself.
+    fn draw_held_item<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        let Some(mesh) = &self.held_mesh else {
+            return;
+        };
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        render_pass.set_viewport(0.0, 0.0, width, height, 0.0, held::DEPTH_RANGE);
+        render_pass.set_bind_group(0, &self.atlas.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.light_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice());
+        render_pass.set_vertex_buffer(1, mesh.instance_buffer.slice(..));
+        render_pass.set_index_buffer(mesh.index_buffer.slice(), wgpu::IndexFormat::Uint32);
+        if mesh.num_indices > 0 {
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+        if mesh.num_transparent_indices > 0 {
+            let indices = mesh.num_indices..mesh.num_indices + mesh.num_transparent_indices;
+            render_pass.set_pipeline(&self.transparent_pipeline);
+            render_pass.draw_indexed(indices, 0, 0..1);
+        }
+        render_pass.set_viewport(0.0, 0.0, width, height, 0.0, 1.0);
+    }
+
+    // Renders the scene from `camera` into an offscreen texture of the given
+    // size and returns its RGBA8 pixels. The swapchain is left untouched, so
+    // this can be used for thumbnails or minimap views at any time.
+    pub fn render_to_texture(&self, camera: &Camera, width: u32, height: u32) -> Result<Vec<u8>> {
+        self.start_render_to_texture(camera, width, height)?
+            .wait(&self.device)
+    }
+
+    // Like `render_to_texture`, without waiting for the GPU. The pixels
+    // arrive once the device has been polled enough, which `update` does
+    // every frame.
+    pub fn start_render_to_texture(
+        &self,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+    ) -> Result<Readback> {
+        self.render_offscreen(camera, width, height, false)
+    }
+
+    // Debug lines are only drawn if asked for, and must have been prepared
+    // for the same size.
+    fn render_offscreen(
+        &self,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+        debug_lines: bool,
+    ) -> Result<Readback> {
+        ensure!(width > 0 && height > 0, "texture size must be non-zero");
+
+        let mut projection = self.projection.clone();
+        projection.resize(width, height);
+        let render_camera = self.render_camera(camera);
+        let mut camera_uniform = CameraUniform::new();
+        camera_uniform.update_view_proj(&render_camera, &projection);
+        let camera_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Offscreen Camera Buffer"),
+                contents: bytemuck::cast_slice(&[camera_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let camera_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+            label: Some("offscreen_camera_bind_group"),
+        });
+
+        // The pipeline targets the surface format, so render in that format
+        // and convert to RGBA on readback.
+        let config = wgpu::SurfaceConfiguration {
+            width,
+            height,
+            ..self.config.clone()
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_texture = Texture::create_depth_texture(
+            &self.device,
+            &config,
+            self.sample_count,
+            "offscreen_depth_texture",
+        );
+        let msaa_texture = (self.sample_count > 1).then(|| {
+            Texture::create_multisampled_texture(
+                &self.device,
+                &config,
+                self.sample_count,
+                "offscreen_msaa_texture",
+            )
+        });
 
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: msaa_texture.as_ref().map_or(&view, |msaa| &msaa.view),
+                    resolve_target: msaa_texture.as_ref().map(|_| &view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(self.clear_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            self.draw_scene(
+                &mut render_pass,
+                &camera_bind_group,
+                &render_camera.frustum(&projection),
+                render_camera.position,
+            );
+            if debug_lines {
+                self.debug_renderer
+                    .draw(&mut render_pass, &camera_bind_group);
+            }
+        }
         self.queue.submit(iter::once(encoder.finish()));
-        output.present();
 
-        Ok(())
+        Ok(Readback::new(
+            &self.device,
+            &self.queue,
+            &texture,
+            width,
+            height,
+        ))
+    }
+
+    // Saves the next presented frame, held block, debug lines and text
+    // included, as a timestamped PNG in `dir`. It's copied back without
+    // waiting for the GPU and written by `poll` once it arrives. Returns the
+    // path it will be written to.
+    pub fn request_screenshot<P: AsRef<Path>>(&mut self, dir: P) -> PathBuf {
+        let path = screenshot::screenshot_path(dir);
+        self.screenshot_requested = Some(path.clone());
+        path
+    }
+
+    // Starts copying the frame just submitted to `frame`, the surface's
+    // texture, before it's presented. With MSAA the frame is resolved a
+    // second time, into a texture that can be copied from, since the surface
+    // might not be. Without it, the surface itself has to be.
+    fn capture_frame(&self, frame: &wgpu::Texture) -> Result<Readback> {
+        let (width, height) = (self.config.width, self.config.height);
+        let Some(msaa) = &self.msaa_texture else {
+            ensure!(
+                self.config.usage.contains(wgpu::TextureUsages::COPY_SRC),
+                "the surface can't be read back, turn on MSAA to take screenshots"
+            );
+            return Ok(Readback::new(
+                &self.device,
+                &self.queue,
+                frame,
+                width,
+                height,
+            ));
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        // Nothing is drawn, the pass only resolves.
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Capture Resolve Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &msaa.view,
+                resolve_target: Some(&view),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        self.queue.submit(iter::once(encoder.finish()));
+        Ok(Readback::new(
+            &self.device,
+            &self.queue,
+            &texture,
+            width,
+            height,
+        ))
+    }
+
+    // Drives finished GPU work's callbacks, like buffer maps for readbacks,
+    // without blocking, and saves screenshots whose pixels arrived. Called at
+    // the start of every `update`, but headless users that don't update
+    // every frame need to call it themselves. Returns whether all submitted
+    // work is done.
+    pub fn poll(&mut self) -> bool {
+        let done = self.device.poll(wgpu::Maintain::Poll);
+        self.screenshots.retain(|pending| {
+            let Some(pixels) = pending.readback.try_take() else {
+                return true;
+            };
+            let saved = pixels.and_then(|pixels| {
+                screenshot::save_png(&pending.path, pending.width, pending.height, &pixels)
+            });
+            match saved {
+                Ok(()) => log::info!("saved screenshot to {}", pending.path.display()),
+                Err(err) => log::error!("couldn't save screenshot: {}", err),
+            }
+            false
+        });
+        done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::{chunk::Chunk, inventory::Inventory, voxel::Voxel};
+    use wgpu_core::device::DeviceError;
+
+    // How wgpu reports an error from wgpu-core on an uncaptured error.
+    fn validation_error(cause: impl std::fmt::Display) -> wgpu::Error {
+        wgpu::Error::Validation {
+            source: Box::new(std::fmt::Error),
+            description: format!("Validation Error\n\nCaused by:\n    {}\n", cause),
+        }
+    }
+
+    // A renderer on whatever adapter is around, or `None` if there isn't
+    // one, in which case the test has nothing to check.
+    fn headless(width: u32, height: u32) -> Option<Renderer> {
+        headless_with(RendererConfig::default(), width, height)
+    }
+
+    fn headless_with(config: RendererConfig, width: u32, height: u32) -> Option<Renderer> {
+        match pollster::block_on(Renderer::headless(config, width, height)) {
+            Ok(renderer) => Some(renderer),
+            Err(err) => {
+                eprintln!("skipping, no GPU: {}", err);
+                None
+            }
+        }
+    }
+
+    fn solid_chunk(position: cgmath::Vector3<i32>) -> Chunk {
+        let mut chunk = Chunk::new(position);
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    chunk.set(x, y, z, Voxel::new(VoxelType::Stone));
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn render_to_texture_draws_the_scene() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        let camera = Camera::new((8.0, 40.0, 8.0), cgmath::Deg(0.0), cgmath::Deg(-89.0));
+        let empty = renderer.render_to_texture(&camera, 64, 48).unwrap();
+        assert_eq!(empty.len(), 64 * 48 * 4);
+        assert!(empty.chunks(4).all(|pixel| pixel == &empty[..4]));
+
+        let position = cgmath::Vector3::new(0, 0, 0);
+        let mesh = solid_chunk(position).build_mesh([None; 6]);
+        renderer.upload_chunk_mesh(position, &mesh);
+        let pixels = renderer.render_to_texture(&camera, 64, 48).unwrap();
+        assert_eq!(pixels.len(), 64 * 48 * 4);
+        assert!(pixels.chunks(4).any(|pixel| pixel != &empty[..4]));
+    }
+
+    // Runs `update_chunks` until `count` chunks are on the GPU, since
+    // meshing happens off the main thread.
+    fn mesh_world(renderer: &mut Renderer, count: usize) {
+        for _ in 0..1000 {
+            renderer.update_chunks();
+            if renderer.chunks.len() >= count {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(renderer.chunks.len(), count);
+    }
+
+    #[test]
+    fn stats_match_what_was_uploaded() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        // Two lone voxels of six faces each, the glass one's indexed with the
+        // transparent geometry.
+        let mut chunk = Chunk::new(cgmath::Vector3::new(0, 0, 0));
+        chunk.set(3, 4, 5, Voxel::new(VoxelType::Stone));
+        chunk.set(9, 9, 9, Voxel::new(VoxelType::Glass));
+        let mesh = chunk.build_mesh([None; 6]);
+        assert_eq!(mesh.vertex_count(), 2 * 24);
+        assert_eq!(mesh.triangle_count(), 2 * 12);
+        assert_eq!(mesh.transparent_indices.len(), 36);
+        renderer.world.add_chunk(chunk);
+        mesh_world(&mut renderer, 1);
+
+        let stats = renderer.stats();
+        assert_eq!((stats.chunks, stats.vertices, stats.triangles), (1, 48, 24));
+        let gpu = &renderer.chunks[&cgmath::Vector3::new(0, 0, 0)];
+        assert_eq!(
+            gpu.vertex_buffer.len,
+            (stats.vertices * std::mem::size_of::<Vertex>()) as u64
+        );
+        assert_eq!(
+            gpu.index_buffer.len,
+            (stats.triangles * 3 * std::mem::size_of::<u32>()) as u64
+        );
+        assert_eq!((gpu.num_indices, gpu.num_transparent_indices), (36, 36));
+    }
+
+    #[test]
+    fn teleporting_far_away_recenters_the_camera() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.world.set_streaming_enabled(false);
+        let far = cgmath::Point3::new(3_000_000.5, 70.0, -1_500_000.5);
+        renderer.world.teleport_player(far);
+        renderer.update(instant::Duration::from_millis(16));
+
+        assert_eq!(renderer.origin, renderer.world.origin());
+        let eye = renderer.render_camera(&renderer.camera).position;
+        assert!(
+            eye.to_vec().magnitude() < 2.0 * CHUNK_SIZE as f32,
+            "{:?}",
+            eye
+        );
+    }
+
+    #[test]
+    fn moving_with_streaming_off_loads_nothing() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.world.set_streaming_enabled(false);
+        renderer.world.player.flying = true;
+        let loaded = [cgmath::Vector3::new(0, 0, 0), cgmath::Vector3::new(1, 0, 0)];
+        for position in loaded {
+            renderer.world.add_chunk(Chunk::new(position));
+        }
+        for x in 0..10 {
+            let eye = cgmath::Point3::new(x as f32 * 100.0, 8.0, 0.0);
+            renderer.world.teleport_player(eye);
+            renderer.update(instant::Duration::from_millis(16));
+        }
+        assert_eq!(renderer.world.streamer.pending_count(), 0);
+        let mut positions: Vec<_> = renderer.world.chunks().map(|c| c.position()).collect();
+        positions.sort_by_key(|p| p.x);
+        assert_eq!(positions, loaded);
+
+        // Turning it back on streams around wherever the camera ended up.
+        renderer.world.set_streaming_enabled(true);
+        renderer.update(instant::Duration::from_millis(16));
+        let requested = renderer.world.streamer.pending_count() + renderer.world.chunks().count();
+        assert!(requested > loaded.len());
+    }
+
+    #[test]
+    fn camera_and_seed_survive_a_save() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        renderer.world.reset(99);
+        renderer.world.generator.frequency = 1.0 / 64.0;
+        renderer.world.generator.amplitude = 20.0;
+        renderer.world.generator.caves.as_mut().unwrap().threshold = 0.8;
+        renderer
+            .world
+            .teleport_player(cgmath::Point3::new(120.5, 40.0, -75.25));
+        renderer.world.player.yaw = cgmath::Rad(2.0);
+        renderer.world.player.pitch = cgmath::Rad(-0.5);
+        let eye = renderer.world.player.eye();
+        let path = std::env::temp_dir().join(format!("voxl-{}-camera.sav", std::process::id()));
+        renderer.save_world(&path).unwrap();
+
+        renderer.world.reset(1);
+        renderer.world.spawn_player();
+        let loaded = renderer.load_world(&path);
+        std::fs::remove_file(&path).ok();
+        loaded.unwrap();
+        let generator = &renderer.world.generator;
+        assert_eq!(generator.seed, 99);
+        assert_eq!(generator.frequency, 1.0 / 64.0);
+        assert_eq!(generator.amplitude, 20.0);
+        assert_eq!(generator.caves.as_ref().unwrap().threshold, 0.8);
+        assert_eq!(renderer.camera.position, eye);
+        assert_eq!(renderer.camera.yaw(), cgmath::Rad(2.0));
+        assert_eq!(renderer.camera.pitch(), cgmath::Rad(-0.5));
+    }
+
+    #[test]
+    fn thousand_instances_fill_one_buffer() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        let mesh = renderer.upload_mesh(held::held_mesh(VoxelType::Stone));
+        let instances: Vec<_> = (0..1000)
+            .map(|i| Instance {
+                position: cgmath::Vector3::new(i as f32, 0.0, 0.0),
+                rotation: cgmath::Quaternion::one(),
+                scale: 1.0,
+            })
+            .collect();
+        renderer.draw_instanced(mesh, &instances);
+        assert_eq!(renderer.queued_instances(mesh), 1000);
+
+        let (device, queue) = (&renderer.device, &renderer.queue);
+        let instanced = &mut renderer.instanced_meshes[mesh.0];
+        instanced.prepare(device, queue);
+        assert_eq!(std::mem::size_of::<InstanceRaw>(), 64);
+        assert_eq!(instanced.instance_buffer.len, 64_000);
+        assert_eq!(instanced.instance_buffer.buffer.size(), 64_000);
+        assert_eq!(instanced.num_instances, 1000);
+        assert!(instanced.queued.is_empty());
+    }
+
+    #[test]
+    fn box_tool_fills_between_two_clicks() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        // A stone floor with its top at y = -1 and air above.
+        renderer
+            .world
+            .add_chunk(solid_chunk(cgmath::Vector3::new(0, -1, 0)));
+        renderer
+            .world
+            .add_chunk(Chunk::new(cgmath::Vector3::new(0, 0, 0)));
+        renderer
+            .world
+            .teleport_player(cgmath::Point3::new(8.0, 50.0, 8.0));
+        renderer.world.inventory = Inventory::new();
+        renderer.world.inventory.add(VoxelType::Dirt, 1);
+        let frame = instant::Duration::from_millis(16);
+        let place_looking_down_at = |renderer: &mut Renderer, x: f32, z: f32| {
+            renderer.camera = Camera::new((x, 3.0, z), cgmath::Deg(0.0), cgmath::Deg(-89.0));
+            renderer.place_pressed = true;
+            renderer.update_interaction(frame);
+        };
+
+        assert!(renderer.handle_action(Action::CycleTool, true));
+        assert_eq!(renderer.world.tool_mode, ToolMode::BoxFill);
+        place_looking_down_at(&mut renderer, 2.5, 2.5);
+        assert_eq!(
+            renderer.world.box_selection.corner(),
+            Some(cgmath::Vector3::new(2, 0, 2))
+        );
+        assert_eq!(
+            renderer.targeted_voxel,
+            Some(cgmath::Vector3::new(2, -1, 2))
+        );
+        place_looking_down_at(&mut renderer, 5.5, 4.5);
+        assert_eq!(renderer.world.box_selection.corner(), None);
+        for x in 0..8 {
+            for z in 0..8 {
+                let inside = (2..=5).contains(&x) && (2..=4).contains(&z);
+                let pos = cgmath::Vector3::new(x, 0, z);
+                assert_eq!(renderer.world.is_solid(pos), inside, "{:?}", pos);
+            }
+        }
+
+        // Back to single voxels, a click places just one.
+        renderer.handle_action(Action::CycleTool, true);
+        assert_eq!(renderer.world.tool_mode, ToolMode::Single);
+        place_looking_down_at(&mut renderer, 0.5, 0.5);
+        assert!(renderer.world.is_solid(cgmath::Vector3::new(0, 0, 0)));
+        assert!(!renderer.world.is_solid(cgmath::Vector3::new(1, 0, 0)));
+        assert_eq!(renderer.world.box_selection.corner(), None);
+    }
+
+    #[test]
+    fn resetting_the_world_drops_its_gpu_chunks() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        let position = cgmath::Vector3::new(0, 0, 0);
+        renderer.world.add_chunk(solid_chunk(position));
+        mesh_world(&mut renderer, 1);
+
+        renderer.world.reset(7);
+        renderer.update_chunks();
+        assert!(renderer.chunks.is_empty());
+        assert_eq!(renderer.stats().chunks, 0);
+    }
+
+    #[test]
+    fn configured_alpha_mode_reaches_the_surface_config() {
+        let config = RendererConfig {
+            alpha_mode: wgpu::CompositeAlphaMode::PreMultiplied,
+            ..RendererConfig::default()
+        };
+        let Some(renderer) = headless_with(config, 8, 8) else {
+            return;
+        };
+        assert_eq!(
+            renderer.config.alpha_mode,
+            wgpu::CompositeAlphaMode::PreMultiplied
+        );
+        assert_eq!(
+            renderer.alpha_mode(),
+            wgpu::CompositeAlphaMode::PreMultiplied
+        );
+        // Nothing drawn, so the canvas stays see-through.
+        let camera = renderer.camera.clone();
+        let pixels = renderer.render_to_texture(&camera, 8, 8).unwrap();
+        assert!(pixels.chunks(4).all(|pixel| pixel[3] == 0));
+    }
+
+    #[test]
+    fn opening_the_inventory_releases_the_cursor() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        assert!(renderer.wants_cursor_capture());
+        assert!(renderer.crosshair_visible());
+
+        renderer.handle_action(Action::ToggleInventory, true);
+        assert!(renderer.ui_open());
+        assert!(!renderer.wants_cursor_capture());
+        assert!(!renderer.crosshair_visible());
+        // Holding the key doesn't toggle it back.
+        renderer.handle_action(Action::ToggleInventory, false);
+        assert!(renderer.ui_open());
+
+        renderer.handle_action(Action::ToggleInventory, true);
+        assert!(!renderer.ui_open());
+        assert!(renderer.wants_cursor_capture());
+        assert!(renderer.crosshair_visible());
+    }
+
+    #[test]
+    fn shading_switches_without_remeshing() {
+        let Some(mut renderer) = headless(64, 48) else {
+            return;
+        };
+        let position = cgmath::Vector3::new(0, 0, 0);
+        let mesh = solid_chunk(position).build_mesh([None; 6]);
+        renderer.upload_chunk_mesh(position, &mesh);
+        let camera = Camera::new((8.0, 40.0, 8.0), cgmath::Deg(0.0), cgmath::Deg(-89.0));
+        let flat = renderer.render_to_texture(&camera, 64, 48).unwrap();
+
+        renderer.set_shading(Shading::Smooth);
+        assert_eq!(renderer.shading(), Shading::Smooth);
+        assert_eq!(renderer.world.dirty_chunks().count(), 0);
+        assert_eq!(renderer.chunks[&position].vertex_count, mesh.vertex_count());
+        let smooth = renderer.render_to_texture(&camera, 64, 48).unwrap();
+        assert_ne!(flat, smooth);
+
+        renderer.set_shading(Shading::Flat);
+        assert_eq!(renderer.render_to_texture(&camera, 64, 48).unwrap(), flat);
+    }
+
+    #[test]
+    fn resize_recreates_every_target() {
+        let config = RendererConfig {
+            sample_count: 4,
+            ..RendererConfig::default()
+        };
+        let Some(mut renderer) = headless_with(config, 64, 48) else {
+            return;
+        };
+        renderer.resize(winit::dpi::PhysicalSize::new(100, 30));
+
+        let size = |texture: &Texture| {
+            let size = texture.texture.size();
+            (size.width, size.height)
+        };
+        assert_eq!((renderer.config.width, renderer.config.height), (100, 30));
+        assert_eq!(size(&renderer.depth_texture), (100, 30));
+        let msaa = renderer.msaa_texture.as_ref().unwrap();
+        assert_eq!(size(msaa), (100, 30));
+        assert_eq!(msaa.texture.sample_count(), 4);
+        assert!((renderer.projection.aspect() - 100.0 / 30.0).abs() < 1e-6);
+
+        // A minimized window reports a zero size, which keeps the old
+        // targets.
+        renderer.resize(winit::dpi::PhysicalSize::new(0, 0));
+        assert_eq!(size(&renderer.depth_texture), (100, 30));
+    }
+
+    #[test]
+    fn screenshots_resolve_the_multisampled_frame() {
+        let config = RendererConfig {
+            sample_count: 4,
+            ..RendererConfig::default()
+        };
+        let Some(renderer) = headless_with(config, 8, 4) else {
+            return;
+        };
+        let msaa = renderer.msaa_texture.as_ref().unwrap();
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &msaa.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        renderer.queue.submit(iter::once(encoder.finish()));
+
+        // With MSAA the frame's texture isn't read from, which the headless
+        // renderer doesn't have anyway.
+        let pixels = renderer
+            .capture_frame(&msaa.texture)
+            .unwrap()
+            .wait(&renderer.device)
+            .unwrap();
+        assert_eq!(pixels.len(), 8 * 4 * 4);
+        assert!(pixels.chunks(4).all(|pixel| pixel == [255, 0, 0, 255]));
+    }
+
+    fn stacked_faces(count: usize) -> ChunkMesh {
+        let mut mesh = ChunkMesh::new();
+        for i in 0..count {
+            mesh.push_face(
+                Face::PosY,
+                i as f32,
+                [0.0, 0.0],
+                [1.0, 1.0],
+                VoxelType::Stone,
+            );
+        }
+        mesh
+    }
+
+    #[test]
+    fn chunk_buffers_are_reused_until_outgrown() {
+        let Some(renderer) = headless(64, 48) else {
+            return;
+        };
+        let (device, queue) = (&renderer.device, &renderer.queue);
+        let position = cgmath::Vector3::new(0, 0, 0);
+        let mut chunk = GpuChunk::new(device, position, position, &stacked_faces(4));
+        let capacity = chunk.capacity();
+
+        chunk.upload(device, queue, &stacked_faces(2));
+        assert_eq!(chunk.capacity(), capacity);
+        assert_eq!(chunk.num_indices, 12);
+        assert_eq!(
+            chunk.vertex_buffer.len,
+            (8 * std::mem::size_of::<Vertex>()) as u64
+        );
+
+        chunk.upload(device, queue, &stacked_faces(8));
+        assert!(chunk.capacity() > capacity);
+        assert_eq!(chunk.num_indices, 48);
+
+        let mut buffer = GrowableBuffer::new(device, "test", wgpu::BufferUsages::VERTEX, &[0; 16]);
+        assert!(!buffer.write(device, queue, &[1; 8]));
+        assert_eq!((buffer.capacity, buffer.len), (16, 8));
+        assert!(buffer.write(device, queue, &[2; 32]));
+        assert_eq!((buffer.capacity, buffer.len), (32, 32));
+    }
+
+    // How many pixels down the middle column a red line across the view at
+    // `depth` covers.
+    fn line_thickness(renderer: &mut Renderer, width: LineWidth, depth: f32) -> usize {
+        let camera = Camera::new((0.0, 0.0, 0.0), cgmath::Deg(0.0), cgmath::Deg(0.0));
+        renderer.set_outline_width(width);
+        renderer.draw_debug_line(
+            (-100.0, 0.0, -depth).into(),
+            (100.0, 0.0, -depth).into(),
+            [1.0, 0.0, 0.0],
+        );
+        renderer.debug_renderer.prepare(
+            &renderer.device,
+            &renderer.queue,
+            [64, 64],
+            renderer.projection.focal_length(),
+        );
+        let pixels = renderer
+            .render_offscreen(&camera, 64, 64, true)
+            .unwrap()
+            .wait(&renderer.device)
+            .unwrap();
+        (0..64)
+            .map(|y| &pixels[(y * 64 + 32) * 4..][..4])
+            .filter(|pixel| pixel[0] > 128 && pixel[1] < 128)
+            .count()
+    }
+
+    #[test]
+    fn pixel_outlines_keep_their_width_with_distance() {
+        let Some(mut renderer) = headless(64, 64) else {
+            return;
+        };
+        let near = line_thickness(&mut renderer, LineWidth::Pixels(4.0), 5.0);
+        let far = line_thickness(&mut renderer, LineWidth::Pixels(4.0), 40.0);
+        assert!((3..=5).contains(&near), "{} pixels", near);
+        assert_eq!(near, far);
+
+        // Unlike world-space ones, which thin out.
+        let near = line_thickness(&mut renderer, LineWidth::World(0.5), 5.0);
+        let far = line_thickness(&mut renderer, LineWidth::World(0.5), 20.0);
+        assert!(far > 0 && near > far * 2, "{} vs {} pixels", near, far);
+    }
+
+    #[test]
+    fn device_lost_message_matches_wgpu() {
+        assert!(DeviceError::Lost.to_string().contains(DEVICE_LOST_MESSAGE));
+        assert!(!DeviceError::OutOfMemory
+            .to_string()
+            .contains(DEVICE_LOST_MESSAGE));
+    }
+
+    #[test]
+    fn device_lost_error_flags_loss() {
+        let lost = AtomicBool::new(false);
+        Renderer::handle_device_error(&lost, validation_error(DeviceError::Lost));
+        assert!(lost.load(Ordering::Relaxed));
+        // Every later call on the lost device fails the same way.
+        Renderer::handle_device_error(&lost, validation_error(DeviceError::Lost));
+        assert!(lost.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    #[should_panic(expected = "wgpu error")]
+    fn other_errors_still_panic() {
+        let lost = AtomicBool::new(false);
+        Renderer::handle_device_error(&lost, validation_error(DeviceError::OutOfMemory));
     }
 }
@@ -0,0 +1,63 @@
+use anyhow::{ensure, Result};
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Writes tightly packed RGBA8 pixels, like those from a `Readback`, to a
+// PNG file.
+pub fn save_png<P: AsRef<Path>>(path: P, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    ensure!(
+        pixels.len() == 4 * width as usize * height as usize,
+        "expected {}x{} RGBA pixels, got {} bytes",
+        width,
+        height,
+        pixels.len()
+    );
+    image::save_buffer_with_format(
+        path,
+        pixels,
+        width,
+        height,
+        image::ColorType::Rgba8,
+        image::ImageFormat::Png,
+    )?;
+    Ok(())
+}
+
+// A path in `dir` named after the current UTC time down to the millisecond,
+// like `screenshot-2023-03-14_15-09-26.535.png`, so screenshots sort by
+// when they were taken.
+pub fn screenshot_path<P: AsRef<Path>>(dir: P) -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let name = format!(
+        "screenshot-{:04}-{:02}-{:02}_{:02}-{:02}-{:02}.{:03}.png",
+        year,
+        month,
+        day,
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    );
+    dir.as_ref().join(name)
+}
+
+// The Gregorian date `days` after 1970-01-01, from Howard Hinnant's
+// `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
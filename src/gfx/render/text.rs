@@ -0,0 +1,468 @@
+use crate::gfx::render::texture::Texture;
+use wgpu::util::DeviceExt;
+
+// Printable ASCII in 8x8 cells, one byte per row with the lowest bit the
+// leftmost pixel. Derived from the public domain font8x8 glyphs.
+const BITMAP_FONT: &[u8; 96 * 8] = include_bytes!("../../../res/fonts/ascii_8x8.bin");
+const BITMAP_CELL: u32 = 8;
+const FIRST_GLYPH: u8 = b' ';
+const GLYPH_COUNT: usize = 96;
+const ATLAS_COLUMNS: u32 = 16;
+const INITIAL_CAPACITY: usize = 6 * 256;
+
+// Coverage of every printable ASCII glyph laid out on a grid of equally
+// sized cells.
+#[derive(Clone)]
+pub struct GlyphAtlas {
+    // Cell size in pixels.
+    cell: [u32; 2],
+    // Horizontal advance of each glyph in pixels.
+    advances: [f32; GLYPH_COUNT],
+    pixels: Vec<u8>,
+}
+
+impl GlyphAtlas {
+    // The embedded bitmap font, which needs no font crate or font file.
+    pub fn bitmap() -> Self {
+        let mut atlas = Self::empty([BITMAP_CELL; 2], BITMAP_CELL as f32);
+        for (i, rows) in BITMAP_FONT.chunks_exact(8).enumerate() {
+            for (y, row) in rows.iter().enumerate() {
+                for x in 0..8 {
+                    if row >> x & 1 != 0 {
+                        atlas.set(i, x, y as u32, u8::MAX);
+                    }
+                }
+            }
+        }
+        atlas
+    }
+
+    // Rasterizes a TrueType/OpenType font at `size` pixels, giving smoother,
+    // anti-aliased text than the bitmap font.
+    #[cfg(feature = "ttf-text")]
+    pub fn from_font(bytes: &[u8], size: f32) -> anyhow::Result<Self> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|err| anyhow::anyhow!("failed to load font: {}", err))?;
+        let line = font
+            .horizontal_line_metrics(size)
+            .ok_or_else(|| anyhow::anyhow!("font has no horizontal metrics"))?;
+        let glyphs = (0..GLYPH_COUNT)
+            .map(|i| font.rasterize((FIRST_GLYPH + i as u8) as char, size))
+            .collect::<Vec<_>>();
+        let width = glyphs
+            .iter()
+            .map(|(metrics, _)| (metrics.xmin.max(0) as usize + metrics.width) as u32)
+            .max()
+            .unwrap_or(1);
+        let height = line.new_line_size.ceil() as u32;
+
+        let mut atlas = Self::empty([width, height], 0.0);
+        for (i, (metrics, coverage)) in glyphs.iter().enumerate() {
+            atlas.advances[i] = metrics.advance_width;
+            // Glyph bitmaps are positioned relative to the baseline.
+            let top = line.ascent.ceil() as i32 - metrics.ymin - metrics.height as i32;
+            for y in 0..metrics.height {
+                for x in 0..metrics.width {
+                    let (ax, ay) = (metrics.xmin.max(0) + x as i32, top + y as i32);
+                    if (0..height as i32).contains(&ay) {
+                        atlas.set(i, ax as u32, ay as u32, coverage[y * metrics.width + x]);
+                    }
+                }
+            }
+        }
+        Ok(atlas)
+    }
+
+    // Spaces are never drawn, so their cell is filled in solid for drawing
+    // rectangles with.
+    fn empty(cell: [u32; 2], advance: f32) -> Self {
+        let [width, height] = Self::size_for(cell);
+        let mut atlas = Self {
+            cell,
+            advances: [advance; GLYPH_COUNT],
+            pixels: vec![0; (width * height) as usize],
+        };
+        let space = Self::glyph(' ');
+        for y in 0..cell[1] {
+            for x in 0..cell[0] {
+                atlas.set(space, x, y, u8::MAX);
+            }
+        }
+        atlas
+    }
+
+    // Texture coordinates somewhere in the solid space cell.
+    fn solid_tex_coords(&self) -> [f32; 2] {
+        let [[u0, v0], [u1, v1]] = self.tex_rect(Self::glyph(' '));
+        [(u0 + u1) / 2.0, (v0 + v1) / 2.0]
+    }
+
+    fn size_for(cell: [u32; 2]) -> [u32; 2] {
+        let rows = (GLYPH_COUNT as u32).div_ceil(ATLAS_COLUMNS);
+        [cell[0] * ATLAS_COLUMNS, cell[1] * rows]
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        Self::size_for(self.cell)
+    }
+
+    fn set(&mut self, glyph: usize, x: u32, y: u32, value: u8) {
+        let [width, _] = self.size();
+        let (gx, gy) = (glyph as u32 % ATLAS_COLUMNS, glyph as u32 / ATLAS_COLUMNS);
+        let index = (gy * self.cell[1] + y) * width + gx * self.cell[0] + x;
+        self.pixels[index as usize] = value;
+    }
+
+    // Index of the glyph drawn for `c`, with unsupported characters shown
+    // as '?'.
+    fn glyph(c: char) -> usize {
+        match c {
+            ' '..='~' => (c as u8 - FIRST_GLYPH) as usize,
+            _ => (b'?' - FIRST_GLYPH) as usize,
+        }
+    }
+
+    fn tex_rect(&self, glyph: usize) -> [[f32; 2]; 2] {
+        let [width, height] = self.size().map(|v| v as f32);
+        let (gx, gy) = (glyph as u32 % ATLAS_COLUMNS, glyph as u32 / ATLAS_COLUMNS);
+        let min = [
+            (gx * self.cell[0]) as f32 / width,
+            (gy * self.cell[1]) as f32 / height,
+        ];
+        let max = [
+            min[0] + self.cell[0] as f32 / width,
+            min[1] + self.cell[1] as f32 / height,
+        ];
+        [min, max]
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextVertex {
+    // In pixels from the top left of the viewport.
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl TextVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+        const ATTRIBUTE_LAYOUT: &[wgpu::VertexAttribute] =
+            &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<TextVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: ATTRIBUTE_LAYOUT,
+        }
+    }
+}
+
+// Screen-space text for overlays and tooltips. Like the debug renderer it's
+// immediate mode: text is queued with `draw` every frame and cleared once
+// it's been uploaded.
+pub struct TextRenderer {
+    atlas: GlyphAtlas,
+    pipeline: wgpu::RenderPipeline,
+    atlas_bind_group: wgpu::BindGroup,
+    viewport_buffer: wgpu::Buffer,
+    viewport_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    capacity: usize,
+    vertices: Vec<TextVertex>,
+    num_vertices: u32,
+}
+
+impl TextRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        atlas: GlyphAtlas,
+    ) -> Self {
+        let [width, height] = atlas.size();
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &atlas.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(width),
+                rows_per_image: std::num::NonZeroU32::new(height),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Nearest keeps the bitmap font crisp at integer scales; rasterized
+        // fonts already carry their anti-aliasing in the coverage values.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let atlas_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("glyph_atlas_bind_group_layout"),
+            });
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("glyph_atlas_bind_group"),
+        });
+
+        let viewport_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Viewport Buffer"),
+            contents: bytemuck::cast_slice(&[0.0f32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let viewport_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("text_viewport_bind_group_layout"),
+            });
+        let viewport_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &viewport_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: viewport_buffer.as_entire_binding(),
+            }],
+            label: Some("text_viewport_bind_group"),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../../res/shaders/text.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&atlas_bind_group_layout, &viewport_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[TextVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Text is drawn on top of everything, but the pass it's drawn in
+            // has a depth attachment so the pipeline has to declare one.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        });
+
+        Self {
+            atlas,
+            pipeline,
+            atlas_bind_group,
+            viewport_buffer,
+            viewport_bind_group,
+            vertex_buffer: Self::create_vertex_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            vertices: vec![],
+            num_vertices: 0,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<TextVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    // Height of a line of text in pixels at the given scale.
+    pub fn line_height(&self, scale: f32) -> f32 {
+        self.atlas.cell[1] as f32 * scale
+    }
+
+    // Queues `text` with its top left corner at `pos` (in pixels). `scale`
+    // multiplies the atlas' glyph size and '\n' starts a new line.
+    pub fn draw(&mut self, text: &str, pos: [f32; 2], scale: f32, color: [f32; 4]) {
+        let [cell_w, cell_h] = self.atlas.cell.map(|v| v as f32 * scale);
+        let [mut x, mut y] = pos;
+        for c in text.chars() {
+            if c == '\n' {
+                x = pos[0];
+                y += cell_h;
+                continue;
+            }
+            let glyph = GlyphAtlas::glyph(c);
+            if c != ' ' {
+                let [[u0, v0], [u1, v1]] = self.atlas.tex_rect(glyph);
+                let corners = [
+                    ([x, y], [u0, v0]),
+                    ([x, y + cell_h], [u0, v1]),
+                    ([x + cell_w, y + cell_h], [u1, v1]),
+                    ([x + cell_w, y], [u1, v0]),
+                ];
+                self.vertices.extend([0, 1, 2, 0, 2, 3].map(|i| TextVertex {
+                    position: corners[i].0,
+                    tex_coords: corners[i].1,
+                    color,
+                }));
+            }
+            x += self.atlas.advances[glyph] * scale;
+        }
+    }
+
+    // Queues a filled rectangle with its top left corner at `pos`, both in
+    // pixels.
+    pub fn draw_rect(&mut self, pos: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+        let [x, y] = pos;
+        let [w, h] = size;
+        let tex_coords = self.atlas.solid_tex_coords();
+        let corners = [[x, y], [x, y + h], [x + w, y + h], [x + w, y]];
+        self.vertices.extend([0, 1, 2, 0, 2, 3].map(|i| TextVertex {
+            position: corners[i],
+            tex_coords,
+            color,
+        }));
+    }
+
+    // Number of glyph and rectangle quads queued since the last `prepare`.
+    pub fn queued_glyphs(&self) -> usize {
+        self.vertices.len() / 6
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, viewport: [u32; 2]) {
+        let viewport = [viewport[0] as f32, viewport[1] as f32, 0.0, 0.0];
+        queue.write_buffer(&self.viewport_buffer, 0, bytemuck::cast_slice(&viewport));
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        self.num_vertices = self.vertices.len() as u32;
+        self.vertices.clear();
+    }
+
+    pub fn draw_queued<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.num_vertices == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.atlas_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.viewport_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.num_vertices, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gfx::render::renderer::Renderer;
+
+    #[test]
+    fn one_quad_per_visible_glyph() {
+        let Some((device, queue)) = Renderer::test_device() else {
+            return;
+        };
+        let mut text = TextRenderer::new(
+            &device,
+            &queue,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            1,
+            GlyphAtlas::bitmap(),
+        );
+        // Spaces and line breaks only move the pen.
+        text.draw("fps: 60\nok", [10.0, 20.0], 2.0, [1.0; 4]);
+        assert_eq!(text.queued_glyphs(), 8);
+        text.draw_rect([0.0, 0.0], [4.0, 4.0], [1.0; 4]);
+        assert_eq!(text.queued_glyphs(), 9);
+
+        // "ok" starts back at the left edge, a line lower.
+        let o = &text.vertices[6 * 6];
+        assert_eq!(o.position, [10.0, 20.0 + text.line_height(2.0)]);
+
+        text.prepare(&device, &queue, [64, 64]);
+        assert_eq!(text.queued_glyphs(), 0);
+        assert_eq!(text.num_vertices, 9 * 6);
+    }
+}
@@ -27,8 +27,9 @@ impl Texture {
         let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
+        // Layers are square and stacked vertically.
         let texture_size = dimensions.0;
-        let number_of_layers = 3;
+        let number_of_layers = dimensions.1 / texture_size;
         assert_eq!(texture_size * number_of_layers, dimensions.1);
         let size = wgpu::Extent3d {
             width: texture_size,
@@ -67,9 +68,11 @@ impl Texture {
             ..Default::default()
         });
 
+        // Merged voxel faces use texture coordinates in voxel units, so the
+        // texture has to repeat across them.
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Nearest,
             min_filter: wgpu::FilterMode::Nearest,
@@ -89,6 +92,7 @@ impl Texture {
     pub fn create_depth_texture(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
         label: &str,
     ) -> Self {
         let size = wgpu::Extent3d {
@@ -101,7 +105,7 @@ impl Texture {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Self::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT // 3.
@@ -128,4 +132,62 @@ impl Texture {
             sampler,
         }
     }
+
+    // A color target in the surface's format that render passes draw into
+    // at `sample_count` and then resolve into the surface.
+    pub fn create_multisampled_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        label: &str,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    // Whether `device` can render to `format` at `sample_count`, resolving
+    // it if it's a color format. Without
+    // `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` only the format's
+    // guaranteed features can be used, whatever the adapter reports.
+    pub fn supports_sample_count(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> bool {
+        let features = if device
+            .features()
+            .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        {
+            adapter.get_texture_format_features(format)
+        } else {
+            format.describe().guaranteed_format_features
+        };
+        let is_depth = format.describe().sample_type == wgpu::TextureSampleType::Depth;
+        features.flags.sample_count_supported(sample_count)
+            && (sample_count == 1
+                || is_depth
+                || features
+                    .flags
+                    .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE))
+    }
 }
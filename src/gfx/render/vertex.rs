@@ -4,21 +4,80 @@ pub struct Vertex {
     position: [f32; 3],
     tex_coords: [f32; 2],
     tex_idx: u32,
+    normal: [f32; 3],
+    // Average of the normals of every face sharing this vertex, used for
+    // smooth shading.
+    smooth_normal: [f32; 3],
+    // Baked skylight and ambient occlusion, multiplied into the color.
+    light: f32,
 }
 
 impl Vertex {
-    pub fn new(pos: [i8; 3], tc: [i8; 2], tex_idx: u32) -> Self {
+    pub fn new(pos: [i8; 3], tc: [i8; 2], tex_idx: u32, n: [i8; 3]) -> Self {
+        let normal = [n[0] as f32, n[1] as f32, n[2] as f32];
         Vertex {
             position: [pos[0] as f32, pos[1] as f32, pos[2] as f32],
             tex_coords: [tc[0] as f32, tc[1] as f32],
             tex_idx,
+            normal,
+            smooth_normal: normal,
+            light: 1.0,
         }
     }
 
+    pub fn from_position(
+        position: [f32; 3],
+        normal: [f32; 3],
+        tex_coords: [f32; 2],
+        tex_idx: u32,
+    ) -> Self {
+        Vertex {
+            position,
+            tex_coords,
+            tex_idx,
+            normal,
+            smooth_normal: normal,
+            light: 1.0,
+        }
+    }
+
+    pub fn position(&self) -> [f32; 3] {
+        self.position
+    }
+
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        for (p, o) in self.position.iter_mut().zip(offset) {
+            *p += o;
+        }
+    }
+
+    // Scales the position about the origin, leaving the texture coordinates,
+    // so textures stretch with it.
+    pub fn scale(&mut self, factor: f32) {
+        for p in &mut self.position {
+            *p *= factor;
+        }
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        self.normal
+    }
+
+    pub fn light(&self) -> f32 {
+        self.light
+    }
+
+    pub fn set_light(&mut self, light: f32) {
+        self.light = light;
+    }
+
+    pub fn set_smooth_normal(&mut self, smooth_normal: [f32; 3]) {
+        self.smooth_normal = smooth_normal;
+    }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
-        const ATTRIBUTE_LAYOUT: &'static [wgpu::VertexAttribute] =
-            &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Sint32];
+        const ATTRIBUTE_LAYOUT: &[wgpu::VertexAttribute] = &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Sint32, 3 => Float32x3, 4 => Float32x3, 9 => Float32];
         wgpu::VertexBufferLayout {
             array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -30,35 +89,35 @@ impl Vertex {
 pub fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
     let vertices = [
         // front
-        Vertex::new([1, 1, 0], [1, 0], 0),
-        Vertex::new([1, -1, 0], [1, 1], 0),
-        Vertex::new([-1, 1, 0], [0, 0], 0),
-        Vertex::new([-1, -1, 0], [0, 1], 0),
+        Vertex::new([1, 1, 0], [1, 0], 0, [0, 0, 1]),
+        Vertex::new([1, -1, 0], [1, 1], 0, [0, 0, 1]),
+        Vertex::new([-1, 1, 0], [0, 0], 0, [0, 0, 1]),
+        Vertex::new([-1, -1, 0], [0, 1], 0, [0, 0, 1]),
         // back
-        Vertex::new([1, 1, -2], [1, 0], 0),
-        Vertex::new([1, -1, -2], [1, 1], 0),
-        Vertex::new([-1, 1, -2], [0, 0], 0),
-        Vertex::new([-1, -1, -2], [0, 1], 0),
+        Vertex::new([1, 1, -2], [1, 0], 0, [0, 0, -1]),
+        Vertex::new([1, -1, -2], [1, 1], 0, [0, 0, -1]),
+        Vertex::new([-1, 1, -2], [0, 0], 0, [0, 0, -1]),
+        Vertex::new([-1, -1, -2], [0, 1], 0, [0, 0, -1]),
         // right
-        Vertex::new([1, -1, 0], [0, 1], 0),
-        Vertex::new([1, 1, 0], [0, 0], 0),
-        Vertex::new([1, 1, -2], [1, 0], 0),
-        Vertex::new([1, -1, -2], [1, 1], 0),
+        Vertex::new([1, -1, 0], [0, 1], 0, [1, 0, 0]),
+        Vertex::new([1, 1, 0], [0, 0], 0, [1, 0, 0]),
+        Vertex::new([1, 1, -2], [1, 0], 0, [1, 0, 0]),
+        Vertex::new([1, -1, -2], [1, 1], 0, [1, 0, 0]),
         // left
-        Vertex::new([-1, 1, 0], [1, 0], 0),
-        Vertex::new([-1, -1, 0], [1, 1], 0),
-        Vertex::new([-1, 1, -2], [0, 0], 0),
-        Vertex::new([-1, -1, -2], [0, 1], 0),
+        Vertex::new([-1, 1, 0], [1, 0], 0, [-1, 0, 0]),
+        Vertex::new([-1, -1, 0], [1, 1], 0, [-1, 0, 0]),
+        Vertex::new([-1, 1, -2], [0, 0], 0, [-1, 0, 0]),
+        Vertex::new([-1, -1, -2], [0, 1], 0, [-1, 0, 0]),
         // top
-        Vertex::new([1, 1, 0], [1, 1], 1),
-        Vertex::new([-1, 1, 0], [0, 1], 1),
-        Vertex::new([1, 1, -2], [1, 0], 1),
-        Vertex::new([-1, 1, -2], [0, 0], 1),
+        Vertex::new([1, 1, 0], [1, 1], 1, [0, 1, 0]),
+        Vertex::new([-1, 1, 0], [0, 1], 1, [0, 1, 0]),
+        Vertex::new([1, 1, -2], [1, 0], 1, [0, 1, 0]),
+        Vertex::new([-1, 1, -2], [0, 0], 1, [0, 1, 0]),
         // bottom
-        Vertex::new([1, -1, 0], [1, 1], 2),
-        Vertex::new([-1, -1, 0], [0, 1], 2),
-        Vertex::new([1, -1, -2], [1, 0], 2),
-        Vertex::new([-1, -1, -2], [0, 0], 2),
+        Vertex::new([1, -1, 0], [1, 1], 2, [0, -1, 0]),
+        Vertex::new([-1, -1, 0], [0, 1], 2, [0, -1, 0]),
+        Vertex::new([1, -1, -2], [1, 0], 2, [0, -1, 0]),
+        Vertex::new([-1, -1, -2], [0, 0], 2, [0, -1, 0]),
     ];
 
     let indices: &[u16] = &[
@@ -0,0 +1,474 @@
+use instant::{Duration, Instant};
+use std::collections::{HashMap, HashSet};
+use winit::event::{ElementState, VirtualKeyCode};
+
+// Something a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    // Jumps while walking and rises while flying. Double tapping it toggles
+    // flying.
+    MoveUp,
+    MoveDown,
+    // Selects a hotbar slot, counting from 0.
+    SelectSlot(usize),
+    ToggleInventory,
+    ToggleChunkBorders,
+    ToggleHud,
+    ToggleFullscreen,
+    ToggleWireframe,
+    CyclePresentMode,
+    // Switches between editing single voxels and filling boxes.
+    CycleTool,
+    IncreaseRenderDistance,
+    DecreaseRenderDistance,
+    Screenshot,
+    Quit,
+}
+
+impl Action {
+    pub fn is_movement(self) -> bool {
+        matches!(
+            self,
+            Action::MoveForward
+                | Action::MoveBack
+                | Action::MoveLeft
+                | Action::MoveRight
+                | Action::MoveUp
+                | Action::MoveDown
+        )
+    }
+}
+
+// Which action each key triggers. Several keys can share an action, like
+// WASD and the arrow keys, but each key triggers at most one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBindings {
+    bindings: HashMap<VirtualKeyCode, Action>,
+}
+
+// The bindings `KeyBindings::default` starts with: WASD or the arrow keys
+// to move, number keys for the hotbar and Escape to quit.
+pub const DEFAULT_BINDINGS: [(VirtualKeyCode, Action); 32] = {
+    use VirtualKeyCode::*;
+    [
+        (W, Action::MoveForward),
+        (Up, Action::MoveForward),
+        (S, Action::MoveBack),
+        (Down, Action::MoveBack),
+        (A, Action::MoveLeft),
+        (Left, Action::MoveLeft),
+        (D, Action::MoveRight),
+        (Right, Action::MoveRight),
+        (Space, Action::MoveUp),
+        (LShift, Action::MoveDown),
+        (E, Action::ToggleInventory),
+        (F3, Action::ToggleHud),
+        (F7, Action::ToggleChunkBorders),
+        (F8, Action::ToggleWireframe),
+        (F9, Action::CyclePresentMode),
+        (F11, Action::ToggleFullscreen),
+        (F2, Action::Screenshot),
+        (B, Action::CycleTool),
+        (Equals, Action::IncreaseRenderDistance),
+        (NumpadAdd, Action::IncreaseRenderDistance),
+        (Minus, Action::DecreaseRenderDistance),
+        (NumpadSubtract, Action::DecreaseRenderDistance),
+        (Escape, Action::Quit),
+        (Key1, Action::SelectSlot(0)),
+        (Key2, Action::SelectSlot(1)),
+        (Key3, Action::SelectSlot(2)),
+        (Key4, Action::SelectSlot(3)),
+        (Key5, Action::SelectSlot(4)),
+        (Key6, Action::SelectSlot(5)),
+        (Key7, Action::SelectSlot(6)),
+        (Key8, Action::SelectSlot(7)),
+        (Key9, Action::SelectSlot(8)),
+    ]
+};
+
+// What `key` does with the default bindings.
+pub fn default_action(key: VirtualKeyCode) -> Option<Action> {
+    DEFAULT_BINDINGS
+        .iter()
+        .find(|&&(k, _)| k == key)
+        .map(|&(_, action)| action)
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS.into_iter().collect(),
+        }
+    }
+}
+
+impl KeyBindings {
+    // Bindings with no keys bound at all.
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    // Binds `key` to `action`, replacing whatever it was bound to before.
+    pub fn bind(&mut self, key: VirtualKeyCode, action: Action) -> Option<Action> {
+        self.bindings.insert(key, action)
+    }
+
+    pub fn unbind(&mut self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.remove(&key)
+    }
+
+    pub fn action(&self, key: VirtualKeyCode) -> Option<Action> {
+        self.bindings.get(&key).copied()
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = VirtualKeyCode> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, &a)| a == action)
+            .map(|(&key, _)| key)
+    }
+
+    // Whether any key bound to `action` is held down.
+    pub fn is_held(&self, input: &InputState, action: Action) -> bool {
+        self.keys(action).any(|key| input.is_held(key))
+    }
+}
+
+// Keyboard state tracked across events. winit reports held keys as repeated
+// presses, so a press only counts as an edge if the key wasn't already held.
+#[derive(Debug, Default, Clone)]
+pub struct InputState {
+    held: HashSet<VirtualKeyCode>,
+    just_pressed: HashSet<VirtualKeyCode>,
+    just_released: HashSet<VirtualKeyCode>,
+}
+
+impl InputState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Records a key event and returns whether it was a fresh press rather
+    // than a release or a repeat.
+    pub fn process(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+        match state {
+            ElementState::Pressed => {
+                let pressed = self.held.insert(key);
+                if pressed {
+                    self.just_pressed.insert(key);
+                }
+                pressed
+            }
+            ElementState::Released => {
+                if self.held.remove(&key) {
+                    self.just_released.insert(key);
+                }
+                false
+            }
+        }
+    }
+
+    pub fn is_held(&self, key: VirtualKeyCode) -> bool {
+        self.held.contains(&key)
+    }
+
+    // Whether `key` went down since the last `end_frame`.
+    pub fn just_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.just_pressed.contains(&key)
+    }
+
+    pub fn just_released(&self, key: VirtualKeyCode) -> bool {
+        self.just_released.contains(&key)
+    }
+
+    // Clears the per-frame edges. Held keys stay held.
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    // Releases everything, e.g. when the window loses focus and the
+    // release events would never arrive.
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.end_frame();
+    }
+}
+
+pub const DEFAULT_DOUBLE_TAP_WINDOW: Duration = Duration::from_millis(300);
+
+// Detects two presses of the same key in quick succession.
+#[derive(Debug, Clone)]
+pub struct DoubleTap {
+    pub window: Duration,
+    last_press: Option<Instant>,
+}
+
+impl Default for DoubleTap {
+    fn default() -> Self {
+        Self::new(DEFAULT_DOUBLE_TAP_WINDOW)
+    }
+}
+
+impl DoubleTap {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_press: None,
+        }
+    }
+
+    // Records a press at `now` and returns whether it completes a double tap.
+    // The press after a double tap starts a new one.
+    pub fn press(&mut self, now: Instant) -> bool {
+        match self.last_press.take() {
+            Some(last) if now.duration_since(last) <= self.window => true,
+            _ => {
+                self.last_press = Some(now);
+                false
+            }
+        }
+    }
+}
+
+// Controller buttons, named by where they sit rather than what they're
+// labelled, so bindings mean the same thing across controller layouts. They
+// mirror gilrs' buttons, which `gamepad::gamepad_button` maps across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    LeftTrigger,
+    RightBumper,
+    RightTrigger,
+    Select,
+    Start,
+    LeftThumb,
+    RightThumb,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+}
+
+// Which action each controller button triggers, like `KeyBindings` for
+// keys. The sticks aren't bound: the left one always moves and the right
+// one always looks around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GamepadBindings {
+    bindings: HashMap<GamepadButton, Action>,
+}
+
+// The bindings `GamepadBindings::default` starts with: the south button to
+// jump, clicking the right stick to crouch and north for the inventory.
+pub const DEFAULT_GAMEPAD_BINDINGS: [(GamepadButton, Action); 4] = [
+    (GamepadButton::South, Action::MoveUp),
+    (GamepadButton::RightThumb, Action::MoveDown),
+    (GamepadButton::North, Action::ToggleInventory),
+    (GamepadButton::Select, Action::ToggleChunkBorders),
+];
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_GAMEPAD_BINDINGS.into_iter().collect(),
+        }
+    }
+}
+
+impl GamepadBindings {
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    pub fn bind(&mut self, button: GamepadButton, action: Action) -> Option<Action> {
+        self.bindings.insert(button, action)
+    }
+
+    pub fn unbind(&mut self, button: GamepadButton) -> Option<Action> {
+        self.bindings.remove(&button)
+    }
+
+    pub fn action(&self, button: GamepadButton) -> Option<Action> {
+        self.bindings.get(&button).copied()
+    }
+
+    pub fn buttons(&self, action: Action) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, &a)| a == action)
+            .map(|(&button, _)| button)
+    }
+
+    // Whether any button bound to `action` is held on any controller.
+    pub fn is_held(&self, gamepads: &GamepadState, action: Action) -> bool {
+        self.buttons(action).any(|button| gamepads.is_held(button))
+    }
+}
+
+// Stick deflection below which the stick counts as centered, since worn
+// sticks rarely rest exactly at zero.
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+#[derive(Debug, Default, Clone)]
+struct Pad {
+    axes: HashMap<GamepadAxis, f32>,
+    held: HashSet<GamepadButton>,
+}
+
+// The state of every connected controller, fed from a gamepad backend's
+// events. Controllers are told apart by whatever id the backend gives them,
+// and all of them control the same player.
+#[derive(Debug, Clone)]
+pub struct GamepadState {
+    pads: HashMap<usize, Pad>,
+    pub deadzone: f32,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self {
+            pads: HashMap::new(),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl GamepadState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        !self.pads.is_empty()
+    }
+
+    pub fn connect(&mut self, id: usize) {
+        self.pads.entry(id).or_default();
+    }
+
+    // Forgets the controller, returning the buttons it was holding. They're
+    // released as far as `is_held` is concerned, but anything that reacted
+    // to them being pressed needs to hear that they were let go.
+    pub fn disconnect(&mut self, id: usize) -> Vec<GamepadButton> {
+        self.pads
+            .remove(&id)
+            .map_or_else(Vec::new, |pad| pad.held.into_iter().collect())
+    }
+
+    // Records a button event and returns whether it was a fresh press.
+    // Events from controllers that weren't connected connect them first,
+    // in case the connect event was missed.
+    pub fn process_button(&mut self, id: usize, button: GamepadButton, pressed: bool) -> bool {
+        let pad = self.pads.entry(id).or_default();
+        if pressed {
+            pad.held.insert(button)
+        } else {
+            pad.held.remove(&button);
+            false
+        }
+    }
+
+    // `value` runs from -1 to 1, with up and right positive.
+    pub fn process_axis(&mut self, id: usize, axis: GamepadAxis, value: f32) {
+        let pad = self.pads.entry(id).or_default();
+        pad.axes.insert(axis, value.clamp(-1.0, 1.0));
+    }
+
+    pub fn is_held(&self, button: GamepadButton) -> bool {
+        self.pads.values().any(|pad| pad.held.contains(&button))
+    }
+
+    // The stick made of axes `x` and `y`, summed across controllers and
+    // limited to the unit circle. Within the deadzone it's zero, and past it
+    // the deflection is rescaled to start from zero, so there's no jump.
+    pub fn stick(&self, x: GamepadAxis, y: GamepadAxis) -> [f32; 2] {
+        let axis = |axis| -> f32 {
+            self.pads
+                .values()
+                .map(|pad| pad.axes.get(&axis).copied().unwrap_or(0.0))
+                .sum()
+        };
+        let (x, y) = (axis(x), axis(y));
+        let length = (x * x + y * y).sqrt();
+        if length <= self.deadzone {
+            return [0.0, 0.0];
+        }
+        let scaled = ((length - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        [x / length * scaled, y / length * scaled]
+    }
+
+    pub fn left_stick(&self) -> [f32; 2] {
+        self.stick(GamepadAxis::LeftStickX, GamepadAxis::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> [f32; 2] {
+        self.stick(GamepadAxis::RightStickX, GamepadAxis::RightStickY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_repeats_stay_held_without_pressing_again() {
+        let mut input = InputState::new();
+        let key = VirtualKeyCode::W;
+        let mut presses = 0;
+
+        presses += input.process(key, ElementState::Pressed) as usize;
+        assert!(input.just_pressed(key));
+        input.end_frame();
+        // winit repeats a held key as more presses.
+        for _ in 0..5 {
+            presses += input.process(key, ElementState::Pressed) as usize;
+            assert!(input.is_held(key));
+            assert!(!input.just_pressed(key));
+            input.end_frame();
+        }
+        assert_eq!(presses, 1);
+
+        assert!(!input.process(key, ElementState::Released));
+        assert!(!input.is_held(key));
+        assert!(input.just_released(key));
+        input.end_frame();
+        assert!(!input.just_released(key));
+    }
+
+    #[test]
+    fn double_tap_needs_both_presses_inside_the_window() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut tap = DoubleTap::new(ms(300));
+        assert!(!tap.press(start));
+        assert!(tap.press(start + ms(200)));
+        // The next press starts over rather than toggling again.
+        assert!(!tap.press(start + ms(250)));
+
+        // Presses further apart are separate taps, i.e. two jumps.
+        let mut tap = DoubleTap::new(ms(300));
+        assert!(!tap.press(start));
+        assert!(!tap.press(start + ms(400)));
+        assert!(!tap.press(start + ms(800)));
+    }
+}
@@ -0,0 +1,264 @@
+pub mod bench;
+pub mod ecs;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod gfx;
+pub mod input;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod workers;
+
+use crate::{
+    bench::{BenchConfig, BenchScene},
+    gfx::render::{
+        config::RendererConfig,
+        renderer::{NewDevice, Renderer},
+    },
+};
+use std::sync::{mpsc, Arc};
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{CursorGrabMode, Fullscreen, Window},
+};
+
+// Grabs or releases the cursor. Grabbing prefers confining the cursor to the
+// window and falls back to locking it in place, which is all some platforms
+// (e.g. many Wayland compositors) support. If neither works the cursor is
+// left visible and free. Returns whether it ended up grabbed.
+fn set_cursor_captured(window: &Window, captured: bool) -> bool {
+    let grabbed = captured
+        && [CursorGrabMode::Confined, CursorGrabMode::Locked]
+            .into_iter()
+            .any(|mode| match window.set_cursor_grab(mode) {
+                Ok(()) => true,
+                Err(err) => {
+                    log::warn!("failed to grab cursor with {:?}: {}", mode, err);
+                    false
+                }
+            });
+    if !grabbed {
+        if let Err(err) = window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("failed to release cursor: {}", err);
+        }
+    }
+    window.set_cursor_visible(!grabbed);
+    grabbed
+}
+
+// Starts requesting a device to replace the lost one without blocking the
+// event loop, which the web doesn't allow. It arrives on the returned
+// channel.
+fn request_new_device(renderer: &Renderer) -> mpsc::Receiver<anyhow::Result<NewDevice>> {
+    let (sender, receiver) = mpsc::channel();
+    let request = renderer.request_new_device();
+    let send = async move {
+        sender.send(request.await).ok();
+    };
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            wasm_bindgen_futures::spawn_local(send);
+        } else {
+            std::thread::spawn(move || pollster::block_on(send));
+        }
+    }
+    receiver
+}
+
+pub async fn run() {
+    run_with_config(RendererConfig::from_env()).await
+}
+
+// Like `run`, with e.g. custom key bindings.
+pub async fn run_with_config(renderer_config: RendererConfig) {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+            console_log::init_with_level(log::Level::Info).expect("Couldn't initialize logger");
+        } else {
+            env_logger::init();
+        }
+    }
+
+    let bench_config = match BenchConfig::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => {
+            log::error!("{}", err);
+            return;
+        }
+    };
+    // Offscreen runs don't need a window at all.
+    if let Some(config) = bench_config.as_ref().filter(|config| config.offscreen) {
+        match bench::run_offscreen(config.clone(), renderer_config).await {
+            Ok(report) => println!("{}", report),
+            Err(err) => log::error!("bench failed: {}", err),
+        }
+        return;
+    }
+    let mut bench = bench_config.map(BenchScene::new);
+
+    let event_loop = EventLoop::new();
+    let title = env!("CARGO_PKG_NAME");
+    let window = winit::window::WindowBuilder::new()
+        .with_title(title)
+        .with_inner_size(winit::dpi::LogicalSize::new(800, 450))
+        .build(&event_loop)
+        .map(Arc::new)
+        .unwrap();
+
+    let mut render_state = Renderer::with_config(Arc::clone(&window), renderer_config).await; // NEW!
+    if bench.is_some() {
+        render_state.set_render_distance(bench::RENDER_DISTANCE);
+        bench::populate(&mut render_state.world);
+    }
+    // The cursor is released while the window is unfocused and grabbed
+    // again, if it still should be, when it's back.
+    let mut focused = true;
+    let mut cursor_captured = render_state.wants_cursor_capture();
+    set_cursor_captured(&window, cursor_captured);
+    #[cfg(feature = "gamepad")]
+    let mut gamepads = gamepad::GamepadBackend::new();
+    #[cfg(feature = "gamepad")]
+    if let Some(gamepads) = &gamepads {
+        gamepads.connect_existing(&mut render_state);
+    }
+    let mut last_render_time = instant::Instant::now();
+    let mut last_title_update = last_render_time;
+    // Set while a new device is on its way after the old one was lost.
+    let mut device_request: Option<mpsc::Receiver<anyhow::Result<NewDevice>>> = None;
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+        // Opening or closing a UI panel releases or recaptures the cursor.
+        let wants_capture = focused && render_state.wants_cursor_capture();
+        if wants_capture != cursor_captured {
+            cursor_captured = wants_capture;
+            set_cursor_captured(&window, cursor_captured);
+        }
+        if render_state.take_fullscreen_request() {
+            let fullscreen = match window.fullscreen() {
+                Some(_) => None,
+                None => Some(Fullscreen::Borderless(None)),
+            };
+            window.set_fullscreen(fullscreen);
+            // Some platforms drop the grab when the window changes mode. The
+            // new size arrives as a resize.
+            set_cursor_captured(&window, cursor_captured);
+        }
+        #[cfg(feature = "gamepad")]
+        if let (Event::MainEventsCleared, Some(gamepads)) = (&event, gamepads.as_mut()) {
+            gamepads.poll(&mut render_state);
+        }
+        match event {
+            // The bench scene drives frames itself so it also runs when the
+            // window is hidden.
+            Event::MainEventsCleared if bench.is_some() => {
+                let scene = bench.as_mut().unwrap();
+                if let Err(err) = scene.frame(&mut render_state) {
+                    log::error!("bench frame failed: {}", err);
+                    *control_flow = ControlFlow::Exit;
+                } else if scene.is_done() {
+                    println!("{}", scene.report());
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::MainEventsCleared => window.request_redraw(),
+            // NEW!
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, },
+                .. // We're not using device_id currently
+            } if cursor_captured => {
+                render_state.camera_controller.process_mouse(delta.0, delta.1)
+            }
+            // UPDATED!
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !render_state.input(event) => {
+                match event {
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    #[cfg(not(target_arch="wasm32"))]
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(key),
+                                ..
+                            },
+                        ..
+                    } if render_state.key_bindings.action(*key) == Some(input::Action::Quit) => {
+                        *control_flow = ControlFlow::Exit
+                    }
+                    WindowEvent::Focused(is_focused) => focused = *is_focused,
+                    WindowEvent::Resized(physical_size) => {
+                        render_state.resize(*physical_size);
+                        // Confining is to the window's old bounds on some
+                        // platforms, so grab again at the new size.
+                        if cursor_captured {
+                            set_cursor_captured(&window, true);
+                        }
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        render_state.resize(**new_inner_size);
+                    }
+                    _ => {}
+                }
+            }
+            Event::RedrawRequested(window_id) if window_id == window.id() && bench.is_none() => {
+                // Nothing is drawn until the new device arrives.
+                if let Some(request) = &device_request {
+                    let replaced = match request.try_recv() {
+                        Err(mpsc::TryRecvError::Empty) => return,
+                        Ok(new_device) => new_device
+                            .and_then(|new_device| render_state.replace_device(new_device)),
+                        Err(mpsc::TryRecvError::Disconnected) => {
+                            Err(anyhow::anyhow!("the device request was dropped"))
+                        }
+                    };
+                    device_request = None;
+                    if let Err(err) = replaced {
+                        log::error!("failed to recreate the GPU device: {}", err);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    // The wait isn't one long frame.
+                    last_render_time = instant::Instant::now();
+                }
+                // Not every platform reports a resize after switching in
+                // and out of fullscreen.
+                let size = window.inner_size();
+                if size != render_state.size {
+                    render_state.resize(size);
+                }
+                let now = instant::Instant::now();
+                let dt = now - last_render_time;
+                last_render_time = now;
+                let selected = render_state.world.inventory.selected();
+                render_state.set_held_item(selected.map(|stack| stack.voxel_type));
+                render_state.update(dt);
+                if now - last_title_update >= instant::Duration::from_secs(1) {
+                    last_title_update = now;
+                    window.set_title(&format!(
+                        "{} - {:.0} fps ({:.1} ms)",
+                        title,
+                        render_state.fps(),
+                        render_state.frame_time_ms()
+                    ));
+                }
+                match render_state.render() {
+                    Ok(_) => {}
+                    // Start over on a new device if the old one is gone
+                    Err(wgpu::SurfaceError::Lost) if render_state.is_device_lost() => {
+                        device_request = Some(request_new_device(&render_state));
+                    }
+                    // Reconfigure the surface if it's lost or outdated
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => render_state.resize(render_state.size),
+                    // The system is out of memory, we should probably quit
+                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    // We're ignoring timeouts
+                    Err(wgpu::SurfaceError::Timeout) => log::warn!("Surface timeout"),
+                }
+            }
+            _ => {}
+        }
+    });
+}
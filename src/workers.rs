@@ -0,0 +1,78 @@
+// The worker threads shared by everything done off the main thread, like
+// generating terrain and meshing chunks. Tasks are run in the order they're
+// queued and send their own results back, usually over a channel owned by
+// whoever queued them.
+
+use std::sync::{
+    mpsc::{self, Sender},
+    Arc, Mutex, OnceLock,
+};
+
+type Task = Box<dyn FnOnce() + Send>;
+
+static POOL: OnceLock<Sender<Task>> = OnceLock::new();
+
+// Queues `task` on the pool, starting its threads the first time. If every
+// thread has stopped, e.g. from tasks panicking, the task is run right away
+// on the calling thread instead, so it still sends its results.
+pub fn spawn(task: impl FnOnce() + Send + 'static) {
+    let tasks = POOL.get_or_init(start);
+    if let Err(mpsc::SendError(task)) = tasks.send(Box::new(task)) {
+        log::warn!("worker threads stopped, running on the main thread");
+        task();
+    }
+}
+
+// Leaves a core for the main thread, and caps the pool since neither terrain
+// generation nor meshing gets much faster past a few threads.
+fn start() -> Sender<Task> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get().saturating_sub(1))
+        .clamp(1, 4);
+    let (tasks, task_receiver) = mpsc::channel::<Task>();
+    let task_receiver = Arc::new(Mutex::new(task_receiver));
+    for _ in 0..threads {
+        let task_receiver = Arc::clone(&task_receiver);
+        std::thread::spawn(move || loop {
+            // The lock is only held while waiting for a task, so the other
+            // threads can pick up the next one while this one runs.
+            let task = match task_receiver.lock() {
+                Ok(receiver) => receiver.recv(),
+                Err(_) => break,
+            };
+            let Ok(task) = task else {
+                break;
+            };
+            task();
+        });
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_run_off_the_calling_thread() {
+        let (sender, results) = mpsc::channel();
+        for i in 0..16 {
+            let sender = sender.clone();
+            spawn(move || {
+                sender.send((i, std::thread::current().id())).ok();
+            });
+        }
+        let mut results = (0..16)
+            .map(|_| {
+                results
+                    .recv_timeout(std::time::Duration::from_secs(10))
+                    .expect("task never ran")
+            })
+            .collect::<Vec<_>>();
+        assert!(results
+            .iter()
+            .all(|&(_, thread)| thread != std::thread::current().id()));
+        results.sort_by_key(|&(i, _)| i);
+        assert!(results.iter().map(|&(i, _)| i).eq(0..16));
+    }
+}